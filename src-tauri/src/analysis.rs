@@ -0,0 +1,648 @@
+use lofty::{ItemKey, Tag, TagExt, TaggedFileExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::f32::consts::PI;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
+use tauri::Emitter;
+
+use super::{audio_files, get_file_stamp, path_policy, Track};
+use crate::decode;
+use crate::error::CommandError;
+use crate::store;
+use crate::tags::read_tagged_file;
+
+const ANALYSIS_CACHE_FILE: &str = "analysis-cache.json";
+const ANALYSIS_RATE: u32 = 11025;
+const MAX_ANALYSIS_SECONDS: u32 = 600;
+const MIN_ANALYSIS_SECONDS: f64 = 10.0;
+
+const ONSET_WINDOW: usize = 1024;
+const ONSET_HOP: usize = 128;
+const MIN_BPM: f64 = 60.0;
+const MAX_BPM: f64 = 200.0;
+// Autocorrelation peaks are weighted towards this tempo to settle half/double-time ambiguity.
+const PREFERRED_BPM: f64 = 120.0;
+
+const CHROMA_WINDOW: usize = 4096;
+const CHROMA_HOP: usize = 2048;
+const MIN_PITCH_HZ: f64 = 80.0;
+const MAX_PITCH_HZ: f64 = 2000.0;
+
+// Krumhansl-Kessler key profiles, starting at the tonic.
+const MAJOR_PROFILE: [f64; 12] = [6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88];
+const MINOR_PROFILE: [f64; 12] = [6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17];
+const MAJOR_NAMES: [&str; 12] = ["C", "Db", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B"];
+const MINOR_NAMES: [&str; 12] = [
+    "Cm", "C#m", "Dm", "Ebm", "Em", "Fm", "F#m", "Gm", "G#m", "Am", "Bbm", "Bm",
+];
+
+static ANALYSIS_RUNNING: AtomicBool = AtomicBool::new(false);
+static ANALYSIS_CANCELLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct MusicalKey {
+    tonic: u8,
+    minor: bool,
+}
+
+impl MusicalKey {
+    // Accepts "Am", "F#", "Bb minor", "C maj", Camelot ("8A") and Open Key ("1d") notation.
+    pub(crate) fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let digits = text.chars().take_while(char::is_ascii_digit).count();
+        if digits > 0 {
+            let number: u8 = text[..digits].parse().ok().filter(|n| (1..=12).contains(n))?;
+            let (number, minor) = match text[digits..].trim().to_ascii_lowercase().as_str() {
+                "a" => (number, true),
+                "b" => (number, false),
+                "m" => ((number + 6) % 12 + 1, true),
+                "d" => ((number + 6) % 12 + 1, false),
+                _ => return None,
+            };
+            return Some(Self::from_camelot(number, minor));
+        }
+
+        let mut chars = text.chars();
+        let mut tonic: i32 = match chars.next()?.to_ascii_uppercase() {
+            'C' => 0,
+            'D' => 2,
+            'E' => 4,
+            'F' => 5,
+            'G' => 7,
+            'A' => 9,
+            'B' => 11,
+            _ => return None,
+        };
+        let mut rest = chars.as_str();
+        loop {
+            let mut chars = rest.chars();
+            match chars.next() {
+                Some('#' | '♯') => tonic += 1,
+                Some('b' | '♭') => tonic -= 1,
+                _ => break,
+            }
+            rest = chars.as_str();
+        }
+        let minor = match rest.trim().to_lowercase().as_str() {
+            "" | "maj" | "major" => false,
+            "m" | "min" | "minor" => true,
+            _ => return None,
+        };
+        Some(MusicalKey {
+            tonic: tonic.rem_euclid(12) as u8,
+            minor,
+        })
+    }
+
+    fn from_camelot(number: u8, minor: bool) -> Self {
+        let major_tonic = ((number + 4) % 12 * 7) % 12;
+        MusicalKey {
+            tonic: if minor { (major_tonic + 9) % 12 } else { major_tonic },
+            minor,
+        }
+    }
+
+    fn camelot_number(self) -> u8 {
+        let major_tonic = if self.minor { (self.tonic + 3) % 12 } else { self.tonic };
+        (major_tonic * 7 % 12 + 7) % 12 + 1
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        let names = if self.minor { &MINOR_NAMES } else { &MAJOR_NAMES };
+        names[self.tonic as usize]
+    }
+
+    pub(crate) fn camelot(self) -> String {
+        format!("{}{}", self.camelot_number(), if self.minor { 'A' } else { 'B' })
+    }
+
+    // Sorts around the Camelot wheel: 1A, 1B, 2A, …
+    pub(crate) fn order(self) -> u64 {
+        u64::from(self.camelot_number()) * 2 + u64::from(!self.minor)
+    }
+
+    // Same key, its relative major/minor, or one step around the Camelot wheel.
+    pub(crate) fn is_compatible(self, other: MusicalKey) -> bool {
+        let (a, b) = (self.camelot_number(), other.camelot_number());
+        if self.minor != other.minor {
+            return a == b;
+        }
+        a == b || a % 12 + 1 == b || b % 12 + 1 == a
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct AnalysisResult {
+    bpm: Option<f32>,
+    key: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct CachedAnalysis {
+    modified: u64,
+    size: u64,
+    result: AnalysisResult,
+}
+
+#[derive(Default)]
+struct Analysis {
+    results: HashMap<String, CachedAnalysis>,
+    revision: u64,
+}
+
+static ANALYSIS: LazyLock<Mutex<Analysis>> = LazyLock::new(|| Mutex::new(Analysis::default()));
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct AnalysisProgress {
+    done: usize,
+    total: usize,
+    current: String,
+    analyzed: usize,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalysisFailure {
+    path: String,
+    error: CommandError,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalysisReport {
+    root: String,
+    total: usize,
+    tagged: usize,
+    analyzed: usize,
+    cached: usize,
+    written: usize,
+    cancelled: bool,
+    failed: Vec<AnalysisFailure>,
+}
+
+pub(crate) fn init(app: &tauri::AppHandle) {
+    let results: HashMap<String, CachedAnalysis> = match store::load(app, ANALYSIS_CACHE_FILE) {
+        Ok(results) => results,
+        Err(e) => {
+            log::warn!(target: "analysis", "failed to load analysis cache: {}", e);
+            HashMap::new()
+        }
+    };
+    if let Ok(mut state) = ANALYSIS.lock() {
+        state.results = results;
+        state.revision += 1;
+    }
+}
+
+pub(crate) fn revision() -> u64 {
+    ANALYSIS.lock().map(|a| a.revision).unwrap_or(0)
+}
+
+pub(crate) fn bpm_from_tag(tag: &Tag) -> Option<f32> {
+    tag.get_string(&ItemKey::Bpm)
+        .and_then(|bpm| bpm.trim().parse::<f32>().ok())
+        .filter(|bpm| *bpm > 0.0 && *bpm < 1000.0)
+}
+
+pub(crate) fn key_from_tag(tag: &Tag) -> Option<&str> {
+    tag.get_string(&ItemKey::InitialKey)
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+}
+
+// Stores a key in its usual spelling plus Camelot code; unrecognised text is kept as-is.
+pub(crate) fn set_key(track: &mut Track, text: &str) {
+    match MusicalKey::parse(text) {
+        Some(key) => {
+            track.key = Some(key.name().to_string());
+            track.camelot = Some(key.camelot());
+        }
+        None => {
+            track.key = Some(text.to_string());
+            track.camelot = None;
+        }
+    }
+}
+
+pub(crate) fn track_key(track: &Track) -> Option<MusicalKey> {
+    track
+        .camelot
+        .as_deref()
+        .or(track.key.as_deref())
+        .and_then(MusicalKey::parse)
+}
+
+// Fills BPM and key from earlier analysis for local tracks whose tags lack them.
+pub(crate) fn merge_analysis(tracks: &mut [Track]) {
+    let Ok(state) = ANALYSIS.lock() else {
+        return;
+    };
+    for track in tracks.iter_mut().filter(|t| t.source == "local") {
+        let Some(cached) = state.results.get(&track.audio_url) else {
+            continue;
+        };
+        if track.bpm.is_none() {
+            track.bpm = cached.result.bpm;
+        }
+        if track.key.is_none() {
+            if let Some(key) = &cached.result.key {
+                set_key(track, key);
+            }
+        }
+    }
+}
+
+fn hann(size: usize) -> Vec<f32> {
+    (0..size)
+        .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / size as f32).cos())
+        .collect()
+}
+
+// In-place radix-2 FFT; the length must be a power of two.
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+fn magnitudes(frame: &[f32], window: &[f32]) -> Vec<f32> {
+    let mut re: Vec<f32> = frame.iter().zip(window).map(|(s, w)| s * w).collect();
+    let mut im = vec![0.0; re.len()];
+    fft(&mut re, &mut im);
+    re.iter()
+        .zip(&im)
+        .take(re.len() / 2)
+        .map(|(r, i)| (r * r + i * i).sqrt())
+        .collect()
+}
+
+fn frames<'a>(samples: &'a [f32], size: usize, hop: usize) -> impl Iterator<Item = &'a [f32]> {
+    (0..samples.len().saturating_sub(size))
+        .step_by(hop)
+        .map(move |start| &samples[start..start + size])
+}
+
+// Spectral flux onset envelope, autocorrelated over the 60–200 BPM range.
+fn estimate_bpm(samples: &[f32], rate: u32) -> Option<f32> {
+    let window = hann(ONSET_WINDOW);
+    let mut previous: Vec<f32> = Vec::new();
+    let mut envelope: Vec<f64> = Vec::new();
+    for frame in frames(samples, ONSET_WINDOW, ONSET_HOP) {
+        let spectrum: Vec<f32> = magnitudes(frame, &window)
+            .into_iter()
+            .map(|m| (1.0 + 100.0 * m).ln())
+            .collect();
+        let flux: f32 = spectrum.iter().zip(&previous).map(|(a, b)| (a - b).max(0.0)).sum();
+        envelope.push(f64::from(flux));
+        previous = spectrum;
+    }
+
+    let fps = f64::from(rate) / ONSET_HOP as f64;
+    if (envelope.len() as f64) < fps * MIN_ANALYSIS_SECONDS {
+        return None;
+    }
+    // Remove the local average so sustained loudness does not count as rhythm.
+    let half = (fps / 4.0) as usize;
+    let mut prefix = vec![0.0; envelope.len() + 1];
+    for (i, v) in envelope.iter().enumerate() {
+        prefix[i + 1] = prefix[i] + v;
+    }
+    let onsets: Vec<f64> = (0..envelope.len())
+        .map(|i| {
+            let (lo, hi) = (i.saturating_sub(half), (i + half + 1).min(envelope.len()));
+            (envelope[i] - (prefix[hi] - prefix[lo]) / (hi - lo) as f64).max(0.0)
+        })
+        .collect();
+
+    let min_lag = (60.0 * fps / MAX_BPM).floor() as usize;
+    let max_lag = (60.0 * fps / MIN_BPM).ceil() as usize;
+    let autocorrelation = |lag: usize| -> f64 {
+        let n = onsets.len() - lag;
+        onsets[..n].iter().zip(&onsets[lag..]).map(|(a, b)| a * b).sum::<f64>() / n as f64
+    };
+    let correlations: Vec<f64> = (min_lag - 1..=max_lag + 1).map(autocorrelation).collect();
+    let weight = |lag: usize| {
+        let octaves = (60.0 * fps / lag as f64 / PREFERRED_BPM).log2();
+        (-0.5 * (octaves / 0.9).powi(2)).exp()
+    };
+    let best = (1..correlations.len() - 1)
+        .max_by(|a, b| {
+            let score = |i: usize| correlations[i] * weight(min_lag - 1 + i);
+            score(*a).total_cmp(&score(*b))
+        })
+        .filter(|i| correlations[*i] > 0.0)?;
+
+    let (left, center, right) = (correlations[best - 1], correlations[best], correlations[best + 1]);
+    let denominator = left - 2.0 * center + right;
+    let offset = if denominator.abs() > f64::EPSILON {
+        (0.5 * (left - right) / denominator).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+    let lag = (min_lag - 1 + best) as f64 + offset;
+    Some(((60.0 * fps / lag) * 10.0).round() as f32 / 10.0)
+}
+
+fn correlation(a: &[f64; 12], b: &[f64; 12]) -> f64 {
+    let mean = |v: &[f64; 12]| v.iter().sum::<f64>() / 12.0;
+    let (mean_a, mean_b) = (mean(a), mean(b));
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for i in 0..12 {
+        let (da, db) = (a[i] - mean_a, b[i] - mean_b);
+        cov += da * db;
+        var_a += da * da;
+        var_b += db * db;
+    }
+    if var_a == 0.0 || var_b == 0.0 {
+        0.0
+    } else {
+        cov / (var_a * var_b).sqrt()
+    }
+}
+
+// Averages a chromagram over the track and matches it against rotated key profiles.
+fn estimate_key(samples: &[f32], rate: u32) -> Option<MusicalKey> {
+    let window = hann(CHROMA_WINDOW);
+    let bin_hz = f64::from(rate) / CHROMA_WINDOW as f64;
+    let classes: Vec<Option<usize>> = (0..CHROMA_WINDOW / 2)
+        .map(|bin| {
+            let hz = bin as f64 * bin_hz;
+            (MIN_PITCH_HZ..=MAX_PITCH_HZ)
+                .contains(&hz)
+                .then(|| ((12.0 * (hz / 440.0).log2()).round() as i64 + 69).rem_euclid(12) as usize)
+        })
+        .collect();
+
+    let mut chroma = [0.0f64; 12];
+    for frame in frames(samples, CHROMA_WINDOW, CHROMA_HOP) {
+        let mut frame_chroma = [0.0f64; 12];
+        for (magnitude, class) in magnitudes(frame, &window).into_iter().zip(&classes) {
+            if let Some(class) = class {
+                frame_chroma[*class] += f64::from(magnitude);
+            }
+        }
+        let peak = frame_chroma.iter().copied().fold(0.0, f64::max);
+        if peak > 0.0 {
+            for (total, value) in chroma.iter_mut().zip(frame_chroma) {
+                *total += value / peak;
+            }
+        }
+    }
+    if chroma.iter().all(|c| *c == 0.0) {
+        return None;
+    }
+
+    let mut best: Option<(f64, MusicalKey)> = None;
+    for tonic in 0..12u8 {
+        for (profile, minor) in [(&MAJOR_PROFILE, false), (&MINOR_PROFILE, true)] {
+            let rotated: [f64; 12] = std::array::from_fn(|i| profile[(i + 12 - tonic as usize) % 12]);
+            let score = correlation(&chroma, &rotated);
+            if best.is_none_or(|(s, _)| score > s) {
+                best = Some((score, MusicalKey { tonic, minor }));
+            }
+        }
+    }
+    best.map(|(_, key)| key)
+}
+
+fn analyze_file(path: &Path) -> Result<AnalysisResult, CommandError> {
+    let (samples, rate) = decode::mono_samples(path, ANALYSIS_RATE, MAX_ANALYSIS_SECONDS)?;
+    if rate == 0 || (samples.len() as f64) < f64::from(rate) * MIN_ANALYSIS_SECONDS {
+        return Err(CommandError::invalid_input("Track is too short to analyze"));
+    }
+    Ok(AnalysisResult {
+        bpm: estimate_bpm(&samples, rate),
+        key: estimate_key(&samples, rate).map(|k| k.name().to_string()),
+    })
+}
+
+// Writes analysed values into tags that are still empty. Returns whether the file changed.
+fn write_missing_tags(path: &Path, result: &AnalysisResult) -> Result<bool, CommandError> {
+    let mut tagged_file = read_tagged_file(path)?;
+    if tagged_file.primary_tag().is_none() {
+        let tag_type = tagged_file.primary_tag_type();
+        tagged_file.insert_tag(Tag::new(tag_type));
+    }
+    let tag = tagged_file
+        .primary_tag_mut()
+        .ok_or_else(|| CommandError::invalid_input("File does not support tags"))?;
+    let mut changed = false;
+    if let (None, Some(bpm)) = (bpm_from_tag(tag), result.bpm) {
+        changed |= tag.insert_text(ItemKey::Bpm, format!("{}", bpm.round() as u32));
+    }
+    if let (None, Some(key)) = (key_from_tag(tag), &result.key) {
+        changed |= tag.insert_text(ItemKey::InitialKey, key.clone());
+    }
+    if changed {
+        tag.save_to_path(path)?;
+    }
+    Ok(changed)
+}
+
+fn analyze_blocking(
+    app: &tauri::AppHandle,
+    path: &str,
+    force: bool,
+    write_tags: bool,
+) -> Result<AnalysisReport, CommandError> {
+    let root = if write_tags {
+        path_policy::resolve_existing_or_root(app, path)?
+    } else {
+        std::fs::canonicalize(path)?
+    };
+    let files = audio_files(&root);
+    let total = files.len();
+    let mut results = ANALYSIS
+        .lock()
+        .map_err(|_| "analysis lock poisoned".to_string())?
+        .results
+        .clone();
+
+    log::info!(target: "analysis", "analyzing {} files under {}", total, root.display());
+
+    let mut seen = HashSet::new();
+    let mut failed = Vec::new();
+    let (mut tagged, mut analyzed, mut cached, mut written) = (0, 0, 0, 0);
+    let mut cancelled = false;
+
+    for (idx, file) in files.iter().enumerate() {
+        if ANALYSIS_CANCELLED.load(Ordering::SeqCst) {
+            cancelled = true;
+            break;
+        }
+        let path_str = file.to_string_lossy().to_string();
+        let _ = app.emit(
+            "analysis-progress",
+            AnalysisProgress {
+                done: idx,
+                total,
+                current: path_str.clone(),
+                analyzed,
+            },
+        );
+        let Some((modified, size)) = get_file_stamp(file) else {
+            continue;
+        };
+        seen.insert(path_str.clone());
+
+        let has_tags = read_tagged_file(file)
+            .ok()
+            .and_then(|f| {
+                f.primary_tag()
+                    .map(|t| bpm_from_tag(t).is_some() && key_from_tag(t).is_some())
+            })
+            .unwrap_or(false);
+        if has_tags && !force {
+            tagged += 1;
+            continue;
+        }
+
+        let reusable = results
+            .get(&path_str)
+            .filter(|c| !force && c.modified == modified && c.size == size)
+            .map(|c| c.result.clone());
+        let result = match reusable {
+            Some(result) => {
+                cached += 1;
+                result
+            }
+            None => match analyze_file(file) {
+                Ok(result) => {
+                    analyzed += 1;
+                    log::debug!(target: "analysis", "{}: {:?} BPM, key {:?}", path_str, result.bpm, result.key);
+                    result
+                }
+                Err(error) => {
+                    log::warn!(target: "analysis", "failed to analyze {}: {}", path_str, error);
+                    failed.push(AnalysisFailure { path: path_str, error });
+                    continue;
+                }
+            },
+        };
+
+        let mut stamp = (modified, size);
+        if write_tags {
+            match write_missing_tags(file, &result) {
+                Ok(true) => {
+                    written += 1;
+                    stamp = get_file_stamp(file).unwrap_or(stamp);
+                }
+                Ok(false) => {}
+                Err(error) => failed.push(AnalysisFailure {
+                    path: path_str.clone(),
+                    error,
+                }),
+            }
+        }
+        results.insert(
+            path_str,
+            CachedAnalysis {
+                modified: stamp.0,
+                size: stamp.1,
+                result,
+            },
+        );
+    }
+
+    if !cancelled {
+        results.retain(|p, _| !Path::new(p).starts_with(&root) || seen.contains(p));
+    }
+    store::save(app, ANALYSIS_CACHE_FILE, &results)?;
+    if let Ok(mut state) = ANALYSIS.lock() {
+        state.results = results;
+        state.revision += 1;
+    }
+
+    let _ = app.emit(
+        "analysis-progress",
+        AnalysisProgress {
+            done: total,
+            total,
+            current: String::new(),
+            analyzed,
+        },
+    );
+    log::info!(
+        target: "analysis",
+        "analysis of {} {}: {} analyzed, {} cached, {} already tagged, {} written, {} failed",
+        root.display(),
+        if cancelled { "cancelled" } else { "finished" },
+        analyzed,
+        cached,
+        tagged,
+        written,
+        failed.len()
+    );
+
+    Ok(AnalysisReport {
+        root: root.to_string_lossy().to_string(),
+        total,
+        tagged,
+        analyzed,
+        cached,
+        written,
+        cancelled,
+        failed,
+    })
+}
+
+#[tauri::command]
+pub async fn analyze_library(
+    app: tauri::AppHandle,
+    path: String,
+    force: Option<bool>,
+    write_tags: Option<bool>,
+) -> Result<AnalysisReport, CommandError> {
+    if ANALYSIS_RUNNING.swap(true, Ordering::SeqCst) {
+        return Err(CommandError::invalid_input("An analysis job is already running"));
+    }
+    ANALYSIS_CANCELLED.store(false, Ordering::SeqCst);
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        analyze_blocking(&app, &path, force.unwrap_or(false), write_tags.unwrap_or(false))
+    })
+    .await;
+    ANALYSIS_RUNNING.store(false, Ordering::SeqCst);
+    result?
+}
+
+#[tauri::command]
+pub fn cancel_analysis() -> bool {
+    let running = ANALYSIS_RUNNING.load(Ordering::SeqCst);
+    if running {
+        ANALYSIS_CANCELLED.store(true, Ordering::SeqCst);
+    }
+    running
+}
@@ -0,0 +1,220 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+use super::{MusicFolder, Track};
+use crate::query::normalize;
+
+const RECENTLY_ADDED_LIMIT: usize = 200;
+const DISC_MARKERS: [&str; 3] = ["disc", "disk", "cd"];
+const SEPARATORS: [char; 4] = [' ', '-', ',', '_'];
+
+struct Group<'a> {
+    name: String,
+    tracks: Vec<&'a Track>,
+}
+
+struct VirtualLibrary {
+    folders: Vec<MusicFolder>,
+}
+
+impl VirtualLibrary {
+    fn folder(&mut self, id: &str, parent_id: Option<&str>, name: String, track_count: usize) -> &mut MusicFolder {
+        self.folders.push(MusicFolder {
+            id: id.to_string(),
+            parent_id: parent_id.map(|p| p.to_string()),
+            name,
+            path: id.to_string(),
+            track_count,
+            source: "local".to_string(),
+            track_ids: Vec::new(),
+        });
+        let last = self.folders.len() - 1;
+        &mut self.folders[last]
+    }
+
+    fn tracks_folder(&mut self, id: &str, parent_id: &str, name: String, tracks: &[&Track]) {
+        self.folder(id, Some(parent_id), name, tracks.len()).track_ids = tracks.iter().map(|t| t.id.clone()).collect();
+    }
+}
+
+fn is_disc_suffix(text: &str) -> bool {
+    let text = text.trim().to_ascii_lowercase();
+    DISC_MARKERS.iter().any(|marker| {
+        text.strip_prefix(marker)
+            .map(|rest| rest.trim_start_matches(['.', ' ']))
+            .is_some_and(|rest| !rest.is_empty() && rest.chars().all(|c| c.is_ascii_digit()))
+    })
+}
+
+fn album_base_name(album: &str) -> &str {
+    let trimmed = album.trim();
+    for (open, close) in [('(', ')'), ('[', ']')] {
+        let Some(inner) = trimmed.strip_suffix(close) else {
+            continue;
+        };
+        if let Some(start) = inner.rfind(open) {
+            let base = trimmed[..start].trim_end_matches(SEPARATORS);
+            if !base.is_empty() && is_disc_suffix(&inner[start + 1..]) {
+                return base;
+            }
+        }
+    }
+
+    let lower = trimmed.to_ascii_lowercase();
+    for marker in DISC_MARKERS {
+        if let Some(idx) = lower.rfind(marker) {
+            let before = &trimmed[..idx];
+            let base = before.trim_end_matches(SEPARATORS);
+            if before.ends_with(SEPARATORS) && !base.is_empty() && is_disc_suffix(&trimmed[idx..]) {
+                return base;
+            }
+        }
+    }
+    trimmed
+}
+
+fn album_artist(track: &Track) -> &str {
+    track
+        .album_artist
+        .as_deref()
+        .map(str::trim)
+        .filter(|a| !a.is_empty())
+        .unwrap_or(track.artist.trim())
+}
+
+fn album_key(track: &Track) -> String {
+    format!(
+        "{}|{}",
+        normalize(album_artist(track)),
+        normalize(album_base_name(&track.album))
+    )
+}
+
+fn album_order(a: &&Track, b: &&Track) -> Ordering {
+    (a.disc_number.unwrap_or(1), a.track_number.unwrap_or(u32::MAX), &a.title).cmp(&(
+        b.disc_number.unwrap_or(1),
+        b.track_number.unwrap_or(u32::MAX),
+        &b.title,
+    ))
+}
+
+fn group_by<'a>(
+    tracks: impl IntoIterator<Item = &'a Track>,
+    keys: impl Fn(&'a Track) -> Vec<(String, String)>,
+) -> BTreeMap<String, Group<'a>> {
+    let mut groups: BTreeMap<String, Group<'a>> = BTreeMap::new();
+    for track in tracks {
+        for (key, name) in keys(track) {
+            groups
+                .entry(key)
+                .or_insert_with(|| Group {
+                    name,
+                    tracks: Vec::new(),
+                })
+                .tracks
+                .push(track);
+        }
+    }
+    groups
+}
+
+fn album_groups<'a>(tracks: impl IntoIterator<Item = &'a Track>) -> BTreeMap<String, Group<'a>> {
+    let mut albums = group_by(tracks, |t| vec![(album_key(t), album_base_name(&t.album).to_string())]);
+    for album in albums.values_mut() {
+        album.tracks.sort_by(album_order);
+    }
+    albums
+}
+
+fn genre_names(track: &Track) -> Vec<(String, String)> {
+    let mut seen = HashSet::new();
+    track
+        .genre
+        .as_deref()
+        .unwrap_or_default()
+        .split(['\0', ';'])
+        .map(str::trim)
+        .filter(|g| !g.is_empty())
+        .filter_map(|g| {
+            let key = normalize(g);
+            seen.insert(key.clone()).then(|| (key, g.to_string()))
+        })
+        .collect()
+}
+
+pub(crate) fn root_folder_id(root_key: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    root_key.hash(&mut hasher);
+    format!("local:{:016x}", hasher.finish())
+}
+
+pub(crate) fn build(root: &Path, root_key: &str, tracks: &[Track], added: &HashMap<String, u64>) -> Vec<MusicFolder> {
+    let mut library = VirtualLibrary { folders: Vec::new() };
+    if tracks.is_empty() {
+        return library.folders;
+    }
+
+    let root_id = root_folder_id(root_key);
+    let root_name = root
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| root.to_string_lossy().to_string());
+    library.folder(&root_id, None, root_name, tracks.len());
+
+    let artists_id = format!("{}:artists", root_id);
+    library.folder(&artists_id, Some(&root_id), "Artists".to_string(), tracks.len());
+    let artists = group_by(tracks, |t| {
+        let name = album_artist(t).to_string();
+        vec![(normalize(&name), name)]
+    });
+    for (artist_key, artist) in artists {
+        let artist_id = format!("{}:artist:{}", root_id, artist_key);
+        library.folder(&artist_id, Some(&artists_id), artist.name, artist.tracks.len());
+        for (key, album) in album_groups(artist.tracks) {
+            let album_id = format!("{}:album:{}", artist_id, key);
+            library.tracks_folder(&album_id, &artist_id, album.name, &album.tracks);
+        }
+    }
+
+    let albums_id = format!("{}:albums", root_id);
+    library.folder(&albums_id, Some(&root_id), "Albums".to_string(), tracks.len());
+    for (key, album) in album_groups(tracks) {
+        let album_id = format!("{}:album:{}", root_id, key);
+        library.tracks_folder(&album_id, &albums_id, album.name, &album.tracks);
+    }
+
+    let genres_id = format!("{}:genres", root_id);
+    let genres = group_by(tracks, genre_names);
+    let genre_total = tracks.iter().filter(|t| !genre_names(t).is_empty()).count();
+    library.folder(&genres_id, Some(&root_id), "Genres".to_string(), genre_total);
+    for (key, genre) in genres {
+        let genre_id = format!("{}:genre:{}", root_id, key);
+        library.tracks_folder(&genre_id, &genres_id, genre.name, &genre.tracks);
+    }
+
+    let years_id = format!("{}:years", root_id);
+    let years = group_by(tracks, |t| {
+        t.year
+            .map(|y| vec![(format!("{:04}", y), y.to_string())])
+            .unwrap_or_default()
+    });
+    let year_total = tracks.iter().filter(|t| t.year.is_some()).count();
+    library.folder(&years_id, Some(&root_id), "Years".to_string(), year_total);
+    for (key, mut year) in years.into_iter().rev() {
+        year.tracks
+            .sort_by(|a, b| album_key(a).cmp(&album_key(b)).then_with(|| album_order(a, b)));
+        let year_id = format!("{}:year:{}", root_id, key);
+        library.tracks_folder(&year_id, &years_id, year.name, &year.tracks);
+    }
+
+    let mut recent: Vec<&Track> = tracks.iter().collect();
+    recent.sort_by_key(|t| Reverse(added.get(&t.id).copied().unwrap_or(0)));
+    recent.truncate(RECENTLY_ADDED_LIMIT);
+    let recent_id = format!("{}:recent", root_id);
+    library.tracks_folder(&recent_id, &root_id, "Recently Added".to_string(), &recent);
+
+    library.folders
+}
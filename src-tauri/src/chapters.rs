@@ -0,0 +1,599 @@
+use base64::prelude::*;
+use lofty::{FileType, TagType, TaggedFile, TaggedFileExt};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use super::path_policy;
+use crate::error::CommandError;
+
+const MAX_MOOV_BYTES: u64 = 64 * 1024 * 1024;
+const MAX_ID3_TAG_BYTES: u64 = 64 * 1024 * 1024;
+// Bounds the walk over nested CTOC frames, which may reference each other in a cycle.
+const MAX_TOC_ENTRIES: usize = 4096;
+const CHAPTER_EXTENSIONS: [&str; 3] = ["m4b", "m4a", "mp3"];
+const MAX_CHAPTER_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+const MAX_CHAPTER_SAMPLES: usize = 10_000;
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Chapter {
+    title: String,
+    start: f64,
+    end: Option<f64>,
+    image: Option<String>,
+}
+
+struct Image {
+    mime: String,
+    bytes: Vec<u8>,
+}
+
+struct RawChapter {
+    title: Option<String>,
+    start: f64,
+    end: Option<f64>,
+    image: Option<Image>,
+}
+
+fn be_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn be_u64(data: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
+
+fn image_mime(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(&[0xff, 0xd8]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"\x89PNG") {
+        Some("image/png")
+    } else {
+        None
+    }
+}
+
+fn clean_title(title: String) -> Option<String> {
+    let title = title.split('\0').next().unwrap_or_default().trim().to_string();
+    (!title.is_empty()).then_some(title)
+}
+
+// ID3v2.3/2.4 CHAP and CTOC frames (the ID3 chapter addendum).
+
+fn syncsafe(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |acc, b| (acc << 7) | usize::from(b & 0x7f))
+}
+
+fn remove_unsync(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    for (i, b) in data.iter().enumerate() {
+        if !(*b == 0 && i > 0 && data[i - 1] == 0xff) {
+            out.push(*b);
+        }
+    }
+    out
+}
+
+fn id3_frames(data: &[u8], major: u8) -> Vec<([u8; 4], Vec<u8>)> {
+    let mut frames = Vec::new();
+    let mut pos = 0;
+    while pos + 10 <= data.len() && data[pos] != 0 {
+        let id: [u8; 4] = data[pos..pos + 4].try_into().unwrap_or_default();
+        let size = if major == 4 {
+            syncsafe(&data[pos + 4..pos + 8])
+        } else {
+            be_u32(data, pos + 4).unwrap_or(0) as usize
+        };
+        let flags = data[pos + 9];
+        let Some(body) = (pos + 10).checked_add(size).and_then(|end| data.get(pos + 10..end)) else {
+            break;
+        };
+        pos += 10 + body.len();
+
+        let (compressed, encrypted) = if major == 4 {
+            (flags & 0x08 != 0, flags & 0x04 != 0)
+        } else {
+            (flags & 0x80 != 0, flags & 0x40 != 0)
+        };
+        if compressed || encrypted {
+            continue;
+        }
+        let mut body = body.to_vec();
+        if major == 4 && flags & 0x02 != 0 {
+            body = remove_unsync(&body);
+        }
+        if major == 4 && flags & 0x01 != 0 {
+            body.drain(..4.min(body.len()));
+        }
+        frames.push((id, body));
+    }
+    frames
+}
+
+fn id3_text(encoding: u8, bytes: &[u8]) -> String {
+    match encoding {
+        0 => bytes.iter().map(|b| char::from(*b)).collect(),
+        1 | 2 => {
+            let mut units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            let little_endian = encoding == 1 && units.first() == Some(&0xfffe);
+            if little_endian {
+                units = units.iter().map(|u| u.swap_bytes()).collect();
+            }
+            if units.first() == Some(&0xfeff) {
+                units.remove(0);
+            }
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+// Splits off a string terminated according to the text encoding.
+fn id3_terminated(encoding: u8, bytes: &[u8]) -> (&[u8], &[u8]) {
+    let end = if matches!(encoding, 1 | 2) {
+        (0..bytes.len().saturating_sub(1))
+            .step_by(2)
+            .find(|i| bytes[*i] == 0 && bytes[i + 1] == 0)
+            .map(|i| (i, i + 2))
+    } else {
+        bytes.iter().position(|b| *b == 0).map(|i| (i, i + 1))
+    };
+    match end {
+        Some((end, next)) => (&bytes[..end], &bytes[next..]),
+        None => (bytes, &[]),
+    }
+}
+
+fn id3_picture(body: &[u8]) -> Option<Image> {
+    let (&encoding, rest) = body.split_first()?;
+    let (mime, rest) = id3_terminated(0, rest);
+    let (_, rest) = id3_terminated(encoding, rest.get(1..)?);
+    let mime = match String::from_utf8_lossy(mime).to_lowercase().as_str() {
+        "" | "-->" => image_mime(rest)?.to_string(),
+        m if m.contains('/') => m.to_string(),
+        m => format!("image/{}", m.replace("jpg", "jpeg")),
+    };
+    Some(Image {
+        mime,
+        bytes: rest.to_vec(),
+    })
+}
+
+fn id3_chap(body: &[u8], major: u8, with_images: bool) -> Option<(String, RawChapter)> {
+    let (element, rest) = id3_terminated(0, body);
+    let start = be_u32(rest, 0)?;
+    let end = be_u32(rest, 4)?;
+    let mut chapter = RawChapter {
+        title: None,
+        start: f64::from(start) / 1000.0,
+        end: (end != u32::MAX && end > start).then(|| f64::from(end) / 1000.0),
+        image: None,
+    };
+    for (id, frame) in id3_frames(rest.get(16..)?, major) {
+        match &id {
+            b"TIT2" if chapter.title.is_none() => {
+                if let Some((&encoding, text)) = frame.split_first() {
+                    chapter.title = clean_title(id3_text(encoding, text));
+                }
+            }
+            b"APIC" if with_images => chapter.image = id3_picture(&frame),
+            _ => {}
+        }
+    }
+    Some((String::from_utf8_lossy(element).into_owned(), chapter))
+}
+
+fn id3_ctoc(body: &[u8]) -> Option<(String, bool, Vec<String>)> {
+    let (element, rest) = id3_terminated(0, body);
+    let flags = *rest.first()?;
+    let count = *rest.get(1)?;
+    let mut rest = rest.get(2..)?;
+    let mut children = Vec::new();
+    for _ in 0..count {
+        let (child, next) = id3_terminated(0, rest);
+        children.push(String::from_utf8_lossy(child).into_owned());
+        rest = next;
+    }
+    Some((
+        String::from_utf8_lossy(element).into_owned(),
+        flags & 0x02 != 0,
+        children,
+    ))
+}
+
+fn id3_chapters(file: &mut File, with_images: bool) -> Option<Vec<RawChapter>> {
+    let mut header = [0u8; 10];
+    file.read_exact(&mut header).ok()?;
+    let major = header[3];
+    if &header[..3] != b"ID3" || !(3..=4).contains(&major) {
+        return None;
+    }
+    let size = syncsafe(&header[6..10]) as u64;
+    if size > MAX_ID3_TAG_BYTES {
+        return None;
+    }
+    let mut tag = Vec::new();
+    file.by_ref().take(size).read_to_end(&mut tag).ok()?;
+    if tag.len() as u64 != size {
+        return None;
+    }
+    if header[5] & 0x80 != 0 {
+        tag = remove_unsync(&tag);
+    }
+    let mut start = 0;
+    if header[5] & 0x40 != 0 {
+        start = if major == 4 {
+            syncsafe(tag.get(..4)?)
+        } else {
+            (be_u32(&tag, 0)? as usize).checked_add(4)?
+        };
+    }
+
+    let mut chapters: HashMap<String, RawChapter> = HashMap::new();
+    let mut tocs: Vec<(String, bool, Vec<String>)> = Vec::new();
+    for (id, body) in id3_frames(tag.get(start..)?, major) {
+        match &id {
+            b"CHAP" => {
+                if let Some((element, chapter)) = id3_chap(&body, major, with_images) {
+                    chapters.insert(element, chapter);
+                }
+            }
+            b"CTOC" => tocs.extend(id3_ctoc(&body)),
+            _ => {}
+        }
+    }
+
+    // Follow the top-level table of contents (and any nested ones) when there is one.
+    let mut ordered = Vec::new();
+    let mut pending: Vec<String> = tocs
+        .iter()
+        .find(|(_, top_level, _)| *top_level)
+        .or(tocs.first())
+        .map(|(_, _, children)| children.iter().rev().cloned().collect())
+        .unwrap_or_default();
+    let mut visited = 0;
+    while let Some(element) = pending.pop() {
+        visited += 1;
+        if visited > MAX_TOC_ENTRIES {
+            break;
+        }
+        if let Some(chapter) = chapters.remove(&element) {
+            ordered.push(chapter);
+        } else if let Some((_, _, children)) = tocs.iter().find(|(id, _, _)| *id == element) {
+            pending.extend(children.iter().rev().cloned());
+        }
+    }
+    if ordered.is_empty() {
+        ordered = chapters.into_values().collect();
+        ordered.sort_by(|a, b| a.start.total_cmp(&b.start));
+    }
+    Some(ordered)
+}
+
+// MP4 Nero `chpl` lists and QuickTime chapter tracks (`tref/chap`).
+
+fn mp4_boxes(mut data: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut boxes = Vec::new();
+    while data.len() >= 8 {
+        let size = be_u32(data, 0).unwrap_or(0) as u64;
+        let (header, size) = match size {
+            0 => (8, data.len() as u64),
+            1 => (16, be_u64(data, 8).unwrap_or(0)),
+            size => (8, size),
+        };
+        if size < header || size > data.len() as u64 {
+            break;
+        }
+        let size = size as usize;
+        boxes.push((&data[4..8], &data[header as usize..size]));
+        data = &data[size..];
+    }
+    boxes
+}
+
+fn mp4_child<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Option<&'a [u8]> {
+    path.iter().try_fold(data, |data, kind| {
+        mp4_boxes(data)
+            .into_iter()
+            .find(|(k, _)| *k == kind.as_slice())
+            .map(|(_, body)| body)
+    })
+}
+
+fn read_moov(file: &mut File) -> Option<Vec<u8>> {
+    let len = file.metadata().ok()?.len();
+    let mut pos = 0u64;
+    while pos + 8 <= len {
+        file.seek(SeekFrom::Start(pos)).ok()?;
+        let mut header = [0u8; 16];
+        file.read_exact(&mut header[..8]).ok()?;
+        let (header_len, size) = match be_u32(&header, 0)? {
+            0 => (8, len - pos),
+            1 => {
+                file.read_exact(&mut header[8..]).ok()?;
+                (16, be_u64(&header, 8)?)
+            }
+            size => (8, u64::from(size)),
+        };
+        if size < header_len {
+            return None;
+        }
+        if &header[4..8] == b"moov" {
+            let body_len = size - header_len;
+            if body_len > MAX_MOOV_BYTES {
+                return None;
+            }
+            let mut moov = vec![0u8; body_len as usize];
+            file.read_exact(&mut moov).ok()?;
+            return Some(moov);
+        }
+        pos = pos.checked_add(size)?;
+    }
+    None
+}
+
+fn nero_chapters(moov: &[u8]) -> Option<Vec<RawChapter>> {
+    let chpl = mp4_child(moov, &[b"udta", b"chpl"])?;
+    let mut pos = if *chpl.first()? > 0 { 8 } else { 4 };
+    let count = *chpl.get(pos)?;
+    pos += 1;
+    let mut chapters = Vec::new();
+    for _ in 0..count {
+        // Start times are in 100 ns units.
+        let start = be_u64(chpl, pos)?;
+        let len = usize::from(*chpl.get(pos + 8)?);
+        let title = chpl.get(pos + 9..pos + 9 + len)?;
+        pos += 9 + len;
+        chapters.push(RawChapter {
+            title: clean_title(String::from_utf8_lossy(title).into_owned()),
+            start: start as f64 / 10_000_000.0,
+            end: None,
+            image: None,
+        });
+    }
+    Some(chapters)
+}
+
+struct Mp4Track<'a> {
+    id: u32,
+    chapter_refs: Vec<u32>,
+    handler: &'a [u8],
+    timescale: u32,
+    duration: u64,
+    stbl: &'a [u8],
+}
+
+fn mp4_track(trak: &[u8]) -> Option<Mp4Track<'_>> {
+    let tkhd = mp4_child(trak, &[b"tkhd"])?;
+    let id = be_u32(tkhd, if tkhd.first() == Some(&1) { 20 } else { 12 })?;
+    let chapter_refs = mp4_child(trak, &[b"tref", b"chap"])
+        .map(|chap| chap.chunks_exact(4).filter_map(|c| be_u32(c, 0)).collect())
+        .unwrap_or_default();
+    let mdia = mp4_child(trak, &[b"mdia"])?;
+    let mdhd = mp4_child(mdia, &[b"mdhd"])?;
+    let (timescale, duration) = if mdhd.first() == Some(&1) {
+        (be_u32(mdhd, 20)?, be_u64(mdhd, 24)?)
+    } else {
+        (be_u32(mdhd, 12)?, u64::from(be_u32(mdhd, 16)?))
+    };
+    Some(Mp4Track {
+        id,
+        chapter_refs,
+        handler: mp4_child(mdia, &[b"hdlr"])?.get(8..12)?,
+        timescale: timescale.max(1),
+        duration,
+        stbl: mp4_child(mdia, &[b"minf", b"stbl"])?,
+    })
+}
+
+// Start time, file offset and size of every sample in a track.
+fn mp4_samples(stbl: &[u8]) -> Option<Vec<(u64, u64, u32)>> {
+    let stts = mp4_child(stbl, &[b"stts"])?;
+    let mut times = Vec::new();
+    let mut time = 0u64;
+    for i in 0..be_u32(stts, 4)? as usize {
+        let count = be_u32(stts, 8 + i * 8)?;
+        let delta = be_u32(stts, 12 + i * 8)?;
+        for _ in 0..count {
+            if times.len() >= MAX_CHAPTER_SAMPLES {
+                break;
+            }
+            times.push(time);
+            time = time.saturating_add(u64::from(delta));
+        }
+    }
+
+    let stsz = mp4_child(stbl, &[b"stsz"])?;
+    let fixed_size = be_u32(stsz, 4)?;
+    let sample_count = (be_u32(stsz, 8)? as usize).min(times.len());
+    let sizes: Vec<u32> = (0..sample_count)
+        .map(|i| {
+            if fixed_size > 0 {
+                Some(fixed_size)
+            } else {
+                be_u32(stsz, 12 + i * 4)
+            }
+        })
+        .collect::<Option<_>>()?;
+
+    let chunk_offsets: Vec<u64> = if let Some(stco) = mp4_child(stbl, &[b"stco"]) {
+        (0..be_u32(stco, 4)? as usize)
+            .map(|i| be_u32(stco, 8 + i * 4).map(u64::from))
+            .collect::<Option<_>>()?
+    } else {
+        let co64 = mp4_child(stbl, &[b"co64"])?;
+        (0..be_u32(co64, 4)? as usize)
+            .map(|i| be_u64(co64, 8 + i * 8))
+            .collect::<Option<_>>()?
+    };
+
+    let stsc = mp4_child(stbl, &[b"stsc"])?;
+    let runs: Vec<(u32, u32)> = (0..be_u32(stsc, 4)? as usize)
+        .map(|i| Some((be_u32(stsc, 8 + i * 12)?, be_u32(stsc, 12 + i * 12)?)))
+        .collect::<Option<_>>()?;
+
+    let mut samples = Vec::with_capacity(sample_count);
+    for (chunk, offset) in chunk_offsets.iter().enumerate() {
+        let per_chunk = runs
+            .iter()
+            .rev()
+            .find(|(first, _)| *first as usize <= chunk + 1)
+            .map_or(1, |(_, n)| *n);
+        let mut offset = *offset;
+        for _ in 0..per_chunk {
+            let index = samples.len();
+            if index >= sample_count {
+                return Some(samples);
+            }
+            samples.push((times[index], offset, sizes[index]));
+            offset = offset.checked_add(u64::from(sizes[index]))?;
+        }
+    }
+    Some(samples)
+}
+
+fn read_sample(file: &mut File, offset: u64, size: u32) -> Option<Vec<u8>> {
+    if size as usize > MAX_CHAPTER_IMAGE_BYTES {
+        return None;
+    }
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    let mut bytes = vec![0u8; size as usize];
+    file.read_exact(&mut bytes).ok()?;
+    Some(bytes)
+}
+
+fn sample_text(sample: &[u8]) -> Option<String> {
+    let len = usize::from(be_u16(sample, 0)?);
+    let text = sample.get(2..2 + len)?;
+    let text = if text.starts_with(&[0xfe, 0xff]) {
+        id3_text(2, &text[2..])
+    } else {
+        String::from_utf8_lossy(text).into_owned()
+    };
+    clean_title(text)
+}
+
+fn quicktime_chapters(file: &mut File, moov: &[u8], with_images: bool) -> Option<Vec<RawChapter>> {
+    let tracks: Vec<Mp4Track> = mp4_boxes(moov)
+        .into_iter()
+        .filter(|(kind, _)| *kind == b"trak")
+        .filter_map(|(_, trak)| mp4_track(trak))
+        .collect();
+    let refs: Vec<u32> = tracks.iter().flat_map(|t| t.chapter_refs.iter().copied()).collect();
+    let referenced = |handler: &[u8]| tracks.iter().find(|t| refs.contains(&t.id) && t.handler == handler);
+
+    let text = referenced(b"text").or_else(|| referenced(b"sbtl"))?;
+    let scale = f64::from(text.timescale);
+    let mut chapters = Vec::new();
+    for (time, offset, size) in mp4_samples(text.stbl)? {
+        chapters.push(RawChapter {
+            title: read_sample(file, offset, size).and_then(|s| sample_text(&s)),
+            start: time as f64 / scale,
+            end: None,
+            image: None,
+        });
+    }
+    if let Some(last) = chapters.last_mut() {
+        last.end = Some(text.duration as f64 / scale).filter(|end| *end > last.start);
+    }
+
+    if let Some(video) = referenced(b"vide").filter(|_| with_images) {
+        let scale = f64::from(video.timescale);
+        let images = mp4_samples(video.stbl).unwrap_or_default();
+        for chapter in &mut chapters {
+            let sample = images
+                .iter()
+                .rev()
+                .find(|(time, _, _)| *time as f64 / scale <= chapter.start + 0.001);
+            chapter.image = sample
+                .and_then(|(_, offset, size)| read_sample(file, *offset, *size))
+                .and_then(|bytes| {
+                    let mime = image_mime(&bytes)?.to_string();
+                    Some(Image { mime, bytes })
+                });
+        }
+    }
+    Some(chapters)
+}
+
+fn mp4_chapters(file: &mut File, with_images: bool) -> Option<Vec<RawChapter>> {
+    let moov = read_moov(file)?;
+    quicktime_chapters(file, &moov, with_images)
+        .filter(|c| !c.is_empty())
+        .or_else(|| nero_chapters(&moov))
+}
+
+fn read_chapters(path: &Path, with_images: bool) -> Option<Vec<RawChapter>> {
+    let mut file = File::open(path).ok()?;
+    let mut magic = [0u8; 8];
+    file.read_exact(&mut magic).ok()?;
+    file.seek(SeekFrom::Start(0)).ok()?;
+    let mut chapters = if magic.starts_with(b"ID3") {
+        id3_chapters(&mut file, with_images)?
+    } else if &magic[4..8] == b"ftyp" {
+        mp4_chapters(&mut file, with_images)?
+    } else {
+        return None;
+    };
+    // Fill in missing end times from the following chapter.
+    for i in 1..chapters.len() {
+        let next = chapters[i].start;
+        if chapters[i - 1].end.is_none() && next > chapters[i - 1].start {
+            chapters[i - 1].end = Some(next);
+        }
+    }
+    Some(chapters)
+}
+
+// Uses the file type from the scanner's lofty read so only files that can carry chapters are opened again.
+pub(crate) fn chapter_count(path: &Path, tagged_file: &TaggedFile) -> u32 {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let may_have_chapters = match tagged_file.file_type() {
+        FileType::Mp4 => true,
+        FileType::Mpeg => tagged_file.tag(TagType::Id3v2).is_some(),
+        _ => false,
+    };
+    if !may_have_chapters || !CHAPTER_EXTENSIONS.contains(&extension.as_str()) {
+        return 0;
+    }
+    read_chapters(path, false).map_or(0, |chapters| chapters.len() as u32)
+}
+
+#[tauri::command]
+pub async fn get_chapters(app: tauri::AppHandle, track_id: String) -> Result<Vec<Chapter>, CommandError> {
+    if track_id.starts_with("navidrome:") {
+        return Ok(Vec::new());
+    }
+    tauri::async_runtime::spawn_blocking(move || -> Result<Vec<Chapter>, CommandError> {
+        let path = path_policy::resolve_track(&app, &track_id)?;
+        let chapters = read_chapters(&path, true)
+            .unwrap_or_default()
+            .into_iter()
+            .enumerate()
+            .map(|(i, c)| Chapter {
+                title: c.title.unwrap_or_else(|| format!("Chapter {}", i + 1)),
+                start: c.start,
+                end: c.end,
+                image: c
+                    .image
+                    .filter(|image| image.bytes.len() <= MAX_CHAPTER_IMAGE_BYTES)
+                    .map(|image| format!("data:{};base64,{}", image.mime, BASE64_STANDARD.encode(&image.bytes))),
+            })
+            .collect();
+        Ok(chapters)
+    })
+    .await?
+}
@@ -0,0 +1,151 @@
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as DecodeError;
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::{MediaSource, MediaSourceStream};
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use symphonia::core::units::TimeBase;
+
+use crate::error::CommandError;
+
+pub(crate) struct DecodeSession {
+    pub(crate) format: Box<dyn FormatReader>,
+    pub(crate) decoder: Box<dyn Decoder>,
+    pub(crate) track_id: u32,
+    pub(crate) time_base: Option<TimeBase>,
+    pub(crate) sample_rate: Option<u32>,
+    pub(crate) n_frames: Option<u64>,
+}
+
+impl DecodeSession {
+    pub(crate) fn seconds(&self, ts: u64) -> f64 {
+        match (self.time_base, self.sample_rate) {
+            (Some(tb), _) => {
+                let time = tb.calc_time(ts);
+                time.seconds as f64 + time.frac
+            }
+            (None, Some(rate)) if rate > 0 => ts as f64 / rate as f64,
+            _ => 0.0,
+        }
+    }
+}
+
+pub(crate) fn open(path: &Path, verify: bool) -> Result<DecodeSession, CommandError> {
+    let file = std::fs::File::open(path)?;
+    let extension = path.extension().map(|ext| ext.to_string_lossy().to_string());
+    open_source(Box::new(file), extension.as_deref(), verify)
+}
+
+// Opens audio that is already in memory, such as a downloaded stream.
+pub(crate) fn open_bytes(bytes: Vec<u8>, extension: Option<&str>) -> Result<DecodeSession, CommandError> {
+    open_source(Box::new(std::io::Cursor::new(bytes)), extension, false)
+}
+
+fn open_source(
+    source: Box<dyn MediaSource>,
+    extension: Option<&str>,
+    verify: bool,
+) -> Result<DecodeSession, CommandError> {
+    let stream = MediaSourceStream::new(source, Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(ext) = extension {
+        hint.with_extension(ext);
+    }
+
+    let probed = symphonia::default::get_probe().format(
+        &hint,
+        stream,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+    let format = probed.format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| CommandError::parse("No decodable audio track"))?;
+    let decoder = symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions { verify })?;
+
+    Ok(DecodeSession {
+        track_id: track.id,
+        time_base: track.codec_params.time_base,
+        sample_rate: track.codec_params.sample_rate,
+        n_frames: track.codec_params.n_frames,
+        format,
+        decoder,
+    })
+}
+
+// Feeds the decoded audio to `f` as mono samples (channels averaged) together with the sample
+// rate, until the stream ends or `f` returns false. Undecodable packets are skipped.
+pub(crate) fn for_each_mono(
+    session: &mut DecodeSession,
+    mut f: impl FnMut(f32, u32) -> bool,
+) -> Result<(), CommandError> {
+    let mut buffer: Option<SampleBuffer<f32>> = None;
+    loop {
+        let packet = match session.format.next_packet() {
+            Ok(packet) => packet,
+            Err(DecodeError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(DecodeError::ResetRequired) => {
+                session.decoder.reset();
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if packet.track_id() != session.track_id {
+            continue;
+        }
+        let decoded = match session.decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            Err(DecodeError::DecodeError(_)) => continue,
+            Err(e) => return Err(e.into()),
+        };
+        let spec = *decoded.spec();
+        let channels = spec.channels.count().max(1);
+        if buffer
+            .as_ref()
+            .is_none_or(|b| b.capacity() < decoded.capacity() * channels)
+        {
+            buffer = Some(SampleBuffer::new(decoded.capacity() as u64, spec));
+        }
+        let Some(buf) = buffer.as_mut() else {
+            continue;
+        };
+        buf.copy_interleaved_ref(decoded);
+        for frame in buf.samples().chunks(channels) {
+            if !f(frame.iter().sum::<f32>() / channels as f32, spec.rate) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+// Decodes up to `max_seconds` of audio as mono, averaging consecutive samples down to roughly
+// `target_rate`. Returns the samples and their actual rate.
+pub(crate) fn mono_samples(path: &Path, target_rate: u32, max_seconds: u32) -> Result<(Vec<f32>, u32), CommandError> {
+    let mut session = open(path, false)?;
+    let mut samples = Vec::new();
+    let (mut rate, mut factor, mut limit) = (0, 1, usize::MAX);
+    let (mut acc, mut acc_len) = (0.0, 0);
+    for_each_mono(&mut session, |sample, source_rate| {
+        if rate == 0 {
+            factor = (source_rate / target_rate.max(1)).max(1) as usize;
+            rate = source_rate / factor as u32;
+            limit = rate as usize * max_seconds as usize;
+        }
+        acc += sample;
+        acc_len += 1;
+        if acc_len == factor {
+            samples.push(acc / factor as f32);
+            acc = 0.0;
+            acc_len = 0;
+        }
+        samples.len() < limit
+    })?;
+    Ok((samples, rate))
+}
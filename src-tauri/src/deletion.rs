@@ -0,0 +1,240 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, SystemTime};
+
+use super::{history, load_config, path_policy, query, save_config, CachedTrack, Track, SCAN_CACHE};
+use crate::error::CommandError;
+
+const APP_TRASH_RETENTION: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+enum TrashLocation {
+    System,
+    App(PathBuf),
+}
+
+struct DeletedTrack {
+    original_path: PathBuf,
+    location: TrashLocation,
+    cached: Vec<(String, CachedTrack)>,
+    indexed: Vec<(String, Track)>,
+    favorite: Option<(usize, String)>,
+    hidden: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteResult {
+    undo_id: String,
+    path: String,
+    system_trash: bool,
+}
+
+static DELETED_TRACKS: LazyLock<Mutex<HashMap<String, DeletedTrack>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn app_trash_dir(app: &tauri::AppHandle) -> Result<PathBuf, CommandError> {
+    use tauri::Manager;
+    Ok(app.path().resolve("trash", tauri::path::BaseDirectory::AppData)?)
+}
+
+fn move_file(source: &Path, target: &Path) -> std::io::Result<()> {
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if std::fs::rename(source, target).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(source, target)?;
+    std::fs::remove_file(source)
+}
+
+fn move_to_trash(app: &tauri::AppHandle, path: &Path, undo_id: &str) -> Result<TrashLocation, CommandError> {
+    // Items in the macOS Trash cannot be restored programmatically, so undo relies on the app trash there.
+    if cfg!(not(target_os = "macos")) && trash::delete(path).is_ok() {
+        return Ok(TrashLocation::System);
+    }
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| CommandError::invalid_input("Invalid file path"))?;
+    let target = app_trash_dir(app)?.join(undo_id).join(file_name);
+    move_file(path, &target)?;
+    Ok(TrashLocation::App(target))
+}
+
+#[cfg(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+))]
+fn restore_from_system_trash(path: &Path) -> Result<(), CommandError> {
+    let item = trash::os_limited::list()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|item| item.original_path() == path)
+        .max_by_key(|item| item.time_deleted)
+        .ok_or_else(|| CommandError::not_found("Track is no longer in the trash"))?;
+    trash::os_limited::restore_all([item]).map_err(|e| CommandError::internal(e.to_string()))
+}
+
+#[cfg(not(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+)))]
+fn restore_from_system_trash(_path: &Path) -> Result<(), CommandError> {
+    Err(CommandError::invalid_input(
+        "Restoring from the system trash is not supported on this platform",
+    ))
+}
+
+fn take_cached_entries(path_str: &str) -> Vec<(String, CachedTrack)> {
+    let Ok(mut guard) = SCAN_CACHE.lock() else {
+        return Vec::new();
+    };
+    guard
+        .iter_mut()
+        .filter_map(|(root, library)| library.tracks.remove(path_str).map(|cached| (root.clone(), cached)))
+        .collect()
+}
+
+fn restore_cached_entries(path_str: &str, entries: Vec<(String, CachedTrack)>) {
+    let Ok(mut guard) = SCAN_CACHE.lock() else {
+        return;
+    };
+    for (root, cached) in entries {
+        guard
+            .entry(root)
+            .or_default()
+            .tracks
+            .insert(path_str.to_string(), cached);
+    }
+}
+
+pub(crate) fn purge_expired_app_trash(app: &tauri::AppHandle) {
+    let Ok(dir) = app_trash_dir(app) else {
+        return;
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let now = SystemTime::now();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let expired = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_some_and(|age| age > APP_TRASH_RETENTION);
+        if expired {
+            if let Err(e) = std::fs::remove_dir_all(entry.path()) {
+                log::warn!(target: "deletion", "failed to purge {}: {}", entry.path().display(), e);
+            }
+        }
+    }
+}
+
+#[tauri::command]
+pub fn delete_track(app: tauri::AppHandle, path: String) -> Result<DeleteResult, CommandError> {
+    let original_path = path_policy::resolve_existing(&app, &path)?;
+    if !original_path.is_file() {
+        return Err(CommandError::path_not_allowed("Only files can be deleted"));
+    }
+    let path_str = original_path.to_string_lossy().to_string();
+    let undo_id = uuid::Uuid::new_v4().to_string();
+
+    let location = move_to_trash(&app, &original_path, &undo_id)?;
+    let cached = take_cached_entries(&path_str);
+
+    // References to the identity are kept while an identical copy remains in the library.
+    let mut ids = vec![path.clone(), path_str.clone()];
+    for (_, entry) in &cached {
+        let canonical_id = &entry.track.canonical_id;
+        let shared = query::local_tracks(canonical_id)
+            .iter()
+            .any(|t| t.audio_url != path_str);
+        if !shared && !ids.contains(canonical_id) {
+            ids.push(canonical_id.clone());
+        }
+    }
+
+    let mut config = load_config(app.clone())?;
+    let is_deleted = |id: &String| ids.contains(id);
+    let favorite = config
+        .favorites
+        .iter()
+        .position(is_deleted)
+        .map(|idx| (idx, config.favorites[idx].clone()));
+    if favorite.is_some() {
+        config.favorites.retain(|id| !is_deleted(id));
+        save_config(app.clone(), config)?;
+    }
+    let id_refs: Vec<&str> = ids.iter().map(String::as_str).collect();
+    let hidden = history::hide_tracks(&app, &id_refs)?;
+    let indexed = query::remove_local_track(&path_str);
+
+    let system_trash = matches!(location, TrashLocation::System);
+    {
+        let mut deleted = DELETED_TRACKS.lock().map_err(|_| "deleted tracks poisoned".to_string())?;
+        deleted.insert(
+            undo_id.clone(),
+            DeletedTrack {
+                original_path,
+                location,
+                cached,
+                indexed,
+                favorite,
+                hidden,
+            },
+        );
+    }
+
+    Ok(DeleteResult {
+        undo_id,
+        path: path_str,
+        system_trash,
+    })
+}
+
+#[tauri::command]
+pub fn undo_delete(app: tauri::AppHandle, undo_id: String) -> Result<String, CommandError> {
+    let deleted = {
+        let mut guard = DELETED_TRACKS.lock().map_err(|_| "deleted tracks poisoned".to_string())?;
+        guard
+            .remove(&undo_id)
+            .ok_or_else(|| CommandError::not_found("Nothing to undo for this deletion"))?
+    };
+
+    if deleted.original_path.exists() {
+        return Err(CommandError::invalid_input("A file already exists at the original location"));
+    }
+    let restored = match &deleted.location {
+        TrashLocation::System => restore_from_system_trash(&deleted.original_path),
+        TrashLocation::App(trashed) => {
+            let result = move_file(trashed, &deleted.original_path).map_err(CommandError::from);
+            if result.is_ok() {
+                if let Some(dir) = trashed.parent() {
+                    let _ = std::fs::remove_dir(dir);
+                }
+            }
+            result
+        }
+    };
+    if let Err(e) = restored {
+        let mut guard = DELETED_TRACKS.lock().map_err(|_| "deleted tracks poisoned".to_string())?;
+        guard.insert(undo_id, deleted);
+        return Err(e);
+    }
+
+    let path_str = deleted.original_path.to_string_lossy().to_string();
+    restore_cached_entries(&path_str, deleted.cached);
+    query::restore_tracks(deleted.indexed);
+
+    if let Some((idx, id)) = deleted.favorite {
+        let mut config = load_config(app.clone())?;
+        config.favorites.insert(idx.min(config.favorites.len()), id);
+        save_config(app.clone(), config)?;
+    }
+    history::unhide_tracks(&app, &deleted.hidden)?;
+
+    Ok(path_str)
+}
@@ -0,0 +1,320 @@
+use lofty::{Accessor, AudioFile, ItemKey, PictureType, TaggedFileExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::{audio_files, to_unix_seconds, MAX_METADATA_PARSE_FILE_SIZE_BYTES};
+use crate::error::CommandError;
+use crate::tags::read_tagged_file;
+
+const MIN_COVER_DIMENSION: u32 = 300;
+const MIN_COVER_BYTES: usize = 8 * 1024;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum HealthIssueKind {
+    Unreadable,
+    TooLarge,
+    ZeroDuration,
+    MissingTags,
+    InconsistentAlbumArtist,
+    MixedSampleRates,
+    MissingCover,
+    SmallCover,
+}
+
+impl HealthIssueKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            HealthIssueKind::Unreadable => "unreadable",
+            HealthIssueKind::TooLarge => "too-large",
+            HealthIssueKind::ZeroDuration => "zero-duration",
+            HealthIssueKind::MissingTags => "missing-tags",
+            HealthIssueKind::InconsistentAlbumArtist => "inconsistent-album-artist",
+            HealthIssueKind::MixedSampleRates => "mixed-sample-rates",
+            HealthIssueKind::MissingCover => "missing-cover",
+            HealthIssueKind::SmallCover => "small-cover",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthIssue {
+    kind: HealthIssueKind,
+    path: String,
+    message: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthReport {
+    root: String,
+    generated_at: u64,
+    scanned_files: usize,
+    counts: BTreeMap<HealthIssueKind, usize>,
+    issues: Vec<HealthIssue>,
+}
+
+struct FileFacts {
+    path: PathBuf,
+    album: Option<String>,
+    artist: Option<String>,
+    album_artist: Option<String>,
+    sample_rate: Option<u32>,
+}
+
+fn issue(kind: HealthIssueKind, path: &Path, message: impl Into<String>) -> HealthIssue {
+    HealthIssue {
+        kind,
+        path: path.to_string_lossy().to_string(),
+        message: message.into(),
+    }
+}
+
+fn be_u16(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 2)?;
+    Some(u32::from(u16::from_be_bytes([b[0], b[1]])))
+}
+
+fn be_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 4)?;
+    Some(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn image_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some((be_u32(data, 16)?, be_u32(data, 20)?));
+    }
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut i = 2;
+    while i + 3 < data.len() {
+        if data[i] != 0xFF {
+            return None;
+        }
+        let marker = data[i + 1];
+        match marker {
+            0xFF => i += 1,
+            0x01 | 0xD0..=0xD9 => i += 2,
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                return Some((be_u16(data, i + 7)?, be_u16(data, i + 5)?));
+            }
+            _ => i += 2 + be_u16(data, i + 2)? as usize,
+        }
+    }
+    None
+}
+
+fn inspect_file(path: &Path, issues: &mut Vec<HealthIssue>) -> Option<FileFacts> {
+    let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    if size > MAX_METADATA_PARSE_FILE_SIZE_BYTES {
+        issues.push(issue(
+            HealthIssueKind::TooLarge,
+            path,
+            format!("File is {} MiB, metadata is not read above 2 GiB", size / (1024 * 1024)),
+        ));
+        return None;
+    }
+
+    let tagged_file = match read_tagged_file(path) {
+        Ok(f) => f,
+        Err(e) => {
+            issues.push(issue(HealthIssueKind::Unreadable, path, e.to_string()));
+            return None;
+        }
+    };
+
+    let properties = tagged_file.properties();
+    if properties.duration().is_zero() {
+        issues.push(issue(HealthIssueKind::ZeroDuration, path, "Duration is zero"));
+    }
+
+    let tag = tagged_file.primary_tag();
+    let non_empty = |value: Option<String>| value.filter(|v| !v.trim().is_empty());
+    let title = non_empty(tag.and_then(|t| t.title().map(|s| s.to_string())));
+    let artist = non_empty(tag.and_then(|t| t.artist().map(|s| s.to_string())));
+    let album = non_empty(tag.and_then(|t| t.album().map(|s| s.to_string())));
+    let album_artist = non_empty(tag.and_then(|t| t.get_string(&ItemKey::AlbumArtist).map(|s| s.to_string())));
+
+    let missing: Vec<&str> = [("title", title.is_none()), ("artist", artist.is_none()), ("album", album.is_none())]
+        .into_iter()
+        .filter(|(_, is_missing)| *is_missing)
+        .map(|(field, _)| field)
+        .collect();
+    if !missing.is_empty() {
+        issues.push(issue(
+            HealthIssueKind::MissingTags,
+            path,
+            format!("Missing {}", missing.join(", ")),
+        ));
+    }
+
+    let cover = tag.and_then(|t| {
+        t.pictures()
+            .iter()
+            .find(|p| p.pic_type() == PictureType::CoverFront)
+            .or_else(|| t.pictures().first())
+    });
+    match cover {
+        None => issues.push(issue(HealthIssueKind::MissingCover, path, "No embedded cover art")),
+        Some(picture) => match image_dimensions(picture.data()) {
+            Some((width, height)) if width < MIN_COVER_DIMENSION || height < MIN_COVER_DIMENSION => {
+                issues.push(issue(
+                    HealthIssueKind::SmallCover,
+                    path,
+                    format!("Cover art is {}x{}", width, height),
+                ));
+            }
+            None if picture.data().len() < MIN_COVER_BYTES => {
+                issues.push(issue(
+                    HealthIssueKind::SmallCover,
+                    path,
+                    format!("Cover art is only {} bytes", picture.data().len()),
+                ));
+            }
+            _ => {}
+        },
+    }
+
+    Some(FileFacts {
+        path: path.to_path_buf(),
+        album,
+        artist,
+        album_artist,
+        sample_rate: properties.sample_rate(),
+    })
+}
+
+fn check_folder_album_artists(facts: &[FileFacts], issues: &mut Vec<HealthIssue>) {
+    let mut by_folder: BTreeMap<&Path, Vec<&FileFacts>> = BTreeMap::new();
+    for f in facts {
+        if let Some(parent) = f.path.parent() {
+            by_folder.entry(parent).or_default().push(f);
+        }
+    }
+
+    for (folder, files) in by_folder {
+        let values: BTreeSet<&str> = files.iter().filter_map(|f| f.album_artist.as_deref()).collect();
+        let untagged = files.iter().filter(|f| f.album_artist.is_none()).count();
+        if values.len() > 1 {
+            let listed: Vec<&str> = values.into_iter().collect();
+            issues.push(issue(
+                HealthIssueKind::InconsistentAlbumArtist,
+                folder,
+                format!("Album artist differs between tracks: {}", listed.join(", ")),
+            ));
+        } else if !values.is_empty() && untagged > 0 {
+            issues.push(issue(
+                HealthIssueKind::InconsistentAlbumArtist,
+                folder,
+                format!("{} of {} tracks have no album artist", untagged, files.len()),
+            ));
+        }
+    }
+}
+
+fn check_album_sample_rates(facts: &[FileFacts], issues: &mut Vec<HealthIssue>) {
+    let mut albums: HashMap<(String, String), Vec<&FileFacts>> = HashMap::new();
+    for f in facts {
+        let Some(album) = f.album.as_ref() else {
+            continue;
+        };
+        let artist = f.album_artist.as_ref().or(f.artist.as_ref()).cloned().unwrap_or_default();
+        albums
+            .entry((artist.to_lowercase(), album.to_lowercase()))
+            .or_default()
+            .push(f);
+    }
+
+    let mut mixed: Vec<HealthIssue> = albums
+        .into_values()
+        .filter_map(|files| {
+            let rates: BTreeSet<u32> = files.iter().filter_map(|f| f.sample_rate).collect();
+            if rates.len() < 2 {
+                return None;
+            }
+            let first = files.first()?;
+            let folder = first.path.parent().unwrap_or(&first.path);
+            let listed: Vec<String> = rates.iter().map(|r| format!("{} Hz", r)).collect();
+            Some(issue(
+                HealthIssueKind::MixedSampleRates,
+                folder,
+                format!(
+                    "Album '{}' mixes sample rates: {}",
+                    first.album.as_deref().unwrap_or_default(),
+                    listed.join(", ")
+                ),
+            ))
+        })
+        .collect();
+    mixed.sort_by(|a, b| a.path.cmp(&b.path));
+    issues.append(&mut mixed);
+}
+
+fn build_report(path: &str) -> Result<HealthReport, CommandError> {
+    let root = std::fs::canonicalize(path)?;
+    let files = audio_files(&root);
+
+    let mut issues = Vec::new();
+    let facts: Vec<FileFacts> = files.iter().filter_map(|f| inspect_file(f, &mut issues)).collect();
+    check_folder_album_artists(&facts, &mut issues);
+    check_album_sample_rates(&facts, &mut issues);
+
+    let mut counts = BTreeMap::new();
+    for i in &issues {
+        *counts.entry(i.kind).or_insert(0) += 1;
+    }
+    log::info!(
+        target: "scanner",
+        "health report for {}: {} files, {} issues",
+        root.display(),
+        files.len(),
+        issues.len()
+    );
+
+    Ok(HealthReport {
+        root: root.to_string_lossy().to_string(),
+        generated_at: to_unix_seconds(SystemTime::now()),
+        scanned_files: files.len(),
+        counts,
+        issues,
+    })
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn report_to_csv(report: &HealthReport) -> String {
+    let mut out = String::from("kind,path,message\n");
+    for i in &report.issues {
+        out.push_str(&format!(
+            "{},{},{}\n",
+            i.kind.as_str(),
+            csv_field(&i.path),
+            csv_field(&i.message)
+        ));
+    }
+    out
+}
+
+#[tauri::command]
+pub async fn library_health_report(path: String) -> Result<HealthReport, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || build_report(&path)).await?
+}
+
+#[tauri::command]
+pub fn export_health_report(report: HealthReport, format: String) -> Result<String, CommandError> {
+    match format.to_lowercase().as_str() {
+        "json" => Ok(serde_json::to_string_pretty(&report)?),
+        "csv" => Ok(report_to_csv(&report)),
+        other => Err(CommandError::invalid_input(format!("Unsupported export format: {}", other))),
+    }
+}
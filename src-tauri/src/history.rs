@@ -0,0 +1,296 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{LazyLock, Mutex};
+use std::time::SystemTime;
+
+use super::{load_config, save_config, timers, to_unix_seconds, Track};
+use crate::error::CommandError;
+use crate::store;
+
+const HISTORY_FILE: &str = "history.jsonl";
+const HIDDEN_FILE: &str = "history-hidden.json";
+const MAX_PLAYS: usize = 100_000;
+// Trimming drops to this size so the full rewrite happens rarely.
+const TRIMMED_PLAYS: usize = MAX_PLAYS * 9 / 10;
+const SKIP_FRACTION: f64 = 0.5;
+const COMPLETE_FRACTION: f64 = 0.9;
+const DEFAULT_RECENT_LIMIT: usize = 50;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PlayOutcome {
+    Completed,
+    Partial,
+    Skipped,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayRecord {
+    pub(crate) track_id: String,
+    pub(crate) started_at: u64,
+    pub(crate) fraction: f64,
+    pub(crate) outcome: PlayOutcome,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayStats {
+    play_count: u32,
+    skip_count: u32,
+    last_played: Option<u64>,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HistoryRange {
+    from: Option<u64>,
+    to: Option<u64>,
+    track_id: Option<String>,
+    limit: Option<usize>,
+}
+
+#[derive(Default)]
+struct History {
+    plays: Vec<PlayRecord>,
+    stats: HashMap<String, PlayStats>,
+    // Deleted tracks keep their plays but no longer show up in recents.
+    hidden: HashSet<String>,
+    revision: u64,
+}
+
+impl History {
+    fn count(&mut self, play: &PlayRecord) {
+        let stats = self.stats.entry(play.track_id.clone()).or_default();
+        if play.outcome == PlayOutcome::Skipped {
+            stats.skip_count += 1;
+        } else {
+            stats.play_count += 1;
+            stats.last_played = stats.last_played.max(Some(play.started_at));
+        }
+    }
+
+    fn rebuild(&mut self) {
+        self.stats.clear();
+        let plays = std::mem::take(&mut self.plays);
+        for play in &plays {
+            self.count(play);
+        }
+        self.plays = plays;
+        self.revision += 1;
+    }
+}
+
+static HISTORY: LazyLock<Mutex<History>> = LazyLock::new(|| Mutex::new(History::default()));
+
+fn now() -> u64 {
+    to_unix_seconds(SystemTime::now())
+}
+
+fn migrate_recent_tracks(app: &tauri::AppHandle) -> Result<Vec<PlayRecord>, CommandError> {
+    let mut config = load_config(app.clone())?;
+    if config.recent_tracks.is_empty() {
+        return Ok(Vec::new());
+    }
+    let started = now();
+    let plays: Vec<PlayRecord> = config
+        .recent_tracks
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(i, id)| PlayRecord {
+            track_id: id.clone(),
+            started_at: started.saturating_sub(i as u64),
+            fraction: 1.0,
+            outcome: PlayOutcome::Completed,
+        })
+        .collect();
+    for play in &plays {
+        store::append_line(app, HISTORY_FILE, play)?;
+    }
+    config.recent_tracks.clear();
+    save_config(app.clone(), config)?;
+    log::info!(target: "history", "migrated {} recent tracks into play history", plays.len());
+    Ok(plays)
+}
+
+pub(crate) fn init(app: &tauri::AppHandle) {
+    let mut plays: Vec<PlayRecord> = match store::load_lines(app, HISTORY_FILE) {
+        Ok(plays) => plays,
+        Err(e) => {
+            log::warn!(target: "history", "failed to load play history: {}", e);
+            Vec::new()
+        }
+    };
+    match migrate_recent_tracks(app) {
+        Ok(migrated) => plays.extend(migrated),
+        Err(e) => log::warn!(target: "history", "failed to migrate recent tracks: {}", e),
+    }
+    let hidden = store::load(app, HIDDEN_FILE).unwrap_or_else(|e| {
+        log::warn!(target: "history", "failed to load hidden tracks: {}", e);
+        HashSet::new()
+    });
+    if let Ok(mut history) = HISTORY.lock() {
+        history.plays = plays;
+        history.hidden = hidden;
+        history.rebuild();
+    }
+}
+
+pub(crate) fn revision() -> u64 {
+    HISTORY.lock().map(|h| h.revision).unwrap_or(0)
+}
+
+pub(crate) fn plays() -> Vec<PlayRecord> {
+    let mut plays = HISTORY.lock().map(|h| h.plays.clone()).unwrap_or_default();
+    plays.sort_by_key(|p| p.started_at);
+    plays
+}
+
+pub(crate) fn merge_stats(tracks: &mut [Track]) {
+    let Ok(history) = HISTORY.lock() else {
+        return;
+    };
+    for track in tracks {
+        let stats = history.stats.get(&track.canonical_id).copied().unwrap_or_default();
+        track.play_count = stats.play_count;
+        track.skip_count = stats.skip_count;
+        track.last_played = stats.last_played;
+    }
+}
+
+pub(crate) fn relink_tracks(app: &tauri::AppHandle, moved: &HashMap<String, String>) -> Result<(), CommandError> {
+    let mut history = HISTORY.lock().map_err(|_| "history lock poisoned".to_string())?;
+    let mut changed = false;
+    for play in &mut history.plays {
+        if let Some(target) = moved.get(&play.track_id) {
+            play.track_id = target.clone();
+            changed = true;
+        }
+    }
+    if changed {
+        history.rebuild();
+        store::save_lines(app, HISTORY_FILE, &history.plays)?;
+    }
+    Ok(())
+}
+
+// Hides deleted tracks from recents, returning the ids that were newly hidden so the deletion can be undone.
+pub(crate) fn hide_tracks(app: &tauri::AppHandle, track_ids: &[&str]) -> Result<Vec<String>, CommandError> {
+    let mut history = HISTORY.lock().map_err(|_| "history lock poisoned".to_string())?;
+    let hidden: Vec<String> = track_ids
+        .iter()
+        .filter(|id| history.hidden.insert(id.to_string()))
+        .map(|id| id.to_string())
+        .collect();
+    if !hidden.is_empty() {
+        history.revision += 1;
+        store::save(app, HIDDEN_FILE, &history.hidden)?;
+    }
+    Ok(hidden)
+}
+
+pub(crate) fn unhide_tracks(app: &tauri::AppHandle, track_ids: &[String]) -> Result<(), CommandError> {
+    let mut history = HISTORY.lock().map_err(|_| "history lock poisoned".to_string())?;
+    let before = history.hidden.len();
+    history.hidden.retain(|id| !track_ids.contains(id));
+    if history.hidden.len() != before {
+        history.revision += 1;
+        store::save(app, HIDDEN_FILE, &history.hidden)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn record_play(
+    app: tauri::AppHandle,
+    track_id: String,
+    started_at: Option<u64>,
+    fraction: f64,
+) -> Result<PlayStats, CommandError> {
+    if track_id.trim().is_empty() || !fraction.is_finite() {
+        return Err(CommandError::invalid_input("Invalid play record"));
+    }
+    let fraction = fraction.clamp(0.0, 1.0);
+    let play = PlayRecord {
+        track_id,
+        started_at: started_at.unwrap_or_else(now),
+        fraction,
+        outcome: if fraction < SKIP_FRACTION {
+            PlayOutcome::Skipped
+        } else if fraction >= COMPLETE_FRACTION {
+            PlayOutcome::Completed
+        } else {
+            PlayOutcome::Partial
+        },
+    };
+    timers::track_finished(&app, &play.track_id, play.outcome == PlayOutcome::Completed);
+
+    let mut history = HISTORY.lock().map_err(|_| "history lock poisoned".to_string())?;
+    if history.hidden.remove(&play.track_id) {
+        store::save(&app, HIDDEN_FILE, &history.hidden)?;
+    }
+    history.count(&play);
+    history.revision += 1;
+    let stats = history.stats.get(&play.track_id).copied().unwrap_or_default();
+    history.plays.push(play.clone());
+
+    if history.plays.len() > MAX_PLAYS {
+        history.plays.sort_by_key(|p| p.started_at);
+        let excess = history.plays.len() - TRIMMED_PLAYS;
+        history.plays.drain(..excess);
+        history.rebuild();
+        store::save_lines(&app, HISTORY_FILE, &history.plays)?;
+    } else {
+        store::append_line(&app, HISTORY_FILE, &play)?;
+    }
+    Ok(stats)
+}
+
+#[tauri::command]
+pub fn get_history(range: Option<HistoryRange>) -> Result<Vec<PlayRecord>, CommandError> {
+    let range = range.unwrap_or_default();
+    let history = HISTORY.lock().map_err(|_| "history lock poisoned".to_string())?;
+    let mut plays: Vec<PlayRecord> = history
+        .plays
+        .iter()
+        .filter(|p| range.from.is_none_or(|from| p.started_at >= from))
+        .filter(|p| range.to.is_none_or(|to| p.started_at < to))
+        .filter(|p| range.track_id.as_ref().is_none_or(|id| &p.track_id == id))
+        .cloned()
+        .collect();
+    plays.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+    if let Some(limit) = range.limit {
+        plays.truncate(limit);
+    }
+    Ok(plays)
+}
+
+#[tauri::command]
+pub fn get_play_stats(track_ids: Vec<String>) -> Result<HashMap<String, PlayStats>, CommandError> {
+    let history = HISTORY.lock().map_err(|_| "history lock poisoned".to_string())?;
+    Ok(track_ids
+        .into_iter()
+        .map(|id| {
+            let stats = history.stats.get(&id).copied().unwrap_or_default();
+            (id, stats)
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub fn get_recent_tracks(limit: Option<usize>) -> Result<Vec<String>, CommandError> {
+    let limit = limit.unwrap_or(DEFAULT_RECENT_LIMIT);
+    let history = HISTORY.lock().map_err(|_| "history lock poisoned".to_string())?;
+    let mut plays: Vec<&PlayRecord> = history.plays.iter().collect();
+    plays.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+
+    let mut seen = HashSet::new();
+    Ok(plays
+        .into_iter()
+        .filter(|p| !history.hidden.contains(&p.track_id))
+        .filter(|p| seen.insert(p.track_id.as_str()))
+        .take(limit)
+        .map(|p| p.track_id.clone())
+        .collect())
+}
@@ -0,0 +1,85 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::prelude::*;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::{history, load_config, playlists, queue, ratings, resume, save_config, Track};
+use crate::decode;
+use crate::error::CommandError;
+
+const IDENTITY_PACKETS: usize = 64;
+
+pub(crate) fn content_identity(path: &Path) -> Option<String> {
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let mut session = decode::open(path, false).ok()?;
+        let mut hasher = Sha256::new();
+        hasher.update(session.n_frames.unwrap_or(0).to_le_bytes());
+        let mut packets = 0;
+        while packets < IDENTITY_PACKETS {
+            let Ok(packet) = session.format.next_packet() else {
+                break;
+            };
+            if packet.track_id() != session.track_id {
+                continue;
+            }
+            hasher.update(packet.buf());
+            packets += 1;
+        }
+        (packets > 0).then(|| format!("audio:{}", URL_SAFE_NO_PAD.encode(&hasher.finalize()[..18])))
+    }));
+    result.ok().flatten()
+}
+
+pub(crate) fn mbid_identity(recording_id: &str) -> Option<String> {
+    let id = recording_id.trim().to_lowercase();
+    (!id.is_empty()).then(|| format!("mbid:{}", id))
+}
+
+// Favorites, playlists, history, ratings, the queue and resume points written while local
+// tracks were keyed by path are moved over to the identity of the file at that path. Identical
+// copies (or files sharing a recording MBID) stay keyed by their path so their references stay apart.
+pub(crate) fn adopt_identities(app: &tauri::AppHandle, tracks: &mut [Track]) -> Result<(), CommandError> {
+    let mut copies: HashMap<String, usize> = HashMap::new();
+    for track in tracks.iter().filter(|t| t.source == "local") {
+        if let Some(identity) = &track.identity {
+            *copies.entry(identity.clone()).or_default() += 1;
+        }
+    }
+    let mut adopted = HashMap::new();
+    for track in tracks.iter_mut().filter(|t| t.source == "local") {
+        let Some(identity) = &track.identity else {
+            continue;
+        };
+        if copies.get(identity).is_some_and(|count| *count > 1) {
+            track.canonical_id = track.id.clone();
+        } else {
+            track.canonical_id = identity.clone();
+            adopted.insert(track.id.clone(), identity.clone());
+        }
+    }
+    if adopted.is_empty() {
+        return Ok(());
+    }
+    relink_tracks(app, &adopted)
+}
+
+pub(crate) fn relink_tracks(app: &tauri::AppHandle, moved: &HashMap<String, String>) -> Result<(), CommandError> {
+    let mut config = load_config(app.clone())?;
+    let mut changed = false;
+    for id in config.favorites.iter_mut().chain(config.recent_tracks.iter_mut()) {
+        if let Some(target) = moved.get(id) {
+            *id = target.clone();
+            changed = true;
+        }
+    }
+    if changed {
+        save_config(app.clone(), config)?;
+    }
+    playlists::relink_tracks(app, moved)?;
+    history::relink_tracks(app, moved)?;
+    ratings::relink_tracks(app, moved)?;
+    queue::relink_tracks(app, moved)?;
+    resume::relink_tracks(app, moved)?;
+    Ok(())
+}
@@ -0,0 +1,145 @@
+use std::path::Path;
+
+use super::Track;
+
+const FIELDS: [&str; 8] = ["artist", "albumartist", "album", "title", "track", "disc", "year", "genre"];
+
+enum Piece {
+    Literal(String),
+    Field(&'static str),
+}
+
+pub(crate) fn default_path_patterns() -> Vec<String> {
+    vec![
+        "{artist}/{album}/{track} - {title}".to_string(),
+        "{artist}/{album}/{track}. {title}".to_string(),
+        "{artist}/{album}/{track} {title}".to_string(),
+        "{artist} - {title}".to_string(),
+    ]
+}
+
+fn parse_segment(segment: &str) -> Option<Vec<Piece>> {
+    let mut pieces = Vec::new();
+    let mut rest = segment;
+    while !rest.is_empty() {
+        match rest.find('{') {
+            Some(0) => {
+                let end = rest.find('}')?;
+                let name = rest[1..end].trim().to_lowercase();
+                let field = FIELDS.iter().find(|f| **f == name)?;
+                if matches!(pieces.last(), Some(Piece::Field(_))) {
+                    return None;
+                }
+                pieces.push(Piece::Field(*field));
+                rest = &rest[end + 1..];
+            }
+            Some(start) => {
+                pieces.push(Piece::Literal(rest[..start].to_string()));
+                rest = &rest[start..];
+            }
+            None => {
+                pieces.push(Piece::Literal(rest.to_string()));
+                rest = "";
+            }
+        }
+    }
+    Some(pieces)
+}
+
+fn valid_value(field: &str, value: &str) -> bool {
+    let value = value.trim();
+    if value.is_empty() {
+        return false;
+    }
+    match field {
+        "track" | "disc" => value.len() <= 3 && value.chars().all(|c| c.is_ascii_digit()),
+        "year" => value.len() == 4 && value.chars().all(|c| c.is_ascii_digit()),
+        _ => true,
+    }
+}
+
+fn match_pieces(pieces: &[Piece], text: &str, out: &mut Vec<(&'static str, String)>) -> bool {
+    match pieces.split_first() {
+        None => text.is_empty(),
+        Some((Piece::Literal(literal), rest)) => match text.strip_prefix(literal.as_str()) {
+            Some(remaining) => match_pieces(rest, remaining, out),
+            None => false,
+        },
+        Some((Piece::Field(field), rest)) => {
+            let ends = text
+                .char_indices()
+                .skip(1)
+                .map(|(idx, _)| idx)
+                .chain(std::iter::once(text.len()));
+            for end in ends {
+                let value = &text[..end];
+                if !valid_value(field, value) {
+                    continue;
+                }
+                out.push((*field, value.trim().to_string()));
+                if match_pieces(rest, &text[end..], out) {
+                    return true;
+                }
+                out.pop();
+            }
+            false
+        }
+    }
+}
+
+fn match_pattern(pattern: &str, components: &[String]) -> Option<Vec<(&'static str, String)>> {
+    let segments: Vec<&str> = pattern
+        .split(['/', '\\'])
+        .filter(|s| !s.is_empty())
+        .collect();
+    if segments.is_empty() || segments.len() > components.len() {
+        return None;
+    }
+
+    let tail = &components[components.len() - segments.len()..];
+    let mut fields = Vec::new();
+    for (segment, component) in segments.iter().zip(tail) {
+        let pieces = parse_segment(segment)?;
+        if !match_pieces(&pieces, component, &mut fields) {
+            return None;
+        }
+    }
+    Some(fields)
+}
+
+pub(crate) fn fill_missing_fields(track: &mut Track, relative: &Path, patterns: &[String], missing: &[&str]) {
+    let mut components: Vec<String> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+    if let Some(last) = components.last_mut() {
+        let stem = Path::new(last.as_str())
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string());
+        if let Some(stem) = stem {
+            *last = stem;
+        }
+    }
+
+    let Some(fields) = patterns.iter().find_map(|p| match_pattern(p, &components)) else {
+        return;
+    };
+
+    for (field, value) in fields {
+        if !missing.contains(&field) || track.inferred_fields.iter().any(|f| f == field) {
+            continue;
+        }
+        match field {
+            "artist" => track.artist = value,
+            "albumartist" => track.album_artist = Some(value),
+            "album" => track.album = value,
+            "title" => track.title = value,
+            "genre" => track.genre = Some(value),
+            "track" => track.track_number = value.parse().ok(),
+            "disc" => track.disc_number = value.parse().ok(),
+            "year" => track.year = value.parse().ok(),
+            _ => continue,
+        }
+        track.inferred_fields.push(field.to_string());
+    }
+}
@@ -1,7 +1,7 @@
 use base64::prelude::*;
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use discord_rich_presence::{activity, DiscordIpc, DiscordIpcClient};
-use lofty::{Accessor, AudioFile, Probe, TaggedFileExt};
+use lofty::{Accessor, AudioFile, ItemKey, Probe, TaggedFileExt};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use tiny_http::{Header, Method, Response, StatusCode};
@@ -14,11 +14,42 @@ use std::sync::{Arc, LazyLock, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
 use walkdir::WalkDir;
 
+mod analysis;
+mod browse;
+mod chapters;
+mod decode;
+mod deletion;
+mod error;
+mod health;
+mod history;
+mod identity;
+mod infer;
+mod listening;
+mod logging;
 mod navidrome;
+mod organize;
+mod path_policy;
+mod playlists;
+mod query;
+mod queue;
+mod radio;
+mod ratings;
+mod resume;
+mod smart;
+mod store;
+mod tags;
+mod timers;
+mod verify;
+mod volumes;
+mod waveform;
+
+use error::CommandError;
 
 const DISCORD_CLIENT_ID: &str = "1463766565664067594";
+const AUDIO_EXTENSIONS: [&str; 7] = ["mp3", "wav", "ogg", "flac", "m4a", "m4b", "aac"];
+const MAX_METADATA_PARSE_FILE_SIZE_BYTES: u64 = 2 * 1024 * 1024 * 1024;
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 struct Track {
     id: String,
@@ -31,6 +62,42 @@ struct Track {
     audio_url: String,
     folder_id: Option<String>,
     source: String,
+    #[serde(default)]
+    album_artist: Option<String>,
+    #[serde(default)]
+    track_number: Option<u32>,
+    #[serde(default)]
+    disc_number: Option<u32>,
+    #[serde(default)]
+    year: Option<u32>,
+    #[serde(default)]
+    genre: Option<String>,
+    #[serde(default)]
+    composer: Option<String>,
+    #[serde(default)]
+    inferred_fields: Vec<String>,
+    #[serde(default)]
+    format: Option<String>,
+    #[serde(default)]
+    play_count: u32,
+    #[serde(default)]
+    skip_count: u32,
+    #[serde(default)]
+    last_played: Option<u64>,
+    #[serde(default)]
+    rating: Option<f32>,
+    #[serde(default)]
+    identity: Option<String>,
+    #[serde(default)]
+    available: Option<bool>,
+    #[serde(default)]
+    chapter_count: u32,
+    #[serde(default)]
+    bpm: Option<f32>,
+    #[serde(default)]
+    key: Option<String>,
+    #[serde(default)]
+    camelot: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -42,9 +109,12 @@ struct MusicFolder {
     path: String,
     track_count: usize,
     source: String,
+    // Members of virtual folders, by track id, so their tracks are not duplicated.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    track_ids: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 struct ScanResult {
     tracks: Vec<Track>,
@@ -97,6 +167,22 @@ struct AppConfig {
     navidrome_servers: Vec<NavidromeServerConfig>,
     #[serde(default)]
     playlist_collage_covers: bool,
+    #[serde(default)]
+    infer_from_paths: bool,
+    #[serde(default)]
+    path_patterns: Option<Vec<String>>,
+    #[serde(default)]
+    log_level: Option<String>,
+    #[serde(default)]
+    smart_playlists: Vec<smart::SmartPlaylist>,
+    #[serde(default)]
+    folder_volumes: Vec<volumes::FolderVolume>,
+    #[serde(default = "default_resume_threshold_minutes")]
+    resume_threshold_minutes: u32,
+    #[serde(default)]
+    audiobook_folders: Vec<String>,
+    #[serde(default)]
+    alarms: Vec<timers::Alarm>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -133,71 +219,83 @@ fn default_normalize() -> bool {
     false
 }
 
-#[tauri::command]
-fn create_folder(name: String, parent_path: String) -> Result<(), String> {
-    let path = Path::new(&parent_path).join(name);
-    std::fs::create_dir_all(path).map_err(|e| e.to_string())?;
-    Ok(())
+fn default_resume_threshold_minutes() -> u32 {
+    20
 }
 
 #[tauri::command]
-fn delete_track(path: String) -> Result<(), String> {
-    std::fs::remove_file(path).map_err(|e| e.to_string())
+fn create_folder(app: tauri::AppHandle, name: String, parent_path: String) -> Result<(), CommandError> {
+    let path = path_policy::resolve_new_child(&app, &parent_path, &name)?;
+    std::fs::create_dir_all(path)?;
+    Ok(())
 }
 
 #[tauri::command]
-fn show_in_explorer(path: String) -> Result<(), String> {
+fn show_in_explorer(app: tauri::AppHandle, path: String) -> Result<(), CommandError> {
+    path_policy::resolve_existing(&app, &path)?;
     #[cfg(target_os = "windows")]
     {
         std::process::Command::new("explorer")
             .args(["/select,", &path])
-            .spawn()
-            .map_err(|e| e.to_string())?;
+            .spawn()?;
     }
     #[cfg(target_os = "macos")]
     {
         std::process::Command::new("open")
             .args(["-R", &path])
-            .spawn()
-            .map_err(|e| e.to_string())?;
+            .spawn()?;
     }
     #[cfg(target_os = "linux")]
     {
         std::process::Command::new("xdg-open")
             .arg(std::path::Path::new(&path).parent().unwrap_or(std::path::Path::new("/")))
-            .spawn()
-            .map_err(|e| e.to_string())?;
+            .spawn()?;
     }
     Ok(())
 }
 
+// Held across load-modify-save cycles that run off the main thread, such as volume reconciliation.
+static CONFIG_LOCK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
+
+fn lock_config() -> std::sync::MutexGuard<'static, ()> {
+    CONFIG_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
 #[tauri::command]
-fn save_config(app: tauri::AppHandle, config: AppConfig) -> Result<(), String> {
+fn save_config(app: tauri::AppHandle, mut config: AppConfig) -> Result<(), CommandError> {
+    let _guard = lock_config();
+    // Volume records are owned by the backend; callers may hold a copy loaded before the last reconcile.
+    if let Ok(stored) = load_config(app.clone()) {
+        config.folder_volumes = stored.folder_volumes;
+    }
+    volumes::follow_moves(&mut config.music_folders);
+    write_config(&app, &config)
+}
+
+fn write_config(app: &tauri::AppHandle, config: &AppConfig) -> Result<(), CommandError> {
     use tauri::Manager;
-    let config_path = app.path().resolve("config.json", tauri::path::BaseDirectory::AppConfig)
-        .map_err(|e| e.to_string())?;
+    let config_path = app.path().resolve("config.json", tauri::path::BaseDirectory::AppConfig)?;
     
     if let Some(parent) = config_path.parent() {
-        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        std::fs::create_dir_all(parent)?;
     }
 
-    let json = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
-    std::fs::write(config_path, json).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(config)?;
+    std::fs::write(config_path, json)?;
     Ok(())
 }
 
 #[tauri::command]
-fn load_config(app: tauri::AppHandle) -> Result<AppConfig, String> {
+fn load_config(app: tauri::AppHandle) -> Result<AppConfig, CommandError> {
     use tauri::Manager;
-    let config_path = app.path().resolve("config.json", tauri::path::BaseDirectory::AppConfig)
-        .map_err(|e| e.to_string())?;
+    let config_path = app.path().resolve("config.json", tauri::path::BaseDirectory::AppConfig)?;
 
     if !config_path.exists() {
         return Ok(AppConfig::default());
     }
 
-    let json = std::fs::read_to_string(config_path).map_err(|e| e.to_string())?;
-    let config: AppConfig = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+    let json = std::fs::read_to_string(config_path)?;
+    let config: AppConfig = serde_json::from_str(&json)?;
     Ok(config)
 }
 
@@ -306,15 +404,15 @@ fn parse_color_ini(input: &str) -> std::collections::HashMap<String, String> {
     std::collections::HashMap::new()
 }
 
-fn extract_cover_bytes(path: &str) -> Result<Option<CoverEntry>, String> {
+fn extract_cover_bytes(path: &str) -> Result<Option<CoverEntry>, CommandError> {
     const MAX_COVER_ART_BYTES: usize = 5 * 1024 * 1024;
     let tagged_file = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
         Probe::open(Path::new(path))
-            .map_err(|e| e.to_string())?
+            .map_err(CommandError::from)?
             .read()
-            .map_err(|e| e.to_string())
+            .map_err(CommandError::from)
     }))
-    .map_err(|_| "panic while reading cover art".to_string())??;
+    .map_err(|_| CommandError::parse("panic while reading cover art"))??;
     let tag = tagged_file.primary_tag();
     if let Some(tag) = tag {
         if let Some(picture) = tag.pictures().first() {
@@ -330,14 +428,20 @@ fn extract_cover_bytes(path: &str) -> Result<Option<CoverEntry>, String> {
     Ok(None)
 }
 
-fn ensure_cover_server() -> Result<CoverServerState, String> {
+fn respond_cover_request<R: std::io::Read>(request: tiny_http::Request, response: Response<R>) {
+    if let Err(e) = request.respond(response) {
+        log::debug!(target: "cover_server", "failed to send response: {}", e);
+    }
+}
+
+fn ensure_cover_server() -> Result<CoverServerState, CommandError> {
     let mut guard = COVER_SERVER_STATE.lock().map_err(|_| "cover server mutex poisoned".to_string())?;
     if let Some(state) = guard.as_ref() {
         return Ok(state.clone());
     }
 
-    let listener = TcpListener::bind("127.0.0.1:0").map_err(|e| e.to_string())?;
-    let addr = listener.local_addr().map_err(|e| e.to_string())?;
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
     let server = tiny_http::Server::from_listener(listener, None).map_err(|e| e.to_string())?;
 
     let entries: Arc<Mutex<HashMap<String, CoverEntry>>> = Arc::new(Mutex::new(HashMap::new()));
@@ -346,14 +450,16 @@ fn ensure_cover_server() -> Result<CoverServerState, String> {
     std::thread::spawn(move || {
         for request in server.incoming_requests() {
             if request.method() != &Method::Get {
-                let _ = request.respond(Response::empty(StatusCode(405)));
+                log::debug!(target: "cover_server", "rejected {} {}", request.method(), request.url());
+                respond_cover_request(request, Response::empty(StatusCode(405)));
                 continue;
             }
 
             let url = request.url().to_string();
             let id = url.strip_prefix("/cover/").unwrap_or("");
             if id.is_empty() || id.contains('/') {
-                let _ = request.respond(Response::empty(StatusCode(404)));
+                log::debug!(target: "cover_server", "unknown path {}", url);
+                respond_cover_request(request, Response::empty(StatusCode(404)));
                 continue;
             }
 
@@ -366,64 +472,65 @@ fn ensure_cover_server() -> Result<CoverServerState, String> {
                 if let Ok(h) = Header::from_bytes("Cache-Control", "public, max-age=604800".as_bytes()) {
                     resp = resp.with_header(h);
                 }
-                let _ = request.respond(resp);
+                respond_cover_request(request, resp);
             } else {
-                let _ = request.respond(Response::empty(StatusCode(404)));
+                log::debug!(target: "cover_server", "cover {} not registered", id);
+                respond_cover_request(request, Response::empty(StatusCode(404)));
             }
         }
+        log::warn!(target: "cover_server", "cover server stopped accepting requests");
     });
 
     let state = CoverServerState {
         base_url: format!("http://127.0.0.1:{}", addr.port()),
         entries,
     };
+    log::info!(target: "cover_server", "listening on {}", state.base_url);
     *guard = Some(state.clone());
     Ok(state)
 }
 
 #[tauri::command]
-fn load_color_themes(app: tauri::AppHandle) -> Result<ColorIniThemes, String> {
+fn load_color_themes(app: tauri::AppHandle) -> Result<ColorIniThemes, CommandError> {
     use tauri::Manager;
     let ini_path = app
         .path()
-        .resolve("color.ini", tauri::path::BaseDirectory::AppConfig)
-        .map_err(|e| e.to_string())?;
+        .resolve("color.ini", tauri::path::BaseDirectory::AppConfig)?;
 
     if let Some(parent) = ini_path.parent() {
-        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        std::fs::create_dir_all(parent)?;
     }
 
     if !ini_path.exists() {
-        std::fs::write(&ini_path, default_color_ini()).map_err(|e| e.to_string())?;
+        std::fs::write(&ini_path, default_color_ini())?;
     }
 
-    let raw = std::fs::read_to_string(&ini_path).map_err(|e| e.to_string())?;
+    let raw = std::fs::read_to_string(&ini_path)?;
     let parsed = parse_color_ini_themes(&raw);
     if parsed.themes.is_empty() {
-        std::fs::write(&ini_path, default_color_ini()).map_err(|e| e.to_string())?;
-        let raw = std::fs::read_to_string(&ini_path).map_err(|e| e.to_string())?;
+        std::fs::write(&ini_path, default_color_ini())?;
+        let raw = std::fs::read_to_string(&ini_path)?;
         return Ok(parse_color_ini_themes(&raw));
     }
     Ok(parsed)
 }
 
 #[tauri::command]
-fn load_color_ini(app: tauri::AppHandle) -> Result<std::collections::HashMap<String, String>, String> {
+fn load_color_ini(app: tauri::AppHandle) -> Result<std::collections::HashMap<String, String>, CommandError> {
     use tauri::Manager;
     let ini_path = app
         .path()
-        .resolve("color.ini", tauri::path::BaseDirectory::AppConfig)
-        .map_err(|e| e.to_string())?;
+        .resolve("color.ini", tauri::path::BaseDirectory::AppConfig)?;
 
     if let Some(parent) = ini_path.parent() {
-        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        std::fs::create_dir_all(parent)?;
     }
 
     if !ini_path.exists() {
-        std::fs::write(&ini_path, default_color_ini()).map_err(|e| e.to_string())?;
+        std::fs::write(&ini_path, default_color_ini())?;
     }
 
-    let raw = std::fs::read_to_string(ini_path).map_err(|e| e.to_string())?;
+    let raw = std::fs::read_to_string(ini_path)?;
     Ok(parse_color_ini(&raw))
 }
 
@@ -440,7 +547,9 @@ struct DiscordRpcInner {
 
 fn discord_rpc_disconnect(inner: &mut DiscordRpcInner) {
     if let Some(mut client) = inner.client.take() {
-        let _ = client.close();
+        if let Err(e) = client.close() {
+            log::debug!(target: "discord", "failed to close IPC connection: {}", e);
+        }
     }
     inner.client_id = None;
 }
@@ -448,7 +557,7 @@ fn discord_rpc_disconnect(inner: &mut DiscordRpcInner) {
 fn discord_rpc_ensure_connected<'a>(
     inner: &'a mut DiscordRpcInner,
     client_id: &str,
-) -> Result<&'a mut DiscordIpcClient, String> {
+) -> Result<&'a mut DiscordIpcClient, CommandError> {
     let needs_reconnect = inner.client.is_none()
         || match inner.client_id.as_deref() {
             Some(existing) => existing != client_id,
@@ -458,7 +567,11 @@ fn discord_rpc_ensure_connected<'a>(
     if needs_reconnect {
         discord_rpc_disconnect(inner);
         let mut client = DiscordIpcClient::new(client_id);
-        client.connect().map_err(|e| e.to_string())?;
+        client.connect().map_err(|e| {
+            log::warn!(target: "discord", "failed to connect to Discord: {}", e);
+            e.to_string()
+        })?;
+        log::info!(target: "discord", "connected to Discord IPC");
         inner.client_id = Some(client_id.to_string());
         inner.client = Some(client);
     }
@@ -467,17 +580,19 @@ fn discord_rpc_ensure_connected<'a>(
 }
 
 #[tauri::command]
-fn discord_rpc_clear(state: tauri::State<DiscordRpcState>) -> Result<(), String> {
+fn discord_rpc_clear(state: tauri::State<DiscordRpcState>) -> Result<(), CommandError> {
     let mut guard = state.inner.lock().map_err(|_| "discord mutex poisoned".to_string())?;
     if let Some(client) = guard.client.as_mut() {
-        let _ = client.clear_activity();
+        if let Err(e) = client.clear_activity() {
+            log::debug!(target: "discord", "failed to clear activity: {}", e);
+        }
     }
     discord_rpc_disconnect(&mut guard);
     Ok(())
 }
 
 #[tauri::command]
-fn discord_rpc_connect(state: tauri::State<DiscordRpcState>) -> Result<(), String> {
+fn discord_rpc_connect(state: tauri::State<DiscordRpcState>) -> Result<(), CommandError> {
     let mut guard = state.inner.lock().map_err(|_| "discord mutex poisoned".to_string())?;
     let _ = discord_rpc_ensure_connected(&mut guard, DISCORD_CLIENT_ID)?;
     Ok(())
@@ -493,7 +608,7 @@ fn discord_rpc_set_activity(
     duration_ms: u64,
     position_ms: u64,
     is_playing: bool,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let _ = album;
     let _ = cover_url;
     let mut guard = state.inner.lock().map_err(|_| "discord mutex poisoned".to_string())?;
@@ -531,40 +646,44 @@ fn discord_rpc_set_activity(
     let payload = activity_payload.clone();
     match client.set_activity(payload) {
         Ok(()) => Ok(()),
-        Err(_) => {
+        Err(e) => {
+            log::info!(target: "discord", "set_activity failed, reconnecting: {}", e);
             discord_rpc_disconnect(&mut guard);
             let client = discord_rpc_ensure_connected(&mut guard, DISCORD_CLIENT_ID)?;
-            client.set_activity(activity_payload).map_err(|e| e.to_string())
+            client.set_activity(activity_payload).map_err(|e| {
+                log::warn!(target: "discord", "set_activity failed after reconnect: {}", e);
+                CommandError::internal(e.to_string())
+            })
         }
     }
 }
 
 #[tauri::command]
-fn minimize_window(window: tauri::Window) -> Result<(), String> {
-    window.minimize().map_err(|e| e.to_string())
+fn minimize_window(window: tauri::Window) -> Result<(), CommandError> {
+    Ok(window.minimize()?)
 }
 
 #[tauri::command]
-fn close_window(window: tauri::Window) -> Result<(), String> {
-    window.close().map_err(|e| e.to_string())
+fn close_window(window: tauri::Window) -> Result<(), CommandError> {
+    Ok(window.close()?)
 }
 
 #[tauri::command]
-fn set_window_decorations(window: tauri::Window, enabled: bool) -> Result<(), String> {
-    window.set_decorations(enabled).map_err(|e| e.to_string())
+fn set_window_decorations(window: tauri::Window, enabled: bool) -> Result<(), CommandError> {
+    Ok(window.set_decorations(enabled)?)
 }
 
 #[tauri::command]
-fn exit_app(app: tauri::AppHandle) -> Result<(), String> {
+fn exit_app(app: tauri::AppHandle) -> Result<(), CommandError> {
     app.exit(0);
     Ok(())
 }
 
 #[tauri::command]
-fn add_music_folder(app: tauri::AppHandle, path: String) -> Result<Vec<String>, String> {
+fn add_music_folder(app: tauri::AppHandle, path: String) -> Result<Vec<String>, CommandError> {
     let mut config = load_config(app.clone())?;
     
-    let new_path_buf = std::fs::canonicalize(&path).map_err(|e| e.to_string())?;
+    let new_path_buf = std::fs::canonicalize(&path)?;
     
     let mut current_paths: Vec<PathBuf> = config.music_folders.iter()
         .filter_map(|p| std::fs::canonicalize(p).ok())
@@ -589,20 +708,25 @@ fn add_music_folder(app: tauri::AppHandle, path: String) -> Result<Vec<String>,
         .map(|p| p.to_string_lossy().into_owned())
         .collect();
         
-    save_config(app, config.clone())?;
-    
+    save_config(app.clone(), config.clone())?;
+    if let Err(e) = volumes::reconcile(&app) {
+        log::warn!(target: "volumes", "failed to record volume for {}: {}", path, e);
+    }
+
     Ok(config.music_folders)
 }
 
 #[tauri::command]
-fn prune_music_folders(app: tauri::AppHandle) -> Result<Vec<String>, String> {
+fn prune_music_folders(app: tauri::AppHandle) -> Result<Vec<String>, CommandError> {
+    if let Err(e) = volumes::reconcile(&app) {
+        log::warn!(target: "volumes", "failed to reconcile music folders: {}", e);
+    }
     let mut config = load_config(app.clone())?;
-    
+
+    // Missing folders are kept: they are usually unmounted drives whose tracks stay listed as offline.
     let mut valid_paths = Vec::new();
     for p in &config.music_folders {
-        if let Ok(canon) = std::fs::canonicalize(p) {
-            valid_paths.push(canon);
-        }
+        valid_paths.push(std::fs::canonicalize(p).unwrap_or_else(|_| PathBuf::from(p)));
     }
     
     valid_paths.sort();
@@ -631,16 +755,23 @@ struct CachedTrack {
     size: u64,
 }
 
+#[derive(Clone, Default, PartialEq)]
+struct PathInference {
+    root: PathBuf,
+    patterns: Vec<String>,
+}
+
 #[derive(Default)]
 struct CachedLibrary {
     tracks: HashMap<String, CachedTrack>,
+    inference: PathInference,
 }
 
 static SCAN_CACHE: LazyLock<Mutex<HashMap<String, CachedLibrary>>> =
     LazyLock::new(|| Mutex::new(HashMap::new()));
 
 #[tauri::command]
-fn remove_music_folder(app: tauri::AppHandle, path: String) -> Result<Vec<String>, String> {
+fn remove_music_folder(app: tauri::AppHandle, path: String) -> Result<Vec<String>, CommandError> {
     let mut config = load_config(app.clone())?;
     
     if let Ok(target_canon) = std::fs::canonicalize(&path) {
@@ -654,16 +785,42 @@ fn remove_music_folder(app: tauri::AppHandle, path: String) -> Result<Vec<String
     } else {
          config.music_folders.retain(|p| p != &path);
     }
+
+    let root_key = std::fs::canonicalize(&path)
+        .unwrap_or_else(|_| PathBuf::from(&path))
+        .to_string_lossy()
+        .to_lowercase();
+    volumes::forget_root(&app, &root_key);
     
     save_config(app, config.clone())?;
     Ok(config.music_folders)
 }
 
 #[tauri::command]
-async fn scan_music_library(path: String) -> Result<ScanResult, String> {
-    tauri::async_runtime::spawn_blocking(move || scan_music_library_blocking(path))
-        .await
-        .map_err(|e| e.to_string())?
+async fn scan_music_library(app: tauri::AppHandle, path: String) -> Result<ScanResult, CommandError> {
+    let config = load_config(app.clone())?;
+    let patterns = if config.infer_from_paths {
+        config.path_patterns.unwrap_or_else(infer::default_path_patterns)
+    } else {
+        Vec::new()
+    };
+    tauri::async_runtime::spawn_blocking(move || scan_music_library_blocking(&app, path, patterns)).await?
+}
+
+fn audio_files(root: &Path) -> Vec<PathBuf> {
+    WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| {
+            e.path()
+                .extension()
+                .map(|ext| AUDIO_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()))
+                .unwrap_or(false)
+        })
+        .map(|e| e.into_path())
+        .collect()
 }
 
 fn to_unix_seconds(time: SystemTime) -> u64 {
@@ -676,13 +833,13 @@ fn get_file_stamp(path: &Path) -> Option<(u64, u64)> {
     Some((modified, meta.len()))
 }
 
-fn parse_track_metadata(entry_path: &Path, folder_id: Option<String>) -> Track {
+fn read_track_metadata(entry_path: &Path, folder_id: Option<String>) -> (Track, Vec<&'static str>) {
+    const ALL_FIELDS: [&str; 8] = ["title", "artist", "albumartist", "album", "track", "disc", "year", "genre"];
     let entry_path_str = entry_path.to_string_lossy().to_string();
 
-    const MAX_METADATA_PARSE_FILE_SIZE_BYTES: u64 = 2 * 1024 * 1024 * 1024;
     if let Ok(meta) = std::fs::metadata(entry_path) {
         if meta.len() > MAX_METADATA_PARSE_FILE_SIZE_BYTES {
-            return Track {
+            let track = Track {
                 id: entry_path_str.clone(),
                 canonical_id: entry_path_str.clone(),
                 title: entry_path
@@ -697,7 +854,9 @@ fn parse_track_metadata(entry_path: &Path, folder_id: Option<String>) -> Track {
                 audio_url: entry_path_str,
                 folder_id,
                 source: "local".to_string(),
+                ..Default::default()
             };
+            return (track, ALL_FIELDS.to_vec());
         }
     }
 
@@ -716,34 +875,80 @@ fn parse_track_metadata(entry_path: &Path, folder_id: Option<String>) -> Track {
     match tagged_file {
         Ok(tagged_file) => {
             let tag = tagged_file.primary_tag();
-            let title = entry_path
-                .file_stem()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string();
-            let artist = tag
-                .and_then(|t| t.artist().map(|s| s.to_string()))
-                .unwrap_or("Unknown Artist".to_string());
-            let album = tag
-                .and_then(|t| t.album().map(|s| s.to_string()))
-                .unwrap_or("Unknown Album".to_string());
+            let title = tag
+                .and_then(|t| t.title().map(|s| s.to_string()))
+                .filter(|s| !s.trim().is_empty());
+            let artist = tag.and_then(|t| t.artist().map(|s| s.to_string())).filter(|s| !s.trim().is_empty());
+            let album = tag.and_then(|t| t.album().map(|s| s.to_string())).filter(|s| !s.trim().is_empty());
+            let album_artist = tag
+                .and_then(|t| t.get_string(&ItemKey::AlbumArtist).map(|s| s.to_string()))
+                .filter(|s| !s.trim().is_empty());
+            let track_number = tag.and_then(|t| t.track());
+            let disc_number = tag.and_then(|t| t.disk());
+            let year = tag.and_then(|t| t.year());
+            let genre = tag.and_then(|t| t.genre().map(|s| s.to_string())).filter(|s| !s.trim().is_empty());
+            let composer = tag.and_then(|t| t.get_string(&ItemKey::Composer).map(|s| s.to_string()));
+            let rating = tag.and_then(ratings::rating_from_tag);
+            let bpm = tag.and_then(analysis::bpm_from_tag);
+            let key = tag.and_then(analysis::key_from_tag).map(str::to_string);
+            let identity = tag
+                .and_then(|t| t.get_string(&ItemKey::MusicBrainzRecordingId))
+                .and_then(identity::mbid_identity);
+
+            let missing: Vec<&'static str> = [
+                ("title", title.is_none()),
+                ("artist", artist.is_none()),
+                ("albumartist", album_artist.is_none()),
+                ("album", album.is_none()),
+                ("track", track_number.is_none()),
+                ("disc", disc_number.is_none()),
+                ("year", year.is_none()),
+                ("genre", genre.is_none()),
+            ]
+            .into_iter()
+            .filter(|(_, is_missing)| *is_missing)
+            .map(|(field, _)| field)
+            .collect();
+
             let duration = tagged_file.properties().duration().as_secs();
-            Track {
+            let chapter_count = chapters::chapter_count(entry_path, &tagged_file);
+            let mut track = Track {
                 id: entry_path_str.clone(),
                 canonical_id: entry_path_str.clone(),
-                title,
-                artist,
-                album,
+                title: title.unwrap_or_else(|| {
+                    entry_path
+                        .file_stem()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string()
+                }),
+                artist: artist.unwrap_or("Unknown Artist".to_string()),
+                album: album.unwrap_or("Unknown Album".to_string()),
                 duration,
                 cover_url: None,
                 audio_url: entry_path_str,
                 folder_id,
                 source: "local".to_string(),
+                album_artist,
+                track_number,
+                disc_number,
+                year,
+                genre,
+                composer,
+                rating,
+                identity,
+                bpm,
+                chapter_count,
+                ..Default::default()
+            };
+            if let Some(key) = key {
+                analysis::set_key(&mut track, &key);
             }
+            (track, missing)
         }
         Err(e) => {
-            eprintln!("Error reading file {:?}: {}", entry_path, e);
-            Track {
+            log::warn!(target: "scanner", "failed to read metadata from {}: {}", entry_path.display(), e);
+            let track = Track {
                 id: entry_path_str.clone(),
                 canonical_id: entry_path_str.clone(),
                 title: entry_path
@@ -758,24 +963,62 @@ fn parse_track_metadata(entry_path: &Path, folder_id: Option<String>) -> Track {
                 audio_url: entry_path_str,
                 folder_id,
                 source: "local".to_string(),
-            }
+                ..Default::default()
+            };
+            (track, ALL_FIELDS.to_vec())
+        }
+    }
+}
+
+fn parse_track_metadata(entry_path: &Path, folder_id: Option<String>, inference: &PathInference) -> Track {
+    let (mut track, missing) = read_track_metadata(entry_path, folder_id);
+    track.format = entry_path.extension().map(|e| e.to_string_lossy().to_lowercase());
+    if track.identity.is_none() {
+        track.identity = identity::content_identity(entry_path);
+    }
+    // The path stays the locator in `id`; references use the identity so they survive moves.
+    if let Some(identity) = &track.identity {
+        track.canonical_id = identity.clone();
+    }
+    if !missing.is_empty() && !inference.patterns.is_empty() {
+        if let Ok(relative) = entry_path.strip_prefix(&inference.root) {
+            infer::fill_missing_fields(&mut track, relative, &inference.patterns, &missing);
         }
     }
+    track
 }
 
-fn scan_music_library_blocking(path: String) -> Result<ScanResult, String> {
+fn scan_music_library_blocking(
+    app: &tauri::AppHandle,
+    path: String,
+    path_patterns: Vec<String>,
+) -> Result<ScanResult, CommandError> {
     let root_path_buf = std::fs::canonicalize(&path).unwrap_or_else(|_| PathBuf::from(&path));
     let root_path = root_path_buf.as_path();
     let root_path_key = root_path.to_string_lossy().to_string().to_lowercase();
+    let inference = PathInference {
+        root: root_path_buf.clone(),
+        patterns: path_patterns,
+    };
+
+    if !root_path.exists() || volumes::is_offline(&root_path_key) {
+        return volumes::offline_result(app, &root_path_key);
+    }
+
+    let started = std::time::Instant::now();
+    log::info!(target: "scanner", "scanning {}", root_path.display());
 
     let mut cached_tracks = {
         let mut guard = SCAN_CACHE.lock().map_err(|_| "cache lock poisoned".to_string())?;
-        std::mem::take(
-            &mut guard
-                .entry(root_path_key.clone())
-                .or_default()
-                .tracks,
-        )
+        let library = guard.entry(root_path_key.clone()).or_default();
+        if library.inference != inference {
+            if !library.tracks.is_empty() {
+                log::debug!(target: "scanner", "path patterns changed, discarding cached metadata");
+            }
+            library.tracks.clear();
+            library.inference = inference.clone();
+        }
+        std::mem::take(&mut library.tracks)
     };
 
     let mut tracks = Vec::new();
@@ -784,6 +1027,11 @@ fn scan_music_library_blocking(path: String) -> Result<ScanResult, String> {
     let mut folder_index_by_id: HashMap<String, usize> = HashMap::new();
     let mut hasher = DefaultHasher::new();
     let mut seen_tracks: HashSet<String> = HashSet::new();
+    let mut parsed_count = 0usize;
+    let mut added_times: HashMap<String, u64> = HashMap::new();
+    let mut playlist_files: Vec<PathBuf> = Vec::new();
+
+    inference.patterns.hash(&mut hasher);
 
     folder_map.insert(root_path_key.clone(), root_path.to_string_lossy().to_string());
 
@@ -833,6 +1081,7 @@ fn scan_music_library_blocking(path: String) -> Result<ScanResult, String> {
                 path: entry_path_str.clone(),
                 track_count: 0,
                 source: "local".to_string(),
+                track_ids: Vec::new(),
             });
             folder_index_by_id.insert(id.clone(), folders.len() - 1);
             folder_map.insert(entry_path_key.clone(), id);
@@ -848,7 +1097,16 @@ fn scan_music_library_blocking(path: String) -> Result<ScanResult, String> {
                 None => continue,
             };
             let ext = extension.to_string_lossy().to_lowercase();
-            if !["mp3", "wav", "ogg", "flac", "m4a", "aac"].contains(&ext.as_str()) {
+            if playlists::PLAYLIST_EXTENSIONS.contains(&ext.as_str()) {
+                if let Some((modified, size)) = get_file_stamp(entry_path) {
+                    entry_path_key.hash(&mut hasher);
+                    modified.hash(&mut hasher);
+                    size.hash(&mut hasher);
+                    playlist_files.push(entry_path.to_path_buf());
+                }
+                continue;
+            }
+            if !AUDIO_EXTENSIONS.contains(&ext.as_str()) {
                 continue;
             }
 
@@ -886,14 +1144,21 @@ fn scan_music_library_blocking(path: String) -> Result<ScanResult, String> {
                     t.folder_id = folder_id.clone();
                     t
                 } else {
-                    parse_track_metadata(entry_path, folder_id.clone())
+                    parsed_count += 1;
+                    parse_track_metadata(entry_path, folder_id.clone(), &inference)
                 }
             } else {
-                parse_track_metadata(entry_path, folder_id.clone())
+                parsed_count += 1;
+                parse_track_metadata(entry_path, folder_id.clone(), &inference)
             };
 
             track.cover_url = None;
 
+            let added = std::fs::metadata(entry_path)
+                .and_then(|m| m.created())
+                .map(to_unix_seconds)
+                .unwrap_or(modified);
+            added_times.insert(entry_path_str.clone(), added);
             seen_tracks.insert(entry_path_str.clone());
             cached_tracks.insert(
                 entry_path_str.clone(),
@@ -907,26 +1172,90 @@ fn scan_music_library_blocking(path: String) -> Result<ScanResult, String> {
         }
     }
 
+    if let Err(e) = identity::adopt_identities(app, &mut tracks) {
+        log::warn!(target: "scanner", "failed to move references to track identities: {}", e);
+    }
+    for track in &tracks {
+        if let Some(cached) = cached_tracks.get_mut(&track.audio_url) {
+            cached.track.canonical_id = track.canonical_id.clone();
+        }
+    }
+    history::merge_stats(&mut tracks);
+    history::revision().hash(&mut hasher);
+    ratings::merge_ratings(&mut tracks);
+    ratings::revision().hash(&mut hasher);
+    analysis::merge_analysis(&mut tracks);
+    analysis::revision().hash(&mut hasher);
     let revision = format!("{:016x}", hasher.finish());
     cached_tracks.retain(|path, _| seen_tracks.contains(path));
+    log::info!(
+        target: "scanner",
+        "scanned {}: {} tracks ({} parsed, {} cached), {} folders in {:?}",
+        root_path.display(),
+        tracks.len(),
+        parsed_count,
+        tracks.len() - parsed_count,
+        folders.len(),
+        started.elapsed()
+    );
 
     {
         let mut guard = SCAN_CACHE.lock().map_err(|_| "cache lock poisoned".to_string())?;
         guard
-            .entry(root_path_key)
+            .entry(root_path_key.clone())
             .or_default()
             .tracks = cached_tracks;
     }
-
-    Ok(ScanResult {
+    query::update_index(&root_path_key, &revision, &tracks);
+
+    let playlist_folders = playlists::scan_folders(
+        &browse::root_folder_id(&root_path_key),
+        root_path,
+        &playlist_files,
+        &tracks,
+    );
+    let indexed = tracks.len();
+    folders.extend(browse::build(root_path, &root_path_key, &tracks, &added_times));
+    folders.extend(playlist_folders);
+
+    let result = ScanResult {
         tracks,
         folders,
         revision,
-    })
+    };
+    volumes::save_snapshot(app, &root_path_key, &result, indexed);
+    Ok(result)
+}
+
+fn refresh_cached_track(path: &Path) -> Option<Track> {
+    let path_str = path.to_string_lossy().to_string();
+    let (modified, size) = get_file_stamp(path)?;
+    let (folder_id, inference) = {
+        let guard = SCAN_CACHE.lock().ok()?;
+        guard.values().find_map(|library| {
+            library
+                .tracks
+                .get(&path_str)
+                .map(|cached| (cached.track.folder_id.clone(), library.inference.clone()))
+        })?
+    };
+
+    let track = parse_track_metadata(path, folder_id, &inference);
+    let mut guard = SCAN_CACHE.lock().ok()?;
+    for library in guard.values_mut() {
+        if let Some(cached) = library.tracks.get_mut(&path_str) {
+            *cached = CachedTrack {
+                track: track.clone(),
+                modified,
+                size,
+            };
+        }
+    }
+    Some(track)
 }
 
 #[tauri::command]
-fn get_cover_art(path: String) -> Result<Option<String>, String> {
+fn get_cover_art(path: String) -> Result<Option<String>, CommandError> {
     let Some(cover) = extract_cover_bytes(&path)? else {
         return Ok(None);
     };
@@ -935,7 +1264,7 @@ fn get_cover_art(path: String) -> Result<Option<String>, String> {
 }
 
 #[tauri::command]
-fn cover_server_register(path: String) -> Result<Option<String>, String> {
+fn cover_server_register(path: String) -> Result<Option<String>, CommandError> {
     if path.trim().is_empty() {
         return Ok(None);
     }
@@ -962,13 +1291,13 @@ async fn navidrome_create_server(
     username: String,
     password: Option<String>,
     api_key: Option<String>,
-) -> Result<NavidromeServerConfig, String> {
+) -> Result<NavidromeServerConfig, CommandError> {
     let id = uuid::Uuid::new_v4().to_string();
     let has_api_key = api_key.as_ref().is_some_and(|k| !k.trim().is_empty());
     let (token, salt) = if has_api_key {
         (String::new(), String::new())
     } else {
-        let password = password.ok_or_else(|| "Password is required".to_string())?;
+        let password = password.ok_or_else(|| CommandError::invalid_input("Password is required"))?;
         let salt = uuid::Uuid::new_v4().simple().to_string();
         let token_input = format!("{}{}", password, salt);
         let token = format!("{:x}", md5::compute(token_input));
@@ -991,33 +1320,39 @@ async fn navidrome_create_server(
 }
 
 #[tauri::command]
-async fn navidrome_test_connection(app: tauri::AppHandle, server_id: String) -> Result<(), String> {
+async fn navidrome_test_connection(app: tauri::AppHandle, server_id: String) -> Result<(), CommandError> {
     let config = load_config(app)?;
     let server = config
         .navidrome_servers
         .iter()
         .find(|s| s.id == server_id)
-        .ok_or_else(|| "Navidrome server not found".to_string())?;
+        .ok_or_else(|| CommandError::not_found("Navidrome server not found"))?;
     let _ = navidrome::ping(server).await?;
     Ok(())
 }
 
 #[tauri::command]
-async fn navidrome_scan_library(app: tauri::AppHandle, server_id: String) -> Result<ScanResult, String> {
+async fn navidrome_scan_library(app: tauri::AppHandle, server_id: String) -> Result<ScanResult, CommandError> {
     let config = load_config(app)?;
     let server = config
         .navidrome_servers
         .iter()
         .find(|s| s.id == server_id)
-        .ok_or_else(|| "Navidrome server not found".to_string())?;
+        .ok_or_else(|| CommandError::not_found("Navidrome server not found"))?;
+    let index_key = format!("navidrome:{}", server.id);
     if !server.enabled {
+        query::remove_from_index(&index_key);
         return Ok(ScanResult {
             tracks: Vec::new(),
             folders: Vec::new(),
             revision: "disabled".to_string(),
         });
     }
-    navidrome::scan_library(server).await
+    let mut result = navidrome::scan_library(server).await?;
+    history::merge_stats(&mut result.tracks);
+    result.revision = format!("{}:{}", result.revision, history::revision());
+    query::update_index(&index_key, &result.revision, &result.tracks);
+    Ok(result)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -1048,6 +1383,19 @@ pub fn run() {
 
     tauri::Builder::default()
         .manage(DiscordRpcState::default())
+        .plugin(logging::plugin())
+        .setup(|app| {
+            logging::apply_configured_level(app.handle());
+            deletion::purge_expired_app_trash(app.handle());
+            history::init(app.handle());
+            analysis::init(app.handle());
+            ratings::init(app.handle());
+            queue::init(app.handle());
+            resume::init(app.handle());
+            volumes::watch(app.handle().clone());
+            timers::start(app.handle().clone());
+            Ok(())
+        })
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_opener::init())
@@ -1070,11 +1418,75 @@ pub fn run() {
             prune_music_folders,
             remove_music_folder,
             create_folder,
-            delete_track,
+            deletion::delete_track,
+            deletion::undo_delete,
             show_in_explorer,
             navidrome_create_server,
             navidrome_test_connection,
-            navidrome_scan_library
+            navidrome_scan_library,
+            tags::read_tags,
+            tags::write_tags,
+            tags::undo_tag_edit,
+            organize::plan_organize,
+            organize::apply_organize,
+            logging::set_log_level,
+            logging::get_recent_logs,
+            health::library_health_report,
+            health::export_health_report,
+            verify::verify_library,
+            verify::cancel_verify,
+            analysis::analyze_library,
+            analysis::cancel_analysis,
+            query::query_tracks,
+            smart::scan_smart_playlists,
+            smart::preview_smart_playlist,
+            smart::save_smart_playlist,
+            smart::delete_smart_playlist,
+            playlists::list_playlists,
+            playlists::create_playlist,
+            playlists::rename_playlist,
+            playlists::add_to_playlist,
+            playlists::remove_from_playlist,
+            playlists::move_playlist_track,
+            playlists::delete_playlist,
+            playlists::import_playlist,
+            playlists::export_playlist,
+            playlists::scan_playlists,
+            history::record_play,
+            history::get_history,
+            history::get_play_stats,
+            history::get_recent_tracks,
+            listening::listening_report,
+            listening::export_listening_report,
+            ratings::set_rating,
+            volumes::music_folder_status,
+            queue::get_queue,
+            queue::queue_set,
+            queue::queue_enqueue,
+            queue::queue_insert_next,
+            queue::queue_move,
+            queue::queue_remove,
+            queue::queue_clear,
+            queue::queue_jump,
+            queue::queue_next,
+            queue::queue_previous,
+            queue::queue_set_repeat,
+            queue::queue_set_shuffle,
+            queue::queue_set_position,
+            queue::queue_save_as_playlist,
+            resume::get_resume_position,
+            resume::set_resume_position,
+            resume::get_book,
+            chapters::get_chapters,
+            radio::generate_radio,
+            timers::start_sleep_timer,
+            timers::cancel_sleep_timer,
+            timers::get_sleep_timer,
+            timers::sleep_timer_progress,
+            timers::get_alarms,
+            timers::save_alarm,
+            timers::delete_alarm,
+            waveform::get_waveform
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,487 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write as _;
+use std::path::Path;
+use std::time::SystemTime;
+
+use super::{to_unix_seconds, Track};
+use crate::error::CommandError;
+use crate::history::{self, PlayOutcome};
+use crate::playlists::xml_escape;
+use crate::query::{index_snapshot, normalize};
+
+const DEFAULT_TOP: usize = 10;
+const MAX_TOP: usize = 100;
+const SESSION_GAP_SECONDS: u64 = 30 * 60;
+const SESSION_LIMIT: usize = 5;
+const DAY_SECONDS: i64 = 24 * 60 * 60;
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ReportRange {
+    from: Option<u64>,
+    to: Option<u64>,
+    utc_offset_minutes: i32,
+    top: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RankedItem {
+    id: String,
+    name: String,
+    detail: Option<String>,
+    plays: u32,
+    seconds: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Streak {
+    days: u32,
+    start: String,
+    end: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ListeningSession {
+    start: u64,
+    end: u64,
+    seconds: u64,
+    plays: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredArtist {
+    name: String,
+    first_played: u64,
+    plays: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ListeningReport {
+    from: Option<u64>,
+    to: Option<u64>,
+    generated_at: u64,
+    utc_offset_minutes: i32,
+    total_plays: u32,
+    skipped_plays: u32,
+    total_seconds: u64,
+    distinct_tracks: usize,
+    distinct_artists: usize,
+    top_artists: Vec<RankedItem>,
+    top_albums: Vec<RankedItem>,
+    top_tracks: Vec<RankedItem>,
+    top_genres: Vec<RankedItem>,
+    longest_streak: Option<Streak>,
+    current_streak: Option<Streak>,
+    hours: Vec<u32>,
+    weekdays: Vec<u32>,
+    weekday_hours: Vec<Vec<u32>>,
+    new_artists: Vec<DiscoveredArtist>,
+    longest_sessions: Vec<ListeningSession>,
+}
+
+#[derive(Default)]
+struct Tally {
+    items: HashMap<String, RankedItem>,
+}
+
+impl Tally {
+    fn add(&mut self, key: String, name: &str, detail: Option<&str>, seconds: u64) {
+        let item = self.items.entry(key.clone()).or_insert_with(|| RankedItem {
+            id: key,
+            name: name.to_string(),
+            detail: detail.map(|d| d.to_string()),
+            plays: 0,
+            seconds: 0,
+        });
+        item.plays += 1;
+        item.seconds += seconds;
+    }
+
+    fn top(self, limit: usize) -> Vec<RankedItem> {
+        let mut items: Vec<RankedItem> = self.items.into_values().collect();
+        items.sort_by(|a, b| {
+            (Reverse(a.plays), Reverse(a.seconds), &a.name).cmp(&(Reverse(b.plays), Reverse(b.seconds), &b.name))
+        });
+        items.truncate(limit);
+        items
+    }
+}
+
+fn civil_date(days: i64) -> String {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn local_seconds(timestamp: u64, offset_minutes: i32) -> i64 {
+    timestamp as i64 + i64::from(offset_minutes) * 60
+}
+
+fn local_time(timestamp: u64, offset_minutes: i32) -> String {
+    let local = local_seconds(timestamp, offset_minutes);
+    let seconds = local.rem_euclid(DAY_SECONDS);
+    format!(
+        "{} {:02}:{:02}",
+        civil_date(local.div_euclid(DAY_SECONDS)),
+        seconds / 3600,
+        seconds % 3600 / 60
+    )
+}
+
+fn streak(days: &BTreeSet<i64>, end: i64) -> Streak {
+    let mut start = end;
+    while days.contains(&(start - 1)) {
+        start -= 1;
+    }
+    Streak {
+        days: (end - start + 1) as u32,
+        start: civil_date(start),
+        end: civil_date(end),
+    }
+}
+
+fn track_name(track_id: &str) -> String {
+    let path = Path::new(track_id);
+    if path.is_absolute() {
+        if let Some(stem) = path.file_stem() {
+            return stem.to_string_lossy().to_string();
+        }
+    }
+    track_id.to_string()
+}
+
+fn album_artist(track: &Track) -> &str {
+    track
+        .album_artist
+        .as_deref()
+        .filter(|a| !a.trim().is_empty())
+        .unwrap_or(&track.artist)
+}
+
+fn build_report(range: &ReportRange) -> ListeningReport {
+    let plays = history::plays();
+    let (library, _) = index_snapshot();
+    let by_id: HashMap<&str, &Track> = library.iter().map(|t| (t.canonical_id.as_str(), t)).collect();
+    let offset = range.utc_offset_minutes;
+    let top = range.top.unwrap_or(DEFAULT_TOP).clamp(1, MAX_TOP);
+    let in_range =
+        |started_at: u64| range.from.is_none_or(|from| started_at >= from) && range.to.is_none_or(|to| started_at < to);
+
+    let mut first_artist_play: HashMap<String, (String, u64)> = HashMap::new();
+    let mut artists = Tally::default();
+    let mut albums = Tally::default();
+    let mut tracks = Tally::default();
+    let mut genres = Tally::default();
+    let mut days = BTreeSet::new();
+    let mut hours = vec![0u32; 24];
+    let mut weekdays = vec![0u32; 7];
+    let mut weekday_hours = vec![vec![0u32; 24]; 7];
+    let mut sessions: Vec<ListeningSession> = Vec::new();
+    let mut total_plays = 0;
+    let mut skipped_plays = 0;
+    let mut total_seconds = 0;
+
+    for play in &plays {
+        let track = by_id.get(play.track_id.as_str()).copied();
+        let artist = track.map(|t| t.artist.trim()).filter(|a| !a.is_empty());
+        if let Some(artist) = artist.filter(|_| play.outcome != PlayOutcome::Skipped) {
+            first_artist_play
+                .entry(normalize(artist))
+                .or_insert_with(|| (artist.to_string(), play.started_at));
+        }
+        if !in_range(play.started_at) {
+            continue;
+        }
+        if play.outcome == PlayOutcome::Skipped {
+            skipped_plays += 1;
+            continue;
+        }
+
+        let seconds = track
+            .map(|t| (t.duration as f64 * play.fraction).round() as u64)
+            .unwrap_or(0);
+        total_seconds += seconds;
+        match sessions.last_mut() {
+            Some(session) if play.started_at <= session.end + SESSION_GAP_SECONDS => {
+                session.end = session.end.max(play.started_at + seconds);
+                session.seconds += seconds;
+                session.plays += 1;
+            }
+            _ => sessions.push(ListeningSession {
+                start: play.started_at,
+                end: play.started_at + seconds,
+                seconds,
+                plays: 1,
+            }),
+        }
+        total_plays += 1;
+
+        let local = local_seconds(play.started_at, offset);
+        let day = local.div_euclid(DAY_SECONDS);
+        let hour = (local.rem_euclid(DAY_SECONDS) / 3600) as usize;
+        let weekday = (day + 4).rem_euclid(7) as usize;
+        days.insert(day);
+        hours[hour] += 1;
+        weekdays[weekday] += 1;
+        weekday_hours[weekday][hour] += 1;
+
+        let title = track
+            .map(|t| t.title.clone())
+            .unwrap_or_else(|| track_name(&play.track_id));
+        tracks.add(play.track_id.clone(), &title, artist, seconds);
+        if let Some(artist) = artist {
+            artists.add(normalize(artist), artist, None, seconds);
+        }
+        if let Some(track) = track {
+            if !track.album.trim().is_empty() {
+                let owner = album_artist(track);
+                let key = format!("{}|{}", normalize(owner), normalize(&track.album));
+                albums.add(key, &track.album, Some(owner), seconds);
+            }
+            for genre in track
+                .genre
+                .as_deref()
+                .unwrap_or_default()
+                .split(['\0', ';'])
+                .map(str::trim)
+                .filter(|g| !g.is_empty())
+            {
+                genres.add(normalize(genre), genre, None, seconds);
+            }
+        }
+    }
+
+    let mut new_artists: Vec<DiscoveredArtist> = first_artist_play
+        .into_iter()
+        .filter(|(_, (_, first))| in_range(*first))
+        .map(|(key, (name, first_played))| DiscoveredArtist {
+            plays: artists.items.get(&key).map(|a| a.plays).unwrap_or(0),
+            name,
+            first_played,
+        })
+        .collect();
+    new_artists.sort_by_key(|a| (Reverse(a.plays), a.first_played));
+    new_artists.truncate(top);
+
+    let mut longest_streak: Option<Streak> = None;
+    for &day in &days {
+        if !days.contains(&(day + 1)) {
+            let candidate = streak(&days, day);
+            if longest_streak.as_ref().is_none_or(|s| candidate.days > s.days) {
+                longest_streak = Some(candidate);
+            }
+        }
+    }
+    let now = to_unix_seconds(SystemTime::now());
+    let last_day =
+        local_seconds(range.to.map(|to| to.saturating_sub(1)).unwrap_or(now).min(now), offset).div_euclid(DAY_SECONDS);
+    let current_streak = [last_day, last_day - 1]
+        .into_iter()
+        .find(|d| days.contains(d))
+        .map(|d| streak(&days, d));
+
+    sessions.sort_by_key(|s| (Reverse(s.seconds), Reverse(s.plays)));
+    sessions.truncate(SESSION_LIMIT);
+
+    let distinct_tracks = tracks.items.len();
+    let distinct_artists = artists.items.len();
+    ListeningReport {
+        from: range.from,
+        to: range.to,
+        generated_at: now,
+        utc_offset_minutes: offset,
+        total_plays,
+        skipped_plays,
+        total_seconds,
+        distinct_tracks,
+        distinct_artists,
+        top_artists: artists.top(top),
+        top_albums: albums.top(top),
+        top_tracks: tracks.top(top),
+        top_genres: genres.top(top),
+        longest_streak,
+        current_streak,
+        hours,
+        weekdays,
+        weekday_hours,
+        new_artists,
+        longest_sessions: sessions,
+    }
+}
+
+fn duration_text(seconds: u64) -> String {
+    let hours = seconds / 3600;
+    let minutes = seconds % 3600 / 60;
+    if hours > 0 {
+        format!("{}h {:02}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+fn ranked_table(out: &mut String, title: &str, items: &[RankedItem]) {
+    let _ = write!(out, "<section><h2>{}</h2><table>", xml_escape(title));
+    for (i, item) in items.iter().enumerate() {
+        let detail = item
+            .detail
+            .as_deref()
+            .map(|d| format!("<span class=\"muted\"> &middot; {}</span>", xml_escape(d)))
+            .unwrap_or_default();
+        let _ = write!(
+            out,
+            "<tr><td class=\"rank\">{}</td><td>{}{}</td><td class=\"num\">{} plays</td><td class=\"num\">{}</td></tr>",
+            i + 1,
+            xml_escape(&item.name),
+            detail,
+            item.plays,
+            duration_text(item.seconds)
+        );
+    }
+    out.push_str("</table></section>");
+}
+
+fn report_to_html(report: &ListeningReport) -> String {
+    let offset = report.utc_offset_minutes;
+    let period = match (report.from, report.to) {
+        (Some(from), Some(to)) => format!(
+            "{} to {}",
+            local_time(from, offset),
+            local_time(to.saturating_sub(1), offset)
+        ),
+        (Some(from), None) => format!("Since {}", local_time(from, offset)),
+        (None, Some(to)) => format!("Until {}", local_time(to.saturating_sub(1), offset)),
+        (None, None) => "All time".to_string(),
+    };
+
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>Saxon listening report</title><style>");
+    out.push_str(
+        "body{font-family:system-ui,sans-serif;background:#0f0f0f;color:#f2f2f2;margin:0 auto;max-width:960px;padding:32px}\
+         h1{margin-bottom:4px}h2{margin-top:32px;font-size:18px}.muted{color:#9a9a9a}\
+         .cards{display:flex;flex-wrap:wrap;gap:12px}.card{background:#1a1a1a;border-radius:8px;padding:12px 16px;min-width:140px}\
+         .card b{display:block;font-size:22px}table{border-collapse:collapse;width:100%}\
+         td,th{padding:4px 8px;text-align:left}.num{text-align:right;white-space:nowrap}.rank{color:#9a9a9a;width:24px}\
+         .heat td{width:3.5%;height:18px;padding:0;border:1px solid #0f0f0f}.heat th{font-weight:normal;color:#9a9a9a;font-size:12px}",
+    );
+    out.push_str("</style></head><body>");
+    let _ = write!(
+        out,
+        "<h1>Listening report</h1><div class=\"muted\">{}</div>",
+        xml_escape(&period)
+    );
+
+    out.push_str("<div class=\"cards\" style=\"margin-top:24px\">");
+    let streak_days = |s: &Option<Streak>| s.as_ref().map(|s| s.days).unwrap_or(0);
+    for (label, value) in [
+        ("Listening time", duration_text(report.total_seconds)),
+        ("Plays", report.total_plays.to_string()),
+        ("Skips", report.skipped_plays.to_string()),
+        ("Tracks", report.distinct_tracks.to_string()),
+        ("Artists", report.distinct_artists.to_string()),
+        (
+            "Longest streak",
+            format!("{} days", streak_days(&report.longest_streak)),
+        ),
+        (
+            "Current streak",
+            format!("{} days", streak_days(&report.current_streak)),
+        ),
+    ] {
+        let _ = write!(
+            out,
+            "<div class=\"card\"><span class=\"muted\">{}</span><b>{}</b></div>",
+            label, value
+        );
+    }
+    out.push_str("</div>");
+
+    ranked_table(&mut out, "Top artists", &report.top_artists);
+    ranked_table(&mut out, "Top albums", &report.top_albums);
+    ranked_table(&mut out, "Top tracks", &report.top_tracks);
+    ranked_table(&mut out, "Top genres", &report.top_genres);
+
+    let max = report.weekday_hours.iter().flatten().copied().max().unwrap_or(0).max(1);
+    out.push_str("<section><h2>When you listen</h2><table class=\"heat\"><tr><th></th>");
+    for hour in 0..24 {
+        let _ = write!(out, "<th>{}</th>", hour);
+    }
+    out.push_str("</tr>");
+    for (day, row) in report.weekday_hours.iter().enumerate() {
+        let _ = write!(out, "<tr><th>{}</th>", WEEKDAYS[day % 7]);
+        for count in row {
+            let alpha = f64::from(*count) / f64::from(max);
+            let _ = write!(
+                out,
+                "<td title=\"{} plays\" style=\"background:rgba(29,185,84,{:.2})\"></td>",
+                count,
+                alpha.max(0.04)
+            );
+        }
+        out.push_str("</tr>");
+    }
+    out.push_str("</table></section>");
+
+    out.push_str("<section><h2>New artists</h2><table>");
+    for artist in &report.new_artists {
+        let _ = write!(
+            out,
+            "<tr><td>{}</td><td class=\"muted\">first played {}</td><td class=\"num\">{} plays</td></tr>",
+            xml_escape(&artist.name),
+            local_time(artist.first_played, offset),
+            artist.plays
+        );
+    }
+    out.push_str("</table></section>");
+
+    out.push_str("<section><h2>Longest sessions</h2><table>");
+    for session in &report.longest_sessions {
+        let _ = write!(
+            out,
+            "<tr><td>{}</td><td class=\"num\">{} plays</td><td class=\"num\">{}</td></tr>",
+            local_time(session.start, offset),
+            session.plays,
+            duration_text(session.seconds)
+        );
+    }
+    out.push_str("</table></section>");
+
+    let _ = write!(
+        out,
+        "<p class=\"muted\" style=\"margin-top:32px\">Generated by Saxon on {}</p></body></html>",
+        local_time(report.generated_at, offset)
+    );
+    out
+}
+
+#[tauri::command]
+pub async fn listening_report(range: Option<ReportRange>) -> Result<ListeningReport, CommandError> {
+    let range = range.unwrap_or_default();
+    Ok(tauri::async_runtime::spawn_blocking(move || build_report(&range)).await?)
+}
+
+#[tauri::command]
+pub fn export_listening_report(report: ListeningReport, format: String) -> Result<String, CommandError> {
+    match format.to_lowercase().as_str() {
+        "json" => Ok(serde_json::to_string_pretty(&report)?),
+        "html" => Ok(report_to_html(&report)),
+        other => Err(CommandError::invalid_input(format!(
+            "Unsupported export format: {}",
+            other
+        ))),
+    }
+}
@@ -0,0 +1,120 @@
+use log::LevelFilter;
+use std::path::PathBuf;
+use std::str::FromStr;
+use tauri_plugin_log::{RotationStrategy, Target, TargetKind};
+
+use super::{load_config, save_config};
+use crate::error::CommandError;
+
+const LOG_FILE_NAME: &str = "saxon";
+const MAX_LOG_FILE_BYTES: u128 = 2 * 1024 * 1024;
+const KEPT_LOG_FILES: usize = 3;
+const DEFAULT_LOG_LINES: usize = 500;
+const MAX_LOG_LINES: usize = 5000;
+
+pub(crate) fn plugin<R: tauri::Runtime>() -> tauri::plugin::TauriPlugin<R> {
+    tauri_plugin_log::Builder::new()
+        .clear_targets()
+        .target(Target::new(TargetKind::LogDir {
+            file_name: Some(LOG_FILE_NAME.to_string()),
+        }))
+        .target(Target::new(TargetKind::Stdout))
+        .max_file_size(MAX_LOG_FILE_BYTES)
+        .rotation_strategy(RotationStrategy::KeepSome(KEPT_LOG_FILES))
+        .level(LevelFilter::Trace)
+        .level_for("hyper", LevelFilter::Warn)
+        .level_for("hyper_util", LevelFilter::Warn)
+        .level_for("reqwest", LevelFilter::Warn)
+        .level_for("rustls", LevelFilter::Warn)
+        .level_for("tao", LevelFilter::Warn)
+        .level_for("tiny_http", LevelFilter::Warn)
+        .build()
+}
+
+fn default_level() -> LevelFilter {
+    if cfg!(debug_assertions) {
+        LevelFilter::Debug
+    } else {
+        LevelFilter::Info
+    }
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, CommandError> {
+    LevelFilter::from_str(level.trim())
+        .map_err(|_| CommandError::invalid_input(format!("Unknown log level: {}", level)))
+}
+
+pub(crate) fn apply_configured_level(app: &tauri::AppHandle) {
+    let level = load_config(app.clone())
+        .ok()
+        .and_then(|config| config.log_level)
+        .and_then(|level| parse_level(&level).ok())
+        .unwrap_or_else(default_level);
+    log::set_max_level(level);
+    log::info!(target: "app", "Saxon {} starting, log level {}", env!("CARGO_PKG_VERSION"), level);
+}
+
+fn log_files(app: &tauri::AppHandle) -> Result<Vec<PathBuf>, CommandError> {
+    use tauri::Manager;
+    let dir = app.path().app_log_dir()?;
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return Ok(Vec::new());
+    };
+
+    let archived_prefix = format!("{}_", LOG_FILE_NAME);
+    let mut archived: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .map(|n| n.to_string_lossy())
+                .is_some_and(|n| n.starts_with(&archived_prefix) && n.ends_with(".log"))
+        })
+        .collect();
+    archived.sort();
+
+    let current = dir.join(format!("{}.log", LOG_FILE_NAME));
+    if current.is_file() {
+        archived.push(current);
+    }
+    Ok(archived)
+}
+
+#[tauri::command]
+pub fn set_log_level(app: tauri::AppHandle, level: String) -> Result<String, CommandError> {
+    let filter = parse_level(&level)?;
+    let mut config = load_config(app.clone())?;
+    config.log_level = Some(filter.to_string().to_lowercase());
+    save_config(app, config)?;
+    log::set_max_level(filter);
+    log::info!(target: "app", "log level set to {}", filter);
+    Ok(filter.to_string().to_lowercase())
+}
+
+#[tauri::command]
+pub async fn get_recent_logs(app: tauri::AppHandle, lines: Option<usize>) -> Result<Vec<String>, CommandError> {
+    let limit = lines.unwrap_or(DEFAULT_LOG_LINES).clamp(1, MAX_LOG_LINES);
+    let files = log_files(&app)?;
+    tauri::async_runtime::spawn_blocking(move || -> Result<Vec<String>, CommandError> {
+        let mut collected: Vec<String> = Vec::new();
+        for file in files.iter().rev() {
+            let Ok(bytes) = std::fs::read(file) else {
+                continue;
+            };
+            let text = String::from_utf8_lossy(&bytes);
+            let mut chunk: Vec<String> = text
+                .lines()
+                .rev()
+                .take(limit - collected.len())
+                .map(|l| l.to_string())
+                .collect();
+            collected.append(&mut chunk);
+            if collected.len() >= limit {
+                break;
+            }
+        }
+        collected.reverse();
+        Ok(collected)
+    })
+    .await?
+}
@@ -1,9 +1,11 @@
 use reqwest::Client;
 use serde_json::Value;
 use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 
 use super::{MusicFolder, NavidromeServerConfig, ScanResult, Track};
+use crate::error::{CommandError, ErrorKind};
 
 fn rest_base(base_url: &str) -> String {
     let trimmed = base_url.trim_end_matches('/');
@@ -53,43 +55,58 @@ fn request_query(server: &NavidromeServerConfig) -> Vec<(String, String)> {
     query
 }
 
+fn subsonic_error_kind(code: Option<u64>) -> ErrorKind {
+    match code {
+        Some(10) => ErrorKind::InvalidInput,
+        Some(40) | Some(41) | Some(42) | Some(43) | Some(44) | Some(50) => ErrorKind::Auth,
+        Some(70) => ErrorKind::NotFound,
+        _ => ErrorKind::ServerError,
+    }
+}
+
 async fn subsonic_get(
     client: &Client,
     server: &NavidromeServerConfig,
     endpoint: &str,
     params: Vec<(String, String)>,
-) -> Result<Value, String> {
+) -> Result<Value, CommandError> {
     let mut query = request_query(server);
     query.extend(params);
 
     let url = endpoint_url(&server.base_url, endpoint);
-    let response = client
-        .get(url)
-        .query(&query)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
+    log::debug!(target: "navidrome", "{}: GET {}", server.name, endpoint);
+    let response = client.get(url).query(&query).send().await.map_err(|e| {
+        log::warn!(target: "navidrome", "{}: {} request failed: {}", server.name, endpoint, e);
+        e
+    })?;
 
     if !response.status().is_success() {
-        return Err(format!("HTTP {}", response.status()));
+        log::warn!(target: "navidrome", "{}: {} returned HTTP {}", server.name, endpoint, response.status());
+        return Err(CommandError::from_status(response.status()).with_details(endpoint.to_string()));
     }
 
-    let json: Value = response.json().await.map_err(|e| e.to_string())?;
+    let json: Value = response.json().await?;
     let sr = json
         .get("subsonic-response")
-        .ok_or_else(|| "Missing subsonic-response".to_string())?;
+        .ok_or_else(|| CommandError::parse("Missing subsonic-response"))?;
 
     let status = sr
         .get("status")
         .and_then(|v| v.as_str())
         .unwrap_or("failed");
     if status != "ok" {
-        let message = sr
-            .get("error")
+        let error = sr.get("error");
+        let code = value_to_u64(error.and_then(|e| e.get("code")));
+        let message = error
             .and_then(|e| e.get("message"))
             .and_then(|m| m.as_str())
             .unwrap_or("Request failed");
-        return Err(message.to_string());
+        log::warn!(target: "navidrome", "{}: {} failed with Subsonic error {:?}: {}", server.name, endpoint, code, message);
+        let mut err = CommandError::new(subsonic_error_kind(code), message);
+        if let Some(code) = code {
+            err = err.with_details(format!("Subsonic error {}", code));
+        }
+        return Err(err);
     }
 
     Ok(sr.clone())
@@ -120,7 +137,7 @@ fn value_to_u64(value: Option<&Value>) -> Option<u64> {
     }
 }
 
-pub async fn ping(server: &NavidromeServerConfig) -> Result<bool, String> {
+pub async fn ping(server: &NavidromeServerConfig) -> Result<bool, CommandError> {
     let client = Client::new();
     let sr = subsonic_get(&client, server, "ping", Vec::new()).await?;
     Ok(sr
@@ -146,8 +163,159 @@ fn stream_url(server: &NavidromeServerConfig, track_id: &str) -> String {
     format!("{}?{}", url, query_string)
 }
 
-pub async fn scan_library(server: &NavidromeServerConfig) -> Result<ScanResult, String> {
+pub async fn set_rating(server: &NavidromeServerConfig, song_id: &str, rating: u8) -> Result<(), CommandError> {
+    let client = Client::new();
+    subsonic_get(
+        &client,
+        server,
+        "setRating",
+        vec![
+            ("id".to_string(), song_id.to_string()),
+            ("rating".to_string(), rating.min(5).to_string()),
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+pub async fn get_bookmarks(server: &NavidromeServerConfig) -> Result<HashMap<String, f64>, CommandError> {
+    let client = Client::new();
+    let sr = subsonic_get(&client, server, "getBookmarks", Vec::new()).await?;
+    let bookmarks = value_to_vec(sr.get("bookmarks").and_then(|b| b.get("bookmark")));
+    Ok(bookmarks
+        .into_iter()
+        .filter_map(|b| {
+            let id = value_to_string(b.get("entry").and_then(|e| e.get("id")))?;
+            let position = value_to_u64(b.get("position"))?;
+            Some((id, position as f64 / 1000.0))
+        })
+        .collect())
+}
+
+pub async fn create_bookmark(server: &NavidromeServerConfig, song_id: &str, position: f64) -> Result<(), CommandError> {
+    let client = Client::new();
+    subsonic_get(
+        &client,
+        server,
+        "createBookmark",
+        vec![
+            ("id".to_string(), song_id.to_string()),
+            ("position".to_string(), ((position * 1000.0).round() as u64).to_string()),
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_bookmark(server: &NavidromeServerConfig, song_id: &str) -> Result<(), CommandError> {
+    let client = Client::new();
+    let params = vec![("id".to_string(), song_id.to_string())];
+    match subsonic_get(&client, server, "deleteBookmark", params).await {
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        result => result.map(|_| ()),
+    }
+}
+
+// Fetches a song's stream untranscoded for local analysis.
+pub async fn fetch_stream(
+    server: &NavidromeServerConfig,
+    song_id: &str,
+    max_bytes: u64,
+) -> Result<Vec<u8>, CommandError> {
+    let mut query = request_query(server);
+    query.push(("id".to_string(), song_id.to_string()));
+    query.push(("format".to_string(), "raw".to_string()));
+    let url = endpoint_url(&server.base_url, "stream");
+    log::debug!(target: "navidrome", "{}: GET stream", server.name);
+    let mut response = Client::new().get(url).query(&query).send().await?;
+    if !response.status().is_success() {
+        log::warn!(target: "navidrome", "{}: stream returned HTTP {}", server.name, response.status());
+        return Err(CommandError::from_status(response.status()).with_details("stream".to_string()));
+    }
+    // Subsonic reports errors as JSON with a success status.
+    let is_json = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("json"));
+    if is_json {
+        return Err(CommandError::new(
+            ErrorKind::ServerError,
+            "Navidrome did not return audio",
+        ));
+    }
+    let too_large = || CommandError::invalid_input("Song is too large to analyze");
+    if response.content_length().is_some_and(|len| len > max_bytes) {
+        return Err(too_large());
+    }
+    // The length header is optional, so the cap is enforced while reading.
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if (bytes.len() + chunk.len()) as u64 > max_bytes {
+            return Err(too_large());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+fn song_ids(songs: Option<&Value>) -> Vec<String> {
+    value_to_vec(songs).into_iter().filter_map(|s| value_to_string(s.get("id"))).collect()
+}
+
+// Returns the artist id and name of a song, used to seed the similarity endpoints.
+pub async fn get_song_artist(
+    server: &NavidromeServerConfig,
+    song_id: &str,
+) -> Result<(Option<String>, Option<String>), CommandError> {
+    let client = Client::new();
+    let sr = subsonic_get(&client, server, "getSong", vec![("id".to_string(), song_id.to_string())]).await?;
+    let song = sr.get("song");
+    Ok((
+        value_to_string(song.and_then(|s| s.get("artistId"))),
+        value_to_string(song.and_then(|s| s.get("artist"))),
+    ))
+}
+
+// The file extension of the original upload, e.g. "flac".
+pub async fn get_song_suffix(server: &NavidromeServerConfig, song_id: &str) -> Result<Option<String>, CommandError> {
+    let client = Client::new();
+    let params = vec![("id".to_string(), song_id.to_string())];
+    let sr = subsonic_get(&client, server, "getSong", params).await?;
+    Ok(value_to_string(sr.get("song").and_then(|s| s.get("suffix"))))
+}
+
+pub async fn get_similar_songs(
+    server: &NavidromeServerConfig,
+    artist_id: &str,
+    count: usize,
+) -> Result<Vec<String>, CommandError> {
+    let client = Client::new();
+    let params = vec![
+        ("id".to_string(), artist_id.to_string()),
+        ("count".to_string(), count.to_string()),
+    ];
+    let sr = subsonic_get(&client, server, "getSimilarSongs2", params).await?;
+    Ok(song_ids(sr.get("similarSongs2").and_then(|s| s.get("song"))))
+}
+
+pub async fn get_top_songs(
+    server: &NavidromeServerConfig,
+    artist: &str,
+    count: usize,
+) -> Result<Vec<String>, CommandError> {
+    let client = Client::new();
+    let params = vec![
+        ("artist".to_string(), artist.to_string()),
+        ("count".to_string(), count.to_string()),
+    ];
+    let sr = subsonic_get(&client, server, "getTopSongs", params).await?;
+    Ok(song_ids(sr.get("topSongs").and_then(|s| s.get("song"))))
+}
+
+pub async fn scan_library(server: &NavidromeServerConfig) -> Result<ScanResult, CommandError> {
     let client = Client::new();
+    log::info!(target: "navidrome", "{}: scanning library", server.name);
 
     let root_id = format!("navidrome:{}", server.id);
     let all_tracks_folder_id = format!("navidrome:{}:alltracks", server.id);
@@ -167,6 +335,7 @@ pub async fn scan_library(server: &NavidromeServerConfig) -> Result<ScanResult,
         path: root_id.clone(),
         track_count: 0,
         source: "navidrome".to_string(),
+        track_ids: Vec::new(),
     });
 
     folders.push(MusicFolder {
@@ -176,6 +345,7 @@ pub async fn scan_library(server: &NavidromeServerConfig) -> Result<ScanResult,
         path: all_tracks_folder_id.clone(),
         track_count: 0,
         source: "navidrome".to_string(),
+        track_ids: Vec::new(),
     });
 
     folders.push(MusicFolder {
@@ -185,6 +355,7 @@ pub async fn scan_library(server: &NavidromeServerConfig) -> Result<ScanResult,
         path: artists_root_folder_id.clone(),
         track_count: 0,
         source: "navidrome".to_string(),
+        track_ids: Vec::new(),
     });
 
     folders.push(MusicFolder {
@@ -194,6 +365,7 @@ pub async fn scan_library(server: &NavidromeServerConfig) -> Result<ScanResult,
         path: playlists_root_folder_id.clone(),
         track_count: 0,
         source: "navidrome".to_string(),
+        track_ids: Vec::new(),
     });
 
     let artists_sr = subsonic_get(&client, server, "getArtists", Vec::new()).await?;
@@ -216,6 +388,7 @@ pub async fn scan_library(server: &NavidromeServerConfig) -> Result<ScanResult,
                 path: artist_folder_id.clone(),
                 track_count: 0,
                 source: "navidrome".to_string(),
+                track_ids: Vec::new(),
             });
 
             let artist_sr = subsonic_get(
@@ -245,6 +418,7 @@ pub async fn scan_library(server: &NavidromeServerConfig) -> Result<ScanResult,
                     path: album_folder_id.clone(),
                     track_count: 0,
                     source: "navidrome".to_string(),
+                    track_ids: Vec::new(),
                 });
 
                 let album_sr = subsonic_get(
@@ -283,6 +457,7 @@ pub async fn scan_library(server: &NavidromeServerConfig) -> Result<ScanResult,
                     song_artist.hash(&mut hasher);
                     song_album.hash(&mut hasher);
                     duration.hash(&mut hasher);
+                    value_to_u64(song.get("userRating")).hash(&mut hasher);
 
                     tracks.push(Track {
                         canonical_id: id.clone(),
@@ -295,6 +470,16 @@ pub async fn scan_library(server: &NavidromeServerConfig) -> Result<ScanResult,
                         audio_url,
                         folder_id: Some(album_folder_id.clone()),
                         source: "navidrome".to_string(),
+                        album_artist: Some(artist_name.clone()),
+                        track_number: value_to_u64(song.get("track")).map(|v| v as u32),
+                        disc_number: value_to_u64(song.get("discNumber")).map(|v| v as u32),
+                        year: value_to_u64(song.get("year")).map(|v| v as u32),
+                        genre: value_to_string(song.get("genre")),
+                        composer: value_to_string(song.get("displayComposer")),
+                        bpm: value_to_u64(song.get("bpm")).filter(|b| *b > 0).map(|b| b as f32),
+                        format: value_to_string(song.get("suffix")).map(|s| s.to_lowercase()),
+                        rating: value_to_u64(song.get("userRating")).filter(|r| *r > 0).map(|r| r.min(5) as f32),
+                        ..Default::default()
                     });
 
                     album_count += 1;
@@ -332,6 +517,7 @@ pub async fn scan_library(server: &NavidromeServerConfig) -> Result<ScanResult,
             path: playlist_folder_id.clone(),
             track_count: 0,
             source: "navidrome".to_string(),
+            track_ids: Vec::new(),
         });
 
         let playlist_sr = subsonic_get(
@@ -371,6 +557,7 @@ pub async fn scan_library(server: &NavidromeServerConfig) -> Result<ScanResult,
             song_artist.hash(&mut hasher);
             song_album.hash(&mut hasher);
             duration.hash(&mut hasher);
+            value_to_u64(entry.get("userRating")).hash(&mut hasher);
 
             tracks.push(Track {
                 canonical_id: base_id,
@@ -383,6 +570,15 @@ pub async fn scan_library(server: &NavidromeServerConfig) -> Result<ScanResult,
                 audio_url,
                 folder_id: Some(playlist_folder_id.clone()),
                 source: "navidrome".to_string(),
+                track_number: value_to_u64(entry.get("track")).map(|v| v as u32),
+                disc_number: value_to_u64(entry.get("discNumber")).map(|v| v as u32),
+                year: value_to_u64(entry.get("year")).map(|v| v as u32),
+                genre: value_to_string(entry.get("genre")),
+                composer: value_to_string(entry.get("displayComposer")),
+                bpm: value_to_u64(entry.get("bpm")).filter(|b| *b > 0).map(|b| b as f32),
+                format: value_to_string(entry.get("suffix")).map(|s| s.to_lowercase()),
+                rating: value_to_u64(entry.get("userRating")).filter(|r| *r > 0).map(|r| r.min(5) as f32),
+                ..Default::default()
             });
 
             playlist_count += 1;
@@ -403,6 +599,13 @@ pub async fn scan_library(server: &NavidromeServerConfig) -> Result<ScanResult,
     }
 
     let revision = format!("{:016x}", hasher.finish());
+    log::info!(
+        target: "navidrome",
+        "{}: scanned {} tracks in {} folders",
+        server.name,
+        tracks.len(),
+        folders.len()
+    );
     Ok(ScanResult {
         tracks,
         folders,
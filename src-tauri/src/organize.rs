@@ -0,0 +1,465 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tauri::Emitter;
+
+use super::{audio_files, identity, path_policy};
+use crate::error::CommandError;
+use crate::tags::{read_track_tags, TrackTags};
+
+const FOLDER_SIDECAR_EXTENSIONS: [&str; 5] = ["jpg", "jpeg", "png", "webp", "cue"];
+const TRACK_SIDECAR_EXTENSIONS: [&str; 2] = ["lrc", "cue"];
+const ILLEGAL_PATH_CHARS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedMove {
+    source: String,
+    target: String,
+    unchanged: bool,
+    conflict: bool,
+    issues: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OrganizeConflict {
+    target: String,
+    sources: Vec<String>,
+    existing_file: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OrganizePlan {
+    root: String,
+    template: String,
+    moves: Vec<PlannedMove>,
+    conflicts: Vec<OrganizeConflict>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct OrganizeProgress {
+    done: usize,
+    total: usize,
+    current: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OrganizeResult {
+    moved: usize,
+    skipped: usize,
+    failed: Vec<OrganizeFailure>,
+    removed_folders: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OrganizeFailure {
+    source: String,
+    error: String,
+}
+
+enum TemplatePart {
+    Literal(String),
+    Field { name: String, width: Option<usize> },
+}
+
+fn parse_template(template: &str) -> Result<Vec<TemplatePart>, CommandError> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut chars = template.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '}' {
+            return Err(CommandError::invalid_input("Unmatched '}' in template"));
+        }
+        if c != '{' {
+            literal.push(if c == '\\' { '/' } else { c });
+            continue;
+        }
+        if !literal.is_empty() {
+            parts.push(TemplatePart::Literal(std::mem::take(&mut literal)));
+        }
+        let mut token = String::new();
+        loop {
+            match chars.next() {
+                Some('}') => break,
+                Some(c) => token.push(c),
+                None => return Err(CommandError::invalid_input("Unterminated '{' in template")),
+            }
+        }
+        let (name, spec) = match token.split_once(':') {
+            Some((name, spec)) => (name.trim().to_lowercase(), Some(spec.trim())),
+            None => (token.trim().to_lowercase(), None),
+        };
+        if !matches!(
+            name.as_str(),
+            "artist" | "albumartist" | "album" | "title" | "track" | "disc" | "year" | "genre" | "ext"
+        ) {
+            return Err(CommandError::invalid_input(format!("Unknown template field {{{}}}", name)));
+        }
+        let width = match spec {
+            Some(spec) => Some(
+                spec.parse::<usize>()
+                    .map_err(|_| CommandError::invalid_input(format!("Invalid format for {{{}}}: {}", name, spec)))?,
+            ),
+            None => None,
+        };
+        parts.push(TemplatePart::Field { name, width });
+    }
+    if !literal.is_empty() {
+        parts.push(TemplatePart::Literal(literal));
+    }
+    Ok(parts)
+}
+
+fn field_value(name: &str, tags: &TrackTags, ext: &str, fallback_title: &str) -> String {
+    let text = |v: &Option<String>| v.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string);
+    match name {
+        "artist" => text(&tags.artist).unwrap_or_else(|| "Unknown Artist".to_string()),
+        "albumartist" => text(&tags.album_artist)
+            .or_else(|| text(&tags.artist))
+            .unwrap_or_else(|| "Unknown Artist".to_string()),
+        "album" => text(&tags.album).unwrap_or_else(|| "Unknown Album".to_string()),
+        "title" => text(&tags.title).unwrap_or_else(|| fallback_title.to_string()),
+        "genre" => text(&tags.genre).unwrap_or_default(),
+        "track" => tags.track_number.map(|n| n.to_string()).unwrap_or_default(),
+        "disc" => tags.disc_number.map(|n| n.to_string()).unwrap_or_default(),
+        "year" => tags.year.map(|n| n.to_string()).unwrap_or_default(),
+        "ext" => ext.to_string(),
+        _ => String::new(),
+    }
+}
+
+fn sanitize_value(name: &str, value: String, issues: &mut Vec<String>) -> String {
+    if !value.chars().any(|c| ILLEGAL_PATH_CHARS.contains(&c) || c.is_control()) {
+        return value;
+    }
+    issues.push(format!("Illegal characters in {} were replaced: {}", name, value));
+    value
+        .chars()
+        .map(|c| if ILLEGAL_PATH_CHARS.contains(&c) || c.is_control() { '_' } else { c })
+        .collect()
+}
+
+fn sanitize_component(component: &str, issues: &mut Vec<String>) -> String {
+    let trimmed = component.trim().trim_end_matches('.').trim_end();
+    let mut out = if trimmed.is_empty() || trimmed == "." || trimmed == ".." {
+        "_".to_string()
+    } else {
+        trimmed.to_string()
+    };
+    let stem = out.split('.').next().unwrap_or_default().to_uppercase();
+    if RESERVED_NAMES.contains(&stem.as_str()) {
+        issues.push(format!("Reserved file name was escaped: {}", out));
+        out.insert(0, '_');
+    }
+    out
+}
+
+fn render_relative_path(parts: &[TemplatePart], tags: &TrackTags, ext: &str, fallback_title: &str, issues: &mut Vec<String>) -> PathBuf {
+    let mut rendered = String::new();
+    for part in parts {
+        match part {
+            TemplatePart::Literal(text) => rendered.push_str(text),
+            TemplatePart::Field { name, width } => {
+                let mut value = field_value(name, tags, ext, fallback_title);
+                if let Some(width) = width {
+                    if !value.is_empty() && value.len() < *width {
+                        value = format!("{}{}", "0".repeat(width - value.len()), value);
+                    }
+                }
+                rendered.push_str(&sanitize_value(name, value, issues));
+            }
+        }
+    }
+
+    rendered
+        .split('/')
+        .filter(|c| !c.trim().is_empty())
+        .map(|c| sanitize_component(c, issues))
+        .collect()
+}
+
+// Windows and macOS file systems are case-insensitive by default, so a case-only rename is a no-op there.
+fn path_key(path: &Path) -> String {
+    let key = path.to_string_lossy();
+    if cfg!(any(windows, target_os = "macos")) {
+        key.to_lowercase()
+    } else {
+        key.to_string()
+    }
+}
+
+fn build_plan(root: &str, template: &str) -> Result<OrganizePlan, CommandError> {
+    let parts = parse_template(template)?;
+    // Not canonicalized, so moved paths keep the spelling favorites and history were recorded with.
+    let root_path = PathBuf::from(root);
+    if !root_path.is_dir() {
+        return Err(CommandError::not_found(format!("{} is not a folder", root)));
+    }
+
+    let mut moves = Vec::new();
+    for source in audio_files(&root_path) {
+        let mut issues = Vec::new();
+        let ext = source
+            .extension()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_lowercase();
+        let fallback_title = source
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let tags = match read_track_tags(&source) {
+            Ok(tags) => tags,
+            Err(e) => {
+                issues.push(format!("Tags could not be read: {}", e));
+                TrackTags::default()
+            }
+        };
+        let relative = render_relative_path(&parts, &tags, &ext, &fallback_title, &mut issues);
+        let target = root_path.join(relative);
+        moves.push(PlannedMove {
+            unchanged: path_key(&target) == path_key(&source),
+            source: source.to_string_lossy().to_string(),
+            target: target.to_string_lossy().to_string(),
+            conflict: false,
+            issues,
+        });
+    }
+
+    let sources: HashSet<String> = moves.iter().map(|m| path_key(Path::new(&m.source))).collect();
+    let mut by_target: HashMap<String, Vec<usize>> = HashMap::new();
+    for (idx, m) in moves.iter().enumerate() {
+        by_target.entry(path_key(Path::new(&m.target))).or_default().push(idx);
+    }
+
+    let mut conflicts = Vec::new();
+    for indices in by_target.values() {
+        let first = &moves[indices[0]];
+        let target_path = Path::new(&first.target);
+        let existing_file = indices.len() == 1
+            && !first.unchanged
+            && target_path.exists()
+            && !sources.contains(&path_key(target_path));
+        if indices.len() > 1 || existing_file {
+            conflicts.push(OrganizeConflict {
+                target: first.target.clone(),
+                sources: indices.iter().map(|i| moves[*i].source.clone()).collect(),
+                existing_file,
+            });
+            for i in indices {
+                moves[*i].conflict = true;
+            }
+        }
+    }
+    conflicts.sort_by(|a, b| a.target.cmp(&b.target));
+
+    Ok(OrganizePlan {
+        root: root_path.to_string_lossy().to_string(),
+        template: template.to_string(),
+        moves,
+        conflicts,
+    })
+}
+
+fn move_file(source: &Path, target: &Path) -> std::io::Result<()> {
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if std::fs::rename(source, target).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(source, target)?;
+    std::fs::remove_file(source)
+}
+
+fn with_stem_extension(dir: &Path, stem: &std::ffi::OsStr, ext: &str) -> PathBuf {
+    let mut name = stem.to_os_string();
+    name.push(".");
+    name.push(ext);
+    dir.join(name)
+}
+
+fn track_sidecars(source: &Path, target: &Path) -> Vec<(PathBuf, PathBuf)> {
+    let (Some(stem), Some(target_stem)) = (source.file_stem(), target.file_stem()) else {
+        return Vec::new();
+    };
+    let (Some(source_dir), Some(target_dir)) = (source.parent(), target.parent()) else {
+        return Vec::new();
+    };
+    TRACK_SIDECAR_EXTENSIONS
+        .iter()
+        .map(|ext| {
+            (
+                with_stem_extension(source_dir, stem, ext),
+                with_stem_extension(target_dir, target_stem, ext),
+            )
+        })
+        .filter(|(p, _)| p.is_file())
+        .collect()
+}
+
+fn folder_sidecars(source_dir: &Path, target_dir: &Path) -> Vec<(PathBuf, PathBuf)> {
+    let Ok(entries) = std::fs::read_dir(source_dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .filter(|p| {
+            p.extension()
+                .map(|ext| FOLDER_SIDECAR_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()))
+                .unwrap_or(false)
+        })
+        .filter_map(|p| {
+            let name = p.file_name()?.to_os_string();
+            Some((p, target_dir.join(name)))
+        })
+        .collect()
+}
+
+fn remove_empty_dirs(root: &Path, dirs: HashSet<PathBuf>) -> Vec<String> {
+    let mut removed = Vec::new();
+    let mut candidates: Vec<PathBuf> = dirs.into_iter().collect();
+    candidates.sort_by_key(|p| std::cmp::Reverse(p.components().count()));
+
+    for dir in candidates {
+        let mut current = dir;
+        while current.starts_with(root) && current != root {
+            let is_empty = std::fs::read_dir(&current)
+                .map(|mut entries| entries.next().is_none())
+                .unwrap_or(false);
+            if !is_empty || std::fs::remove_dir(&current).is_err() {
+                break;
+            }
+            removed.push(current.to_string_lossy().to_string());
+            match current.parent() {
+                Some(parent) => current = parent.to_path_buf(),
+                None => break,
+            }
+        }
+    }
+    removed
+}
+
+fn apply_plan(app: &tauri::AppHandle, plan: &OrganizePlan) -> Result<OrganizeResult, CommandError> {
+    let root = PathBuf::from(&plan.root);
+    let pending: Vec<&PlannedMove> = plan.moves.iter().filter(|m| !m.unchanged && !m.conflict).collect();
+    let total = pending.len();
+
+    let mut moved_paths: HashMap<String, String> = HashMap::new();
+    let mut touched_dirs: HashSet<PathBuf> = HashSet::new();
+    let mut folder_targets: HashMap<PathBuf, PathBuf> = HashMap::new();
+    let mut failed = Vec::new();
+
+    for (idx, m) in pending.iter().enumerate() {
+        let _ = app.emit(
+            "organize-progress",
+            OrganizeProgress {
+                done: idx,
+                total,
+                current: m.source.clone(),
+            },
+        );
+
+        let source = Path::new(&m.source);
+        let target = Path::new(&m.target);
+        if target.exists() {
+            failed.push(OrganizeFailure {
+                source: m.source.clone(),
+                error: "Target file already exists".to_string(),
+            });
+            continue;
+        }
+        let sidecars = track_sidecars(source, target);
+        if let Err(e) = move_file(source, target) {
+            failed.push(OrganizeFailure {
+                source: m.source.clone(),
+                error: e.to_string(),
+            });
+            continue;
+        }
+        for (sidecar_source, sidecar_target) in sidecars {
+            if !sidecar_target.exists() {
+                if let Err(e) = move_file(&sidecar_source, &sidecar_target) {
+                    log::warn!(target: "organize", "failed to move {}: {}", sidecar_source.display(), e);
+                }
+            }
+        }
+
+        moved_paths.insert(m.source.clone(), m.target.clone());
+        if let (Some(source_dir), Some(target_dir)) = (source.parent(), target.parent()) {
+            touched_dirs.insert(source_dir.to_path_buf());
+            folder_targets
+                .entry(source_dir.to_path_buf())
+                .or_insert_with(|| target_dir.to_path_buf());
+        }
+    }
+
+    for (source_dir, target_dir) in &folder_targets {
+        if source_dir == target_dir {
+            continue;
+        }
+        let has_remaining_audio = audio_files(source_dir)
+            .iter()
+            .any(|p| p.parent() == Some(source_dir.as_path()));
+        if has_remaining_audio {
+            continue;
+        }
+        for (sidecar_source, sidecar_target) in folder_sidecars(source_dir, target_dir) {
+            if !sidecar_target.exists() {
+                if let Err(e) = move_file(&sidecar_source, &sidecar_target) {
+                    log::warn!(target: "organize", "failed to move {}: {}", sidecar_source.display(), e);
+                }
+            }
+        }
+    }
+
+    let removed_folders = remove_empty_dirs(&root, touched_dirs);
+
+    if !moved_paths.is_empty() {
+        identity::relink_tracks(&app, &moved_paths)?;
+    }
+
+    let _ = app.emit(
+        "organize-progress",
+        OrganizeProgress {
+            done: total,
+            total,
+            current: String::new(),
+        },
+    );
+
+    Ok(OrganizeResult {
+        moved: moved_paths.len(),
+        skipped: plan.moves.len() - total,
+        failed,
+        removed_folders,
+    })
+}
+
+#[tauri::command]
+pub async fn plan_organize(root: String, template: String) -> Result<OrganizePlan, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || build_plan(&root, &template)).await?
+}
+
+#[tauri::command]
+pub async fn apply_organize(app: tauri::AppHandle, root: String, template: String) -> Result<OrganizeResult, CommandError> {
+    path_policy::resolve_existing_or_root(&app, &root)?;
+    tauri::async_runtime::spawn_blocking(move || -> Result<OrganizeResult, CommandError> {
+        let plan = build_plan(&root, &template)?;
+        apply_plan(&app, &plan)
+    })
+    .await?
+}
@@ -0,0 +1,110 @@
+//! Every command that creates, moves, deletes or opens files on behalf of the
+//! webview must resolve its paths through this module. Paths are canonicalized
+//! (resolving symlinks) and must stay inside one of the configured music folders.
+
+use std::path::{Component, Path, PathBuf};
+
+use super::load_config;
+use crate::error::CommandError;
+use crate::query;
+
+fn library_roots(app: &tauri::AppHandle) -> Result<Vec<PathBuf>, CommandError> {
+    let config = load_config(app.clone())?;
+    Ok(config
+        .music_folders
+        .iter()
+        .filter_map(|p| std::fs::canonicalize(p).ok())
+        .collect())
+}
+
+fn check_raw_path(path: &Path) -> Result<(), CommandError> {
+    if !path.is_absolute() {
+        return Err(CommandError::path_not_allowed("Path must be absolute"));
+    }
+    if path.components().any(|c| matches!(c, Component::ParentDir)) {
+        return Err(CommandError::path_not_allowed("Path traversal is not allowed"));
+    }
+    Ok(())
+}
+
+fn check_inside_library(roots: &[PathBuf], canonical: &Path, allow_root: bool) -> Result<(), CommandError> {
+    let allowed = roots
+        .iter()
+        .any(|root| canonical.starts_with(root) && (allow_root || canonical != root));
+    if allowed {
+        Ok(())
+    } else {
+        Err(CommandError::path_not_allowed(format!(
+            "{} is outside the configured music folders",
+            canonical.display()
+        )))
+    }
+}
+
+/// Resolves an existing file or folder strictly inside a music folder.
+pub(crate) fn resolve_existing(app: &tauri::AppHandle, path: &str) -> Result<PathBuf, CommandError> {
+    resolve(app, path, false)
+}
+
+/// Like [`resolve_existing`], but also accepts a music folder itself.
+pub(crate) fn resolve_existing_or_root(app: &tauri::AppHandle, path: &str) -> Result<PathBuf, CommandError> {
+    resolve(app, path, true)
+}
+
+fn resolve(app: &tauri::AppHandle, path: &str, allow_root: bool) -> Result<PathBuf, CommandError> {
+    let raw = Path::new(path);
+    check_raw_path(raw)?;
+    let canonical = std::fs::canonicalize(raw)?;
+    check_inside_library(&library_roots(app)?, &canonical, allow_root)?;
+    Ok(canonical)
+}
+
+/// Resolves a local track id, either its file path or its stable identity, to its file.
+pub(crate) fn resolve_track(app: &tauri::AppHandle, track_id: &str) -> Result<PathBuf, CommandError> {
+    if Path::new(track_id).is_absolute() {
+        return resolve_existing(app, track_id);
+    }
+    let track = query::local_tracks(track_id)
+        .into_iter()
+        .next()
+        .ok_or_else(|| CommandError::not_found("Track is not in the library"))?;
+    resolve_existing(app, &track.audio_url)
+}
+
+/// Resolves a path that is about to be created as `name` inside `parent`.
+pub(crate) fn resolve_new_child(app: &tauri::AppHandle, parent: &str, name: &str) -> Result<PathBuf, CommandError> {
+    let name = name.trim();
+    let mut components = Path::new(name).components();
+    let is_single_normal = matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    );
+    if !is_single_normal || name.contains(['/', '\\']) {
+        return Err(CommandError::path_not_allowed(format!("Invalid name: {}", name)));
+    }
+
+    let parent = resolve_existing_or_root(app, parent)?;
+    Ok(parent.join(name))
+}
+
+/// Resolves a target path for a file that may not exist yet, such as a move
+/// destination. The nearest existing ancestor must be inside a music folder.
+pub(crate) fn resolve_target(app: &tauri::AppHandle, path: &Path) -> Result<PathBuf, CommandError> {
+    check_raw_path(path)?;
+    let mut existing = path;
+    let mut missing = Vec::new();
+    while !existing.exists() {
+        let (Some(parent), Some(name)) = (existing.parent(), existing.file_name()) else {
+            return Err(CommandError::path_not_allowed("Path has no existing parent"));
+        };
+        missing.push(name.to_os_string());
+        existing = parent;
+    }
+
+    let mut canonical = std::fs::canonicalize(existing)?;
+    for name in missing.into_iter().rev() {
+        canonical.push(name);
+    }
+    check_inside_library(&library_roots(app)?, &canonical, false)?;
+    Ok(canonical)
+}
@@ -0,0 +1,708 @@
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::hash::{Hash, Hasher};
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+use super::{path_policy, to_unix_seconds, MusicFolder, ScanResult, Track};
+use crate::error::CommandError;
+use crate::query::index_snapshot;
+use crate::store;
+
+const PLAYLISTS_FILE: &str = "playlists.json";
+const ROOT_ID: &str = "playlists";
+
+pub(crate) const PLAYLIST_EXTENSIONS: [&str; 4] = ["m3u", "m3u8", "pls", "xspf"];
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalPlaylist {
+    id: String,
+    name: String,
+    created_at: u64,
+    updated_at: u64,
+    track_ids: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistImport {
+    playlist: LocalPlaylist,
+    missing: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistExport {
+    path: String,
+    written: usize,
+    skipped: usize,
+}
+
+#[derive(Default)]
+struct ParsedPlaylist {
+    name: Option<String>,
+    entries: Vec<String>,
+}
+
+fn now() -> u64 {
+    to_unix_seconds(SystemTime::now())
+}
+
+fn playlist_format(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_string_lossy().to_lowercase();
+    PLAYLIST_EXTENSIONS.into_iter().find(|e| *e == ext)
+}
+
+fn read_text(path: &Path) -> Result<String, CommandError> {
+    let bytes = std::fs::read(path)?;
+    let bytes = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(&bytes);
+    Ok(match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|&b| char::from(b)).collect(),
+    })
+}
+
+fn parse_m3u(text: &str) -> ParsedPlaylist {
+    let mut parsed = ParsedPlaylist::default();
+    for line in text.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix("#PLAYLIST:") {
+            parsed.name = Some(name.trim().to_string());
+        } else if !line.is_empty() && !line.starts_with('#') {
+            parsed.entries.push(line.to_string());
+        }
+    }
+    parsed
+}
+
+fn parse_pls(text: &str) -> ParsedPlaylist {
+    let mut files: Vec<(u32, String)> = text
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.trim().split_once('=')?;
+            let index = key.trim().strip_prefix("File")?.parse().ok()?;
+            Some((index, value.trim().to_string()))
+        })
+        .collect();
+    files.sort_by_key(|(index, _)| *index);
+    ParsedPlaylist {
+        name: None,
+        entries: files.into_iter().map(|(_, file)| file).collect(),
+    }
+}
+
+fn xml_unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        let Some(len) = rest[start..].find(';') else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let entity = &rest[start + 1..start + len];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => out.push(c),
+            None => out.push_str(&rest[start..start + len + 1]),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+pub(crate) fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn xml_elements<'a>(text: &'a str, tag: &str) -> Vec<&'a str> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let mut found = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(&open) {
+        let body = &rest[start + open.len()..];
+        let Some(end) = body.find(&close) else {
+            break;
+        };
+        found.push(&body[..end]);
+        rest = &body[end + close.len()..];
+    }
+    found
+}
+
+fn parse_xspf(text: &str) -> ParsedPlaylist {
+    let header = text.split("<trackList>").next().unwrap_or_default();
+    ParsedPlaylist {
+        name: xml_elements(header, "title").first().map(|t| xml_unescape(t.trim())),
+        entries: xml_elements(text, "location")
+            .into_iter()
+            .map(|l| {
+                let location = xml_unescape(l.trim());
+                if location.contains("://") {
+                    location
+                } else {
+                    percent_decode(&location)
+                }
+            })
+            .collect(),
+    }
+}
+
+fn parse_playlist_file(path: &Path) -> Result<ParsedPlaylist, CommandError> {
+    let text = read_text(path)?;
+    Ok(match playlist_format(path) {
+        Some("pls") => parse_pls(&text),
+        Some("xspf") => parse_xspf(&text),
+        Some(_) => parse_m3u(&text),
+        None => return Err(CommandError::invalid_input("Unsupported playlist format")),
+    })
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = |b: u8| char::from(b).to_digit(16);
+            if let (Some(hi), Some(lo)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                out.push((hi * 16 + lo) as u8);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+fn percent_encode(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for b in text.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~/".contains(&b) {
+            out.push(char::from(b));
+        } else {
+            let _ = write!(out, "%{:02X}", b);
+        }
+    }
+    out
+}
+
+fn lexical_normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
+}
+
+fn resolve_entry(base_dir: &Path, entry: &str) -> Option<PathBuf> {
+    let entry = entry.trim();
+    let entry = match entry.strip_prefix("file://") {
+        Some(rest) => {
+            let decoded = percent_decode(rest.strip_prefix("localhost").unwrap_or(rest));
+            let bytes = decoded.as_bytes();
+            if cfg!(windows) && bytes.len() > 2 && bytes[0] == b'/' && bytes[2] == b':' {
+                decoded[1..].to_string()
+            } else {
+                decoded
+            }
+        }
+        None if entry.contains("://") => return None,
+        None => entry.to_string(),
+    };
+    let entry = if cfg!(windows) { entry } else { entry.replace('\\', "/") };
+    let path = Path::new(&entry);
+    let joined = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        base_dir.join(path)
+    };
+    Some(lexical_normalize(&joined))
+}
+
+fn relative_path(from_dir: &Path, target: &Path) -> Option<PathBuf> {
+    let from: Vec<Component> = from_dir.components().collect();
+    let to: Vec<Component> = target.components().collect();
+    if from.first() != to.first() {
+        return None;
+    }
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut out = PathBuf::new();
+    for _ in common..from.len() {
+        out.push("..");
+    }
+    for component in &to[common..] {
+        out.push(component.as_os_str());
+    }
+    Some(out)
+}
+
+fn file_uri(path: &Path) -> String {
+    let text = path.to_string_lossy().replace('\\', "/");
+    if text.starts_with('/') {
+        format!("file://{}", percent_encode(&text))
+    } else {
+        format!("file:///{}", percent_encode(&text))
+    }
+}
+
+pub(crate) fn scan_folders(root_id: &str, root: &Path, files: &[PathBuf], tracks: &[Track]) -> Vec<MusicFolder> {
+    let mut folders = Vec::new();
+    if files.is_empty() || tracks.is_empty() {
+        return folders;
+    }
+
+    let by_path: HashMap<String, &Track> = tracks.iter().map(|t| (t.audio_url.to_lowercase(), t)).collect();
+    let playlists_id = format!("{}:playlists", root_id);
+    let mut total = 0;
+
+    for file in files {
+        let parsed = match parse_playlist_file(file) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                log::warn!(target: "scanner", "failed to read playlist {}: {}", file.display(), e);
+                continue;
+            }
+        };
+        let base_dir = file.parent().unwrap_or(root);
+        let relative = file.strip_prefix(root).unwrap_or(file).to_string_lossy().to_lowercase();
+        let folder_id = format!("{}:playlist:{}", root_id, relative);
+
+        let mut seen = HashSet::new();
+        let mut track_ids = Vec::new();
+        for entry in &parsed.entries {
+            let Some(path) = resolve_entry(base_dir, entry) else {
+                continue;
+            };
+            let Some(track) = by_path.get(&path.to_string_lossy().to_lowercase()) else {
+                continue;
+            };
+            if seen.insert(track.canonical_id.as_str()) {
+                track_ids.push(track.id.clone());
+            }
+        }
+        total += track_ids.len();
+
+        let name = parsed.name.filter(|n| !n.is_empty()).unwrap_or_else(|| {
+            file.file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+        folders.push(MusicFolder {
+            id: folder_id.clone(),
+            parent_id: Some(playlists_id.clone()),
+            name,
+            path: folder_id,
+            track_count: track_ids.len(),
+            source: "local".to_string(),
+            track_ids,
+        });
+    }
+
+    if !folders.is_empty() {
+        folders.insert(
+            0,
+            MusicFolder {
+                id: playlists_id.clone(),
+                parent_id: Some(root_id.to_string()),
+                name: "Playlists".to_string(),
+                path: playlists_id,
+                track_count: total,
+                source: "local".to_string(),
+                track_ids: Vec::new(),
+            },
+        );
+    }
+    folders
+}
+
+pub(crate) fn relink_tracks(app: &tauri::AppHandle, moved: &HashMap<String, String>) -> Result<(), CommandError> {
+    let mut playlists: Vec<LocalPlaylist> = store::load(app, PLAYLISTS_FILE)?;
+    let mut changed = false;
+    for playlist in &mut playlists {
+        for id in &mut playlist.track_ids {
+            if let Some(target) = moved.get(id) {
+                *id = target.clone();
+                changed = true;
+            }
+        }
+    }
+    if changed {
+        store::save(app, PLAYLISTS_FILE, &playlists)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn track_lists(app: &tauri::AppHandle) -> Result<Vec<Vec<String>>, CommandError> {
+    let playlists: Vec<LocalPlaylist> = store::load(app, PLAYLISTS_FILE)?;
+    Ok(playlists.into_iter().map(|p| p.track_ids).collect())
+}
+
+fn update_playlist(
+    app: &tauri::AppHandle,
+    id: &str,
+    change: impl FnOnce(&mut LocalPlaylist) -> Result<(), CommandError>,
+) -> Result<LocalPlaylist, CommandError> {
+    let mut playlists: Vec<LocalPlaylist> = store::load(app, PLAYLISTS_FILE)?;
+    let playlist = playlists
+        .iter_mut()
+        .find(|p| p.id == id)
+        .ok_or_else(|| CommandError::not_found(format!("Playlist not found: {}", id)))?;
+    change(playlist)?;
+    playlist.updated_at = now();
+    let updated = playlist.clone();
+    store::save(app, PLAYLISTS_FILE, &playlists)?;
+    Ok(updated)
+}
+
+fn clean_name(name: &str) -> Result<String, CommandError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(CommandError::invalid_input("Playlist name cannot be empty"));
+    }
+    Ok(name.to_string())
+}
+
+fn insert_unique(target: &mut Vec<String>, track_ids: Vec<String>, position: Option<usize>) {
+    let mut seen: HashSet<String> = target.iter().cloned().collect();
+    let new_ids: Vec<String> = track_ids.into_iter().filter(|id| seen.insert(id.clone())).collect();
+    let at = position.unwrap_or(target.len()).min(target.len());
+    target.splice(at..at, new_ids);
+}
+
+pub(crate) fn create(
+    app: &tauri::AppHandle,
+    name: String,
+    track_ids: Vec<String>,
+) -> Result<LocalPlaylist, CommandError> {
+    let created_at = now();
+    let mut playlist = LocalPlaylist {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: clean_name(&name)?,
+        created_at,
+        updated_at: created_at,
+        track_ids: Vec::new(),
+    };
+    insert_unique(&mut playlist.track_ids, track_ids, None);
+
+    let mut playlists: Vec<LocalPlaylist> = store::load(app, PLAYLISTS_FILE)?;
+    playlists.push(playlist.clone());
+    store::save(app, PLAYLISTS_FILE, &playlists)?;
+    Ok(playlist)
+}
+
+#[tauri::command]
+pub fn list_playlists(app: tauri::AppHandle) -> Result<Vec<LocalPlaylist>, CommandError> {
+    store::load(&app, PLAYLISTS_FILE)
+}
+
+#[tauri::command]
+pub fn create_playlist(
+    app: tauri::AppHandle,
+    name: String,
+    track_ids: Option<Vec<String>>,
+) -> Result<LocalPlaylist, CommandError> {
+    create(&app, name, track_ids.unwrap_or_default())
+}
+
+#[tauri::command]
+pub fn rename_playlist(app: tauri::AppHandle, id: String, name: String) -> Result<LocalPlaylist, CommandError> {
+    let name = clean_name(&name)?;
+    update_playlist(&app, &id, |p| {
+        p.name = name;
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn add_to_playlist(
+    app: tauri::AppHandle,
+    id: String,
+    track_ids: Vec<String>,
+    position: Option<usize>,
+) -> Result<LocalPlaylist, CommandError> {
+    update_playlist(&app, &id, |p| {
+        insert_unique(&mut p.track_ids, track_ids, position);
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn remove_from_playlist(
+    app: tauri::AppHandle,
+    id: String,
+    track_ids: Vec<String>,
+) -> Result<LocalPlaylist, CommandError> {
+    let removed: HashSet<String> = track_ids.into_iter().collect();
+    update_playlist(&app, &id, |p| {
+        p.track_ids.retain(|t| !removed.contains(t));
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn move_playlist_track(
+    app: tauri::AppHandle,
+    id: String,
+    from: usize,
+    to: usize,
+) -> Result<LocalPlaylist, CommandError> {
+    update_playlist(&app, &id, |p| {
+        if from >= p.track_ids.len() || to >= p.track_ids.len() {
+            return Err(CommandError::invalid_input("Playlist position out of range"));
+        }
+        let track = p.track_ids.remove(from);
+        p.track_ids.insert(to, track);
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn delete_playlist(app: tauri::AppHandle, id: String) -> Result<(), CommandError> {
+    let mut playlists: Vec<LocalPlaylist> = store::load(&app, PLAYLISTS_FILE)?;
+    let before = playlists.len();
+    playlists.retain(|p| p.id != id);
+    if playlists.len() == before {
+        return Err(CommandError::not_found(format!("Playlist not found: {}", id)));
+    }
+    store::save(&app, PLAYLISTS_FILE, &playlists)
+}
+
+#[tauri::command]
+pub async fn import_playlist(app: tauri::AppHandle, path: String) -> Result<PlaylistImport, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || -> Result<PlaylistImport, CommandError> {
+        let file = std::fs::canonicalize(&path)?;
+        let parsed = parse_playlist_file(&file)?;
+        let base_dir = file.parent().unwrap_or(Path::new("/"));
+
+        let (library, _) = index_snapshot();
+        let by_path: HashMap<String, String> = library
+            .into_iter()
+            .filter(|t| t.source == "local")
+            .map(|t| (t.audio_url.to_lowercase(), t.canonical_id))
+            .collect();
+
+        let mut track_ids = Vec::new();
+        let mut missing = Vec::new();
+        for entry in &parsed.entries {
+            let resolved = resolve_entry(base_dir, entry).and_then(|p| {
+                let key = p.to_string_lossy().to_lowercase();
+                by_path
+                    .get(&key)
+                    .cloned()
+                    .or_else(|| p.is_file().then(|| p.to_string_lossy().to_string()))
+            });
+            match resolved {
+                Some(id) => track_ids.push(id),
+                None => missing.push(entry.clone()),
+            }
+        }
+
+        let name = parsed.name.filter(|n| !n.is_empty()).unwrap_or_else(|| {
+            file.file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| "Imported Playlist".to_string())
+        });
+        let playlist = create(&app, name, track_ids)?;
+        log::info!(
+            target: "playlists",
+            "imported {} as {} ({} tracks, {} missing)",
+            file.display(),
+            playlist.id,
+            playlist.track_ids.len(),
+            missing.len()
+        );
+        Ok(PlaylistImport { playlist, missing })
+    })
+    .await?
+}
+
+#[tauri::command]
+pub async fn export_playlist(
+    app: tauri::AppHandle,
+    id: String,
+    path: String,
+    relative: Option<bool>,
+    overwrite: Option<bool>,
+) -> Result<PlaylistExport, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || -> Result<PlaylistExport, CommandError> {
+        let format = playlist_format(Path::new(&path))
+            .ok_or_else(|| CommandError::invalid_input("Playlist files must end in .m3u, .m3u8, .pls or .xspf"))?;
+        let target = path_policy::resolve_target(&app, Path::new(&path))?;
+        if target.is_dir() {
+            return Err(CommandError::invalid_input("Export path is a folder"));
+        }
+        if target.exists() && !overwrite.unwrap_or(false) {
+            return Err(CommandError::invalid_input(format!("{} already exists", target.display())));
+        }
+        let dir = target
+            .parent()
+            .ok_or_else(|| CommandError::invalid_input("Invalid export path"))?
+            .to_path_buf();
+
+        let playlists: Vec<LocalPlaylist> = store::load(&app, PLAYLISTS_FILE)?;
+        let playlist = playlists
+            .into_iter()
+            .find(|p| p.id == id)
+            .ok_or_else(|| CommandError::not_found(format!("Playlist not found: {}", id)))?;
+
+        let (library, _) = index_snapshot();
+        let by_id: HashMap<&str, &Track> = library.iter().map(|t| (t.canonical_id.as_str(), t)).collect();
+
+        let relative = relative.unwrap_or(false);
+        let mut entries = Vec::new();
+        let mut skipped = 0;
+        for track_id in &playlist.track_ids {
+            let track = by_id.get(track_id.as_str()).copied();
+            let track_path = match track {
+                Some(track) if track.source == "local" => Path::new(&track.audio_url),
+                _ if Path::new(track_id).is_absolute() => Path::new(track_id),
+                _ => {
+                    skipped += 1;
+                    continue;
+                }
+            };
+            let location = if relative {
+                relative_path(&dir, track_path).unwrap_or_else(|| track_path.to_path_buf())
+            } else {
+                track_path.to_path_buf()
+            };
+            entries.push((location, track));
+        }
+
+        let mut out = String::new();
+        match format {
+            "pls" => {
+                out.push_str("[playlist]\n");
+                for (i, (location, track)) in entries.iter().enumerate() {
+                    let n = i + 1;
+                    let _ = writeln!(out, "File{}={}", n, location.display());
+                    if let Some(track) = track {
+                        let _ = writeln!(out, "Title{}={} - {}", n, track.artist, track.title);
+                        let _ = writeln!(out, "Length{}={}", n, track.duration);
+                    }
+                }
+                let _ = writeln!(out, "NumberOfEntries={}\nVersion=2", entries.len());
+            }
+            "xspf" => {
+                out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+                out.push_str("<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n");
+                let _ = writeln!(out, "  <title>{}</title>\n  <trackList>", xml_escape(&playlist.name));
+                for (location, track) in &entries {
+                    let uri = if location.is_absolute() {
+                        file_uri(location)
+                    } else {
+                        percent_encode(&location.to_string_lossy().replace('\\', "/"))
+                    };
+                    let _ = writeln!(out, "    <track>\n      <location>{}</location>", xml_escape(&uri));
+                    if let Some(track) = track {
+                        let _ = writeln!(out, "      <title>{}</title>", xml_escape(&track.title));
+                        let _ = writeln!(out, "      <creator>{}</creator>", xml_escape(&track.artist));
+                        let _ = writeln!(out, "      <album>{}</album>", xml_escape(&track.album));
+                        let _ = writeln!(out, "      <duration>{}</duration>", track.duration * 1000);
+                    }
+                    out.push_str("    </track>\n");
+                }
+                out.push_str("  </trackList>\n</playlist>\n");
+            }
+            _ => {
+                let _ = writeln!(out, "#EXTM3U\n#PLAYLIST:{}", playlist.name);
+                for (location, track) in &entries {
+                    if let Some(track) = track {
+                        let _ = writeln!(out, "#EXTINF:{},{} - {}", track.duration, track.artist, track.title);
+                    }
+                    let _ = writeln!(out, "{}", location.display());
+                }
+            }
+        }
+        std::fs::write(&target, out)?;
+
+        Ok(PlaylistExport {
+            path: target.to_string_lossy().to_string(),
+            written: entries.len(),
+            skipped,
+        })
+    })
+    .await?
+}
+
+#[tauri::command]
+pub async fn scan_playlists(app: tauri::AppHandle) -> Result<ScanResult, CommandError> {
+    let playlists: Vec<LocalPlaylist> = store::load(&app, PLAYLISTS_FILE)?;
+    tauri::async_runtime::spawn_blocking(move || -> Result<ScanResult, CommandError> {
+        let (library, index_revision) = index_snapshot();
+        let by_id: HashMap<&str, &Track> = library.iter().map(|t| (t.canonical_id.as_str(), t)).collect();
+
+        let mut hasher = DefaultHasher::new();
+        index_revision.hash(&mut hasher);
+        serde_json::to_string(&playlists)?.hash(&mut hasher);
+        let mut result = ScanResult {
+            tracks: Vec::new(),
+            folders: Vec::new(),
+            revision: format!("{:016x}", hasher.finish()),
+        };
+        if playlists.is_empty() {
+            return Ok(result);
+        }
+
+        let mut total = 0;
+        for playlist in &playlists {
+            let folder_id = format!("playlist:{}", playlist.id);
+            let track_ids: Vec<String> = playlist
+                .track_ids
+                .iter()
+                .filter_map(|id| by_id.get(id.as_str()))
+                .map(|t| t.id.clone())
+                .collect();
+            total += track_ids.len();
+            result.folders.push(MusicFolder {
+                id: folder_id.clone(),
+                parent_id: Some(ROOT_ID.to_string()),
+                name: playlist.name.clone(),
+                path: folder_id,
+                track_count: track_ids.len(),
+                source: "local".to_string(),
+                track_ids,
+            });
+        }
+        result.folders.insert(
+            0,
+            MusicFolder {
+                id: ROOT_ID.to_string(),
+                parent_id: None,
+                name: "Playlists".to_string(),
+                path: ROOT_ID.to_string(),
+                track_count: total,
+                source: "local".to_string(),
+                track_ids: Vec::new(),
+            },
+        );
+        Ok(result)
+    })
+    .await?
+}
@@ -0,0 +1,592 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::{LazyLock, Mutex};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use super::Track;
+use crate::analysis::{track_key, MusicalKey};
+use crate::error::CommandError;
+
+const DEFAULT_PAGE_SIZE: usize = 200;
+const MAX_PAGE_SIZE: usize = 5000;
+
+struct IndexedTrack {
+    track: Track,
+    title: String,
+    artist: String,
+    album: String,
+    album_artist: String,
+    genre: String,
+    path: String,
+    format: String,
+    key: Option<MusicalKey>,
+}
+
+struct IndexedSource {
+    revision: String,
+    tracks: Vec<IndexedTrack>,
+}
+
+static LIBRARY_INDEX: LazyLock<Mutex<HashMap<String, IndexedSource>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+static INDEX_EDITS: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Copy, PartialEq)]
+enum TextField {
+    Title,
+    Artist,
+    Album,
+    AlbumArtist,
+    Genre,
+    Path,
+    Format,
+    Source,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum NumberField {
+    Year,
+    Duration,
+    Track,
+    Disc,
+    Bpm,
+}
+
+enum Clause {
+    Term(String),
+    Text(TextField, String),
+    Range(NumberField, Option<u64>, Option<u64>),
+    // `key:8A` matches the key itself, `key:~8A` also its harmonically compatible keys.
+    Key(MusicalKey, bool),
+}
+
+struct Condition {
+    clause: Clause,
+    negate: bool,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SortKey {
+    field: String,
+    #[serde(default)]
+    descending: bool,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TrackQuery {
+    query: String,
+    sort: Vec<SortKey>,
+    offset: usize,
+    limit: Option<usize>,
+    folder_id: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackQueryResult {
+    tracks: Vec<Track>,
+    total: usize,
+    offset: usize,
+}
+
+pub(crate) fn normalize(text: &str) -> String {
+    text.nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn index_track(track: &Track) -> IndexedTrack {
+    let path = if track.source == "local" {
+        normalize(&track.audio_url)
+    } else {
+        String::new()
+    };
+    IndexedTrack {
+        title: normalize(&track.title),
+        artist: normalize(&track.artist),
+        album: normalize(&track.album),
+        album_artist: normalize(track.album_artist.as_deref().unwrap_or_default()),
+        genre: normalize(track.genre.as_deref().unwrap_or_default()),
+        format: track.format.as_deref().unwrap_or_default().to_lowercase(),
+        key: track_key(track),
+        path,
+        track: track.clone(),
+    }
+}
+
+pub(crate) fn update_index(source_key: &str, revision: &str, tracks: &[Track]) {
+    if let Ok(guard) = LIBRARY_INDEX.lock() {
+        if guard.get(source_key).is_some_and(|s| s.revision == revision) {
+            return;
+        }
+    }
+    let source = IndexedSource {
+        revision: revision.to_string(),
+        tracks: tracks.iter().map(index_track).collect(),
+    };
+    if let Ok(mut guard) = LIBRARY_INDEX.lock() {
+        guard.insert(source_key.to_string(), source);
+    }
+}
+
+pub(crate) fn remove_from_index(source_key: &str) {
+    if let Ok(mut guard) = LIBRARY_INDEX.lock() {
+        guard.remove(source_key);
+    }
+}
+
+// Drops a deleted local file from the index, returning its entries so the deletion can be undone.
+pub(crate) fn remove_local_track(path: &str) -> Vec<(String, Track)> {
+    let Ok(mut guard) = LIBRARY_INDEX.lock() else {
+        return Vec::new();
+    };
+    let mut removed = Vec::new();
+    for (key, source) in guard.iter_mut() {
+        let before = source.tracks.len();
+        source.tracks.retain(|t| {
+            let deleted = t.track.source == "local" && t.track.audio_url == path;
+            if deleted {
+                removed.push((key.clone(), t.track.clone()));
+            }
+            !deleted
+        });
+        if source.tracks.len() != before {
+            bump_revision(source);
+        }
+    }
+    removed
+}
+
+pub(crate) fn restore_tracks(entries: Vec<(String, Track)>) {
+    let Ok(mut guard) = LIBRARY_INDEX.lock() else {
+        return;
+    };
+    for (key, track) in entries {
+        // A rescan replaced the source in the meantime and indexes the restored file itself.
+        let Some(source) = guard.get_mut(&key) else {
+            continue;
+        };
+        if source.tracks.iter().all(|t| t.track.id != track.id) {
+            source.tracks.push(index_track(&track));
+            bump_revision(source);
+        }
+    }
+}
+
+// Edited sources get a fresh revision so snapshots change and the next scan re-indexes them.
+fn bump_revision(source: &mut IndexedSource) {
+    let edit = INDEX_EDITS.fetch_add(1, AtomicOrdering::Relaxed);
+    let base = source.revision.split('~').next().unwrap_or_default().to_string();
+    source.revision = format!("{}~{}", base, edit);
+}
+
+pub(crate) fn index_snapshot() -> (Vec<Track>, String) {
+    let Ok(guard) = LIBRARY_INDEX.lock() else {
+        return (Vec::new(), String::new());
+    };
+    let mut keys: Vec<&String> = guard.keys().collect();
+    keys.sort();
+
+    let mut seen = HashSet::new();
+    let mut tracks = Vec::new();
+    let mut revisions = Vec::new();
+    for key in keys {
+        let source = &guard[key];
+        revisions.push(format!("{}={}", key, source.revision));
+        for t in &source.tracks {
+            if seen.insert(t.track.canonical_id.as_str()) {
+                tracks.push(t.track.clone());
+            }
+        }
+    }
+    (tracks, revisions.join("|"))
+}
+
+// Local tracks matching a track id (their path) or canonical id; identical copies share a
+// canonical id. Available copies come first.
+pub(crate) fn local_tracks(track_id: &str) -> Vec<Track> {
+    let Ok(guard) = LIBRARY_INDEX.lock() else {
+        return Vec::new();
+    };
+    let mut found: Vec<&Track> = guard
+        .values()
+        .flat_map(|s| &s.tracks)
+        .map(|t| &t.track)
+        .filter(|t| t.source == "local" && (t.id == track_id || t.canonical_id == track_id))
+        .collect();
+    found.sort_by_key(|t| t.available == Some(false));
+    found.into_iter().cloned().collect()
+}
+
+fn tokenize(query: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for c in query.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+fn text_field(name: &str) -> Option<TextField> {
+    Some(match name {
+        "title" => TextField::Title,
+        "artist" => TextField::Artist,
+        "album" => TextField::Album,
+        "albumartist" => TextField::AlbumArtist,
+        "genre" => TextField::Genre,
+        "path" => TextField::Path,
+        "format" => TextField::Format,
+        "source" => TextField::Source,
+        _ => return None,
+    })
+}
+
+fn number_field(name: &str) -> Option<NumberField> {
+    Some(match name {
+        "year" => NumberField::Year,
+        "duration" => NumberField::Duration,
+        "track" => NumberField::Track,
+        "disc" => NumberField::Disc,
+        "bpm" => NumberField::Bpm,
+        _ => return None,
+    })
+}
+
+fn parse_number(field: NumberField, value: &str) -> Option<u64> {
+    let value = value.trim();
+    if field == NumberField::Duration {
+        if let Some((minutes, seconds)) = value.split_once(':') {
+            return Some(minutes.parse::<u64>().ok()? * 60 + seconds.parse::<u64>().ok()?);
+        }
+    }
+    value.parse().ok()
+}
+
+fn parse_range(field: NumberField, op: &str, value: &str) -> Option<(Option<u64>, Option<u64>)> {
+    let number = |v: &str| -> Option<Option<u64>> {
+        if v.is_empty() {
+            Some(None)
+        } else {
+            parse_number(field, v).map(Some)
+        }
+    };
+    match op {
+        ":" | "=" => match value.split_once("..") {
+            Some((min, max)) => Some((number(min)?, number(max)?)),
+            None => {
+                let exact = parse_number(field, value)?;
+                Some((Some(exact), Some(exact)))
+            }
+        },
+        ">" => Some((Some(parse_number(field, value)?.saturating_add(1)), None)),
+        ">=" => Some((Some(parse_number(field, value)?), None)),
+        "<" => Some((None, Some(parse_number(field, value)?.checked_sub(1)?))),
+        "<=" => Some((None, Some(parse_number(field, value)?))),
+        _ => None,
+    }
+}
+
+fn parse_token(token: &str) -> Result<Condition, CommandError> {
+    let (negate, body) = match token.strip_prefix('-') {
+        Some(rest) if !rest.is_empty() => (true, rest),
+        _ => (false, token),
+    };
+
+    if let Some(split) = body.find([':', '<', '>', '=']) {
+        let name = body[..split].to_lowercase();
+        let rest = &body[split..];
+        let op_len = if rest.starts_with(">=") || rest.starts_with("<=") { 2 } else { 1 };
+        let (op, value) = rest.split_at(op_len);
+
+        if let Some(field) = text_field(&name) {
+            if op == ":" || op == "=" {
+                return Ok(Condition {
+                    clause: Clause::Text(field, normalize(value)),
+                    negate,
+                });
+            }
+        }
+        if name == "key" && (op == ":" || op == "=") {
+            let (compatible, value) = match value.strip_prefix('~') {
+                Some(rest) => (true, rest),
+                None => (false, value),
+            };
+            let key = MusicalKey::parse(value)
+                .ok_or_else(|| CommandError::invalid_input(format!("Invalid key: {}", value)))?;
+            return Ok(Condition {
+                clause: Clause::Key(key, compatible),
+                negate,
+            });
+        }
+        if let Some(field) = number_field(&name) {
+            let (min, max) = parse_range(field, op, value)
+                .ok_or_else(|| CommandError::invalid_input(format!("Invalid filter: {}", body)))?;
+            return Ok(Condition {
+                clause: Clause::Range(field, min, max),
+                negate,
+            });
+        }
+    }
+
+    Ok(Condition {
+        clause: Clause::Term(normalize(body)),
+        negate,
+    })
+}
+
+fn parse_query(query: &str) -> Result<Vec<Condition>, CommandError> {
+    tokenize(query)
+        .iter()
+        .map(|t| parse_token(t))
+        .filter(|c| !matches!(c, Ok(Condition { clause: Clause::Term(term), .. }) if term.is_empty()))
+        .collect()
+}
+
+fn edit_distance_within(a: &str, b: &str, max: usize) -> bool {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max {
+        return false;
+    }
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != cb);
+            current[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(current[j] + 1);
+        }
+        if current.iter().min().copied().unwrap_or(0) > max {
+            return false;
+        }
+        prev = current;
+    }
+    prev[b.len()] <= max
+}
+
+fn term_score(term: &str, text: &str) -> u32 {
+    if text.is_empty() {
+        return 0;
+    }
+    if text == term {
+        return 6;
+    }
+    let mut words = text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty());
+    if text.starts_with(term) || words.clone().any(|w| w.starts_with(term)) {
+        return 4;
+    }
+    if text.contains(term) {
+        return 3;
+    }
+    let max_edits = match term.chars().count() {
+        0..=3 => return 0,
+        4..=6 => 1,
+        _ => 2,
+    };
+    if words.any(|w| edit_distance_within(term, w, max_edits)) {
+        return 1;
+    }
+    0
+}
+
+fn score_term(term: &str, t: &IndexedTrack) -> u32 {
+    [
+        (term_score(term, &t.title), 3),
+        (term_score(term, &t.artist), 2),
+        (term_score(term, &t.album), 2),
+        (term_score(term, &t.album_artist), 1),
+        (term_score(term, &t.genre), 1),
+        (if t.path.contains(term) { 1 } else { 0 }, 1),
+    ]
+    .into_iter()
+    .map(|(score, weight)| score * weight)
+    .max()
+    .unwrap_or(0)
+}
+
+fn number_value(field: NumberField, track: &Track) -> Option<u64> {
+    match field {
+        NumberField::Year => track.year.map(u64::from),
+        NumberField::Duration => Some(track.duration),
+        NumberField::Track => track.track_number.map(u64::from),
+        NumberField::Disc => track.disc_number.map(u64::from),
+        NumberField::Bpm => track.bpm.map(|bpm| bpm.round() as u64),
+    }
+}
+
+fn matches_clause(clause: &Clause, t: &IndexedTrack, score: &mut u32) -> bool {
+    match clause {
+        Clause::Term(term) => {
+            let term_score = score_term(term, t);
+            *score += term_score;
+            term_score > 0
+        }
+        Clause::Text(field, value) => match field {
+            TextField::Title => t.title.contains(value.as_str()),
+            TextField::Artist => t.artist.contains(value.as_str()),
+            TextField::Album => t.album.contains(value.as_str()),
+            TextField::AlbumArtist => t.album_artist.contains(value.as_str()),
+            TextField::Genre => t.genre.contains(value.as_str()),
+            TextField::Path => t.path.contains(value.as_str()),
+            TextField::Format => t.format == *value,
+            TextField::Source => t.track.source == *value,
+        },
+        Clause::Range(field, min, max) => match number_value(*field, &t.track) {
+            Some(v) => min.is_none_or(|min| v >= min) && max.is_none_or(|max| v <= max),
+            None => false,
+        },
+        Clause::Key(key, compatible) => match t.key {
+            Some(found) if *compatible => key.is_compatible(found),
+            Some(found) => *key == found,
+            None => false,
+        },
+    }
+}
+
+fn compare_field(field: &str, a: &IndexedTrack, b: &IndexedTrack) -> Ordering {
+    let numbers = |x: Option<u64>, y: Option<u64>| match (x, y) {
+        (Some(x), Some(y)) => x.cmp(&y),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    };
+    match field {
+        "title" => a.title.cmp(&b.title),
+        "artist" => a.artist.cmp(&b.artist),
+        "album" => a.album.cmp(&b.album),
+        "albumArtist" => a.album_artist.cmp(&b.album_artist),
+        "genre" => a.genre.cmp(&b.genre),
+        "path" => a.path.cmp(&b.path),
+        "format" => a.format.cmp(&b.format),
+        "source" => a.track.source.cmp(&b.track.source),
+        "year" => numbers(a.track.year.map(u64::from), b.track.year.map(u64::from)),
+        "duration" => a.track.duration.cmp(&b.track.duration),
+        "bpm" => numbers(
+            a.track.bpm.map(|bpm| (bpm * 10.0).round() as u64),
+            b.track.bpm.map(|bpm| (bpm * 10.0).round() as u64),
+        ),
+        "key" => numbers(a.key.map(MusicalKey::order), b.key.map(MusicalKey::order)),
+        "track" => numbers(a.track.disc_number.map(u64::from), b.track.disc_number.map(u64::from)).then(numbers(
+            a.track.track_number.map(u64::from),
+            b.track.track_number.map(u64::from),
+        )),
+        _ => Ordering::Equal,
+    }
+}
+
+const SORT_FIELDS: [&str; 14] = [
+    "relevance",
+    "title",
+    "artist",
+    "album",
+    "albumArtist",
+    "genre",
+    "path",
+    "format",
+    "source",
+    "year",
+    "duration",
+    "track",
+    "bpm",
+    "key",
+];
+
+fn run_query(request: &TrackQuery) -> Result<TrackQueryResult, CommandError> {
+    let conditions = parse_query(&request.query)?;
+    if let Some(unknown) = request.sort.iter().find(|k| !SORT_FIELDS.contains(&k.field.as_str())) {
+        return Err(CommandError::invalid_input(format!("Unknown sort field: {}", unknown.field)));
+    }
+    let has_terms = conditions
+        .iter()
+        .any(|c| !c.negate && matches!(c.clause, Clause::Term(_)));
+
+    let guard = LIBRARY_INDEX.lock().map_err(|_| "library index poisoned".to_string())?;
+    let mut keys: Vec<&String> = guard.keys().collect();
+    keys.sort();
+
+    let mut seen_canonical: HashSet<&str> = HashSet::new();
+    let mut matches: Vec<(&IndexedTrack, u32)> = Vec::new();
+    for t in keys.into_iter().flat_map(|k| guard[k].tracks.iter()) {
+        if let Some(folder_id) = &request.folder_id {
+            if t.track.folder_id.as_ref() != Some(folder_id) {
+                continue;
+            }
+        } else if !seen_canonical.insert(t.track.canonical_id.as_str()) {
+            continue;
+        }
+
+        let mut score = 0;
+        let matched = conditions.iter().all(|c| {
+            let mut ignored = 0;
+            let target = if c.negate { &mut ignored } else { &mut score };
+            matches_clause(&c.clause, t, target) != c.negate
+        });
+        if matched {
+            matches.push((t, score));
+        }
+    }
+
+    let default_sort = [SortKey {
+        field: "relevance".to_string(),
+        descending: false,
+    }];
+    let sort: &[SortKey] = if request.sort.is_empty() && has_terms {
+        &default_sort
+    } else {
+        &request.sort
+    };
+    matches.sort_by(|(a, score_a), (b, score_b)| {
+        sort.iter()
+            .map(|key| {
+                let ordering = if key.field == "relevance" {
+                    score_b.cmp(score_a)
+                } else {
+                    compare_field(&key.field, a, b)
+                };
+                if key.descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            })
+            .find(|o| *o != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    });
+
+    let total = matches.len();
+    let limit = request.limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+    let tracks = matches
+        .into_iter()
+        .skip(request.offset)
+        .take(limit)
+        .map(|(t, _)| t.track.clone())
+        .collect();
+
+    Ok(TrackQueryResult {
+        tracks,
+        total,
+        offset: request.offset,
+    })
+}
+
+#[tauri::command]
+pub async fn query_tracks(request: TrackQuery) -> Result<TrackQueryResult, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || run_query(&request)).await?
+}
@@ -0,0 +1,502 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use super::{playlists, Track};
+use crate::error::CommandError;
+use crate::query::{index_snapshot, normalize};
+use crate::store;
+
+const QUEUE_FILE: &str = "queue.json";
+// Position updates arrive while playing; only every so often are they written to disk.
+const POSITION_SAVE_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RepeatMode {
+    #[default]
+    Off,
+    One,
+    All,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShuffleMode {
+    #[default]
+    Off,
+    Random,
+    Smart,
+    Album,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueEntry {
+    id: u64,
+    track_id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PlayQueue {
+    entries: Vec<QueueEntry>,
+    current: Option<usize>,
+    position: f64,
+    repeat: RepeatMode,
+    shuffle: ShuffleMode,
+    // Entry ids in the order they were added, used to restore the order when shuffle is turned off.
+    unshuffled: Vec<u64>,
+    next_id: u64,
+}
+
+impl PlayQueue {
+    fn entries_for(&mut self, track_ids: Vec<String>) -> Vec<QueueEntry> {
+        track_ids
+            .into_iter()
+            .filter(|id| !id.trim().is_empty())
+            .map(|track_id| {
+                self.next_id += 1;
+                QueueEntry {
+                    id: self.next_id,
+                    track_id,
+                }
+            })
+            .collect()
+    }
+
+    fn current_id(&self) -> Option<u64> {
+        self.current.and_then(|i| self.entries.get(i)).map(|e| e.id)
+    }
+
+    fn insert(&mut self, at: usize, entries: Vec<QueueEntry>) {
+        let at = at.min(self.entries.len());
+        let ids: Vec<u64> = entries.iter().map(|e| e.id).collect();
+        let count = entries.len();
+        let next_to_current = self.current.is_some_and(|c| at == c + 1);
+        let current_id = self.current_id();
+        self.entries.splice(at..at, entries);
+        if let Some(current) = self.current.filter(|c| *c >= at) {
+            self.current = Some(current + count);
+        }
+        if self.shuffle != ShuffleMode::Off {
+            let original_at = current_id
+                .filter(|_| next_to_current)
+                .and_then(|id| self.unshuffled.iter().position(|u| *u == id))
+                .map_or(self.unshuffled.len(), |i| i + 1);
+            self.unshuffled.splice(original_at..original_at, ids);
+        }
+    }
+
+    fn shuffle(&mut self, mode: ShuffleMode) {
+        if self.shuffle == ShuffleMode::Off && mode != ShuffleMode::Off {
+            self.unshuffled = self.entries.iter().map(|e| e.id).collect();
+        }
+        self.shuffle = mode;
+        let current = self.current_id();
+        if mode == ShuffleMode::Off {
+            let rank: HashMap<u64, usize> = self.unshuffled.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+            self.entries
+                .sort_by_key(|e| rank.get(&e.id).copied().unwrap_or(usize::MAX));
+            self.unshuffled.clear();
+        } else {
+            // The playing track stays first; everything else is reordered after it.
+            let mut rest = std::mem::take(&mut self.entries);
+            let head: Vec<QueueEntry> = match current.and_then(|id| rest.iter().position(|e| e.id == id)) {
+                Some(i) => vec![rest.remove(i)],
+                None => Vec::new(),
+            };
+            let tracks = track_lookup();
+            let mut rng = Rng::new();
+            let shuffled = match mode {
+                ShuffleMode::Random => random_order(rest, &mut rng),
+                ShuffleMode::Smart => artist_spread(rest, &tracks, &mut rng),
+                _ => album_order(rest, &tracks, &mut rng),
+            };
+            self.entries = head.into_iter().chain(shuffled).collect();
+        }
+        self.current = current.and_then(|id| self.entries.iter().position(|e| e.id == id));
+    }
+
+    fn advance(&mut self, ended: bool) {
+        self.position = 0.0;
+        if self.entries.is_empty() {
+            self.current = None;
+            return;
+        }
+        if ended && self.repeat == RepeatMode::One && self.current.is_some() {
+            return;
+        }
+        let next = self.current.map_or(0, |c| c + 1);
+        self.current = if next < self.entries.len() {
+            Some(next)
+        } else if self.repeat == RepeatMode::All {
+            Some(0)
+        } else {
+            None
+        };
+    }
+
+    fn back(&mut self) {
+        self.position = 0.0;
+        self.current = match self.current {
+            Some(0) if self.repeat == RepeatMode::All => self.entries.len().checked_sub(1),
+            Some(0) => Some(0),
+            Some(c) => Some(c - 1),
+            None => self.entries.len().checked_sub(1),
+        };
+    }
+}
+
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Rng(nanos)
+    }
+
+    // splitmix64
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+
+    // Uniform in [0, 1).
+    pub(crate) fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub(crate) fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+fn track_lookup() -> HashMap<String, Track> {
+    index_snapshot()
+        .0
+        .into_iter()
+        .map(|t| (t.canonical_id.clone(), t))
+        .collect()
+}
+
+fn random_order(mut entries: Vec<QueueEntry>, rng: &mut Rng) -> Vec<QueueEntry> {
+    rng.shuffle(&mut entries);
+    entries
+}
+
+// Picks artists at random weighted by how many tracks they have left, never the previous
+// artist unless nothing else remains, and forces an artist that would otherwise run out of
+// gaps to spread into.
+fn artist_spread(entries: Vec<QueueEntry>, tracks: &HashMap<String, Track>, rng: &mut Rng) -> Vec<QueueEntry> {
+    let mut groups: Vec<(String, Vec<QueueEntry>)> = Vec::new();
+    for entry in entries {
+        let artist = tracks
+            .get(&entry.track_id)
+            .map(|t| normalize(t.album_artist.as_deref().unwrap_or(&t.artist)))
+            .unwrap_or_default();
+        match groups.iter_mut().find(|(a, _)| *a == artist) {
+            Some((_, group)) => group.push(entry),
+            None => groups.push((artist, vec![entry])),
+        }
+    }
+    for (_, group) in &mut groups {
+        rng.shuffle(group);
+    }
+
+    let mut remaining: usize = groups.iter().map(|(_, g)| g.len()).sum();
+    let mut out = Vec::with_capacity(remaining);
+    let mut last: Option<usize> = None;
+    while remaining > 0 {
+        let candidates: Vec<usize> = (0..groups.len())
+            .filter(|i| !groups[*i].1.is_empty() && Some(*i) != last)
+            .collect();
+        let pick = if candidates.is_empty() {
+            last.unwrap_or(0)
+        } else if let Some(dominant) = candidates.iter().copied().find(|i| groups[*i].1.len() * 2 > remaining) {
+            dominant
+        } else {
+            let total: usize = candidates.iter().map(|i| groups[*i].1.len()).sum();
+            let mut roll = rng.below(total);
+            candidates
+                .iter()
+                .copied()
+                .find(|i| {
+                    let len = groups[*i].1.len();
+                    if roll < len {
+                        true
+                    } else {
+                        roll -= len;
+                        false
+                    }
+                })
+                .unwrap_or(candidates[0])
+        };
+        if let Some(entry) = groups[pick].1.pop() {
+            out.push(entry);
+        }
+        remaining -= 1;
+        last = Some(pick);
+    }
+    out
+}
+
+// Keeps each album together in disc and track order and shuffles the albums.
+fn album_order(entries: Vec<QueueEntry>, tracks: &HashMap<String, Track>, rng: &mut Rng) -> Vec<QueueEntry> {
+    let mut albums: Vec<(String, Vec<QueueEntry>)> = Vec::new();
+    for entry in entries {
+        let key = tracks
+            .get(&entry.track_id)
+            .map(|t| {
+                let artist = t.album_artist.as_deref().unwrap_or(&t.artist);
+                format!("{}|{}", normalize(artist), normalize(&t.album))
+            })
+            .unwrap_or_else(|| entry.track_id.clone());
+        match albums.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, album)) => album.push(entry),
+            None => albums.push((key, vec![entry])),
+        }
+    }
+    for (_, album) in &mut albums {
+        album.sort_by_key(|e| {
+            tracks
+                .get(&e.track_id)
+                .map(|t| (t.disc_number, t.track_number))
+                .unwrap_or_default()
+        });
+    }
+    rng.shuffle(&mut albums);
+    albums.into_iter().flat_map(|(_, album)| album).collect()
+}
+
+static QUEUE: LazyLock<Mutex<PlayQueue>> = LazyLock::new(|| Mutex::new(PlayQueue::default()));
+static LAST_SAVE: LazyLock<Mutex<Option<Instant>>> = LazyLock::new(|| Mutex::new(None));
+
+pub(crate) fn init(app: &tauri::AppHandle) {
+    let mut queue: PlayQueue = match store::load(app, QUEUE_FILE) {
+        Ok(queue) => queue,
+        Err(e) => {
+            log::warn!(target: "queue", "failed to load play queue: {}", e);
+            PlayQueue::default()
+        }
+    };
+    queue.current = queue.current.filter(|c| *c < queue.entries.len());
+    if let Ok(mut state) = QUEUE.lock() {
+        *state = queue;
+    }
+}
+
+fn update<F>(app: &tauri::AppHandle, f: F) -> Result<PlayQueue, CommandError>
+where
+    F: FnOnce(&mut PlayQueue) -> Result<(), CommandError>,
+{
+    let mut queue = QUEUE.lock().map_err(|_| "queue lock poisoned".to_string())?;
+    f(&mut queue)?;
+    save(app, &queue)?;
+    Ok(queue.clone())
+}
+
+fn save(app: &tauri::AppHandle, queue: &PlayQueue) -> Result<(), CommandError> {
+    store::save(app, QUEUE_FILE, queue)?;
+    if let Ok(mut last) = LAST_SAVE.lock() {
+        *last = Some(Instant::now());
+    }
+    Ok(())
+}
+
+fn check_index(queue: &PlayQueue, index: usize) -> Result<(), CommandError> {
+    if index < queue.entries.len() {
+        Ok(())
+    } else {
+        Err(CommandError::invalid_input("Queue index out of range"))
+    }
+}
+
+pub(crate) fn relink_tracks(app: &tauri::AppHandle, moved: &HashMap<String, String>) -> Result<(), CommandError> {
+    let mut queue = QUEUE.lock().map_err(|_| "queue lock poisoned".to_string())?;
+    let mut changed = false;
+    for entry in &mut queue.entries {
+        if let Some(target) = moved.get(&entry.track_id) {
+            entry.track_id = target.clone();
+            changed = true;
+        }
+    }
+    if changed {
+        save(app, &queue)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_queue() -> Result<PlayQueue, CommandError> {
+    Ok(QUEUE.lock().map_err(|_| "queue lock poisoned".to_string())?.clone())
+}
+
+#[tauri::command]
+pub fn queue_set(
+    app: tauri::AppHandle,
+    track_ids: Vec<String>,
+    start: Option<usize>,
+) -> Result<PlayQueue, CommandError> {
+    update(&app, |q| {
+        let entries = q.entries_for(track_ids);
+        q.current = (!entries.is_empty()).then(|| start.unwrap_or(0).min(entries.len() - 1));
+        q.unshuffled.clear();
+        q.entries = entries;
+        q.position = 0.0;
+        let mode = q.shuffle;
+        if mode != ShuffleMode::Off {
+            q.shuffle = ShuffleMode::Off;
+            q.shuffle(mode);
+        }
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn queue_enqueue(app: tauri::AppHandle, track_ids: Vec<String>) -> Result<PlayQueue, CommandError> {
+    update(&app, |q| {
+        let entries = q.entries_for(track_ids);
+        let end = q.entries.len();
+        q.insert(end, entries);
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn queue_insert_next(app: tauri::AppHandle, track_ids: Vec<String>) -> Result<PlayQueue, CommandError> {
+    update(&app, |q| {
+        let entries = q.entries_for(track_ids);
+        let at = q.current.map_or(0, |c| c + 1);
+        q.insert(at, entries);
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn queue_move(app: tauri::AppHandle, from: usize, to: usize) -> Result<PlayQueue, CommandError> {
+    update(&app, |q| {
+        check_index(q, from)?;
+        check_index(q, to)?;
+        let current = q.current_id();
+        let entry = q.entries.remove(from);
+        q.entries.insert(to, entry);
+        q.current = current.and_then(|id| q.entries.iter().position(|e| e.id == id));
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn queue_remove(app: tauri::AppHandle, indices: Vec<usize>) -> Result<PlayQueue, CommandError> {
+    update(&app, |q| {
+        let removed: Vec<u64> = indices.iter().filter_map(|i| q.entries.get(*i)).map(|e| e.id).collect();
+        let current = q.current_id();
+        let removed_before = q
+            .current
+            .map(|c| q.entries[..c].iter().filter(|e| removed.contains(&e.id)).count());
+        q.entries.retain(|e| !removed.contains(&e.id));
+        q.unshuffled.retain(|id| !removed.contains(id));
+        q.current = match (current, q.current, removed_before) {
+            (Some(id), Some(c), Some(before)) if removed.contains(&id) => {
+                // Removing the playing track moves on to whatever followed it.
+                q.position = 0.0;
+                Some(c - before).filter(|c| *c < q.entries.len())
+            }
+            (Some(id), _, _) => q.entries.iter().position(|e| e.id == id),
+            _ => None,
+        };
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn queue_clear(app: tauri::AppHandle) -> Result<PlayQueue, CommandError> {
+    update(&app, |q| {
+        q.entries.clear();
+        q.unshuffled.clear();
+        q.current = None;
+        q.position = 0.0;
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn queue_jump(app: tauri::AppHandle, index: usize) -> Result<PlayQueue, CommandError> {
+    update(&app, |q| {
+        check_index(q, index)?;
+        q.current = Some(index);
+        q.position = 0.0;
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn queue_next(app: tauri::AppHandle, ended: Option<bool>) -> Result<PlayQueue, CommandError> {
+    update(&app, |q| {
+        q.advance(ended.unwrap_or(false));
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn queue_previous(app: tauri::AppHandle) -> Result<PlayQueue, CommandError> {
+    update(&app, |q| {
+        q.back();
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn queue_set_repeat(app: tauri::AppHandle, mode: RepeatMode) -> Result<PlayQueue, CommandError> {
+    update(&app, |q| {
+        q.repeat = mode;
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn queue_set_shuffle(app: tauri::AppHandle, mode: ShuffleMode) -> Result<PlayQueue, CommandError> {
+    update(&app, |q| {
+        q.shuffle(mode);
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn queue_set_position(app: tauri::AppHandle, seconds: f64) -> Result<(), CommandError> {
+    if !seconds.is_finite() || seconds < 0.0 {
+        return Err(CommandError::invalid_input("Invalid playback position"));
+    }
+    let mut queue = QUEUE.lock().map_err(|_| "queue lock poisoned".to_string())?;
+    queue.position = seconds;
+    let last = LAST_SAVE.lock().ok().and_then(|last| *last);
+    if last.is_none_or(|at| at.elapsed() >= POSITION_SAVE_INTERVAL) {
+        save(&app, &queue)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn queue_save_as_playlist(app: tauri::AppHandle, name: String) -> Result<playlists::LocalPlaylist, CommandError> {
+    let track_ids: Vec<String> = {
+        let queue = QUEUE.lock().map_err(|_| "queue lock poisoned".to_string())?;
+        queue.entries.iter().map(|e| e.track_id.clone()).collect()
+    };
+    if track_ids.is_empty() {
+        return Err(CommandError::invalid_input("The queue is empty"));
+    }
+    playlists::create(&app, name, track_ids)
+}
@@ -0,0 +1,331 @@
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+use super::{load_config, navidrome, AppConfig, Track};
+use crate::analysis::{track_key, MusicalKey};
+use crate::error::CommandError;
+use crate::history::{self, PlayOutcome};
+use crate::playlists;
+use crate::query::{index_snapshot, normalize};
+use crate::queue::Rng;
+use crate::resume::navidrome_song;
+
+const DEFAULT_LIMIT: usize = 25;
+const MAX_LIMIT: usize = 200;
+const NAVIDROME_COUNT: usize = 50;
+// Plays further apart than this belong to different listening sessions.
+const SESSION_GAP_SECONDS: u64 = 30 * 60;
+const YEAR_RANGE: f64 = 10.0;
+const BPM_RANGE: f64 = 8.0;
+// Co-occurrence counts above this all score the same.
+const MAX_CO_OCCURRENCE: f64 = 5.0;
+
+const GENRE_WEIGHT: f64 = 3.0;
+const ARTIST_WEIGHT: f64 = 2.0;
+const COMPOSER_WEIGHT: f64 = 1.5;
+const YEAR_WEIGHT: f64 = 1.5;
+const BPM_WEIGHT: f64 = 1.5;
+const KEY_WEIGHT: f64 = 1.0;
+const HISTORY_WEIGHT: f64 = 2.5;
+const PLAYLIST_WEIGHT: f64 = 2.0;
+const SIMILAR_WEIGHT: f64 = 4.0;
+const TOP_SONG_WEIGHT: f64 = 1.5;
+const RATING_WEIGHT: f64 = 0.4;
+const SKIP_WEIGHT: f64 = 1.5;
+// Every candidate keeps a small chance so the radio never runs dry.
+const BASE_WEIGHT: f64 = 0.1;
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum RadioSeed {
+    Track { id: String },
+    Artist { name: String },
+    Album { album: String, artist: Option<String> },
+}
+
+#[derive(Default)]
+struct Profile {
+    seed_ids: HashSet<String>,
+    genres: HashSet<String>,
+    artists: HashSet<String>,
+    composers: HashSet<String>,
+    year: Option<f64>,
+    bpm: Option<f64>,
+    keys: Vec<MusicalKey>,
+}
+
+#[derive(Default)]
+struct RemoteHints {
+    similar: HashSet<String>,
+    top: HashSet<String>,
+}
+
+fn artist_key(track: &Track) -> String {
+    normalize(track.album_artist.as_deref().unwrap_or(&track.artist))
+}
+
+fn genres(track: &Track) -> HashSet<String> {
+    track
+        .genre
+        .as_deref()
+        .unwrap_or_default()
+        .split([';', '/', ',', '\0'])
+        .map(|g| normalize(g.trim()))
+        .filter(|g| !g.is_empty())
+        .collect()
+}
+
+fn average(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let values: Vec<f64> = values.collect();
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+// Tempo distance that treats half and double time as close matches.
+fn bpm_distance(a: f64, b: f64) -> f64 {
+    [(a - b).abs(), (a * 2.0 - b).abs(), (a - b * 2.0).abs()]
+        .into_iter()
+        .fold(f64::INFINITY, f64::min)
+}
+
+fn seed_tracks(seed: &RadioSeed, tracks: &[Track]) -> Vec<Track> {
+    let matches = |t: &&Track| match seed {
+        RadioSeed::Track { id } => t.canonical_id == *id,
+        RadioSeed::Artist { name } => {
+            let name = normalize(name);
+            normalize(&t.artist) == name || t.album_artist.as_deref().map(normalize) == Some(name)
+        }
+        RadioSeed::Album { album, artist } => {
+            normalize(&t.album) == normalize(album) && artist.as_deref().is_none_or(|a| artist_key(t) == normalize(a))
+        }
+    };
+    tracks.iter().filter(matches).cloned().collect()
+}
+
+fn profile(seeds: &[Track]) -> Profile {
+    let mut profile = Profile::default();
+    profile.year = average(seeds.iter().filter_map(|t| t.year).map(f64::from));
+    profile.bpm = average(seeds.iter().filter_map(|t| t.bpm).map(f64::from));
+    for track in seeds {
+        profile.seed_ids.insert(track.canonical_id.clone());
+        profile.genres.extend(genres(track));
+        profile.artists.insert(normalize(&track.artist));
+        profile.artists.insert(artist_key(track));
+        if let Some(composer) = track.composer.as_deref().map(normalize).filter(|c| !c.is_empty()) {
+            profile.composers.insert(composer);
+        }
+        if let Some(key) = track_key(track).filter(|k| !profile.keys.contains(k)) {
+            profile.keys.push(key);
+        }
+    }
+    profile
+}
+
+// Counts how often each track appears in the same listening session as a seed track.
+fn history_co_occurrence(seed_ids: &HashSet<String>) -> HashMap<String, u32> {
+    let mut counts = HashMap::new();
+    let mut session: Vec<String> = Vec::new();
+    let mut last_start: Option<u64> = None;
+    let mut flush = |session: &mut Vec<String>| {
+        if session.iter().any(|id| seed_ids.contains(id)) {
+            let unique: HashSet<String> = session.drain(..).collect();
+            for id in unique {
+                *counts.entry(id).or_insert(0) += 1;
+            }
+        }
+        session.clear();
+    };
+    for play in history::plays() {
+        if last_start.is_some_and(|last| play.started_at > last + SESSION_GAP_SECONDS) {
+            flush(&mut session);
+        }
+        last_start = Some(play.started_at);
+        if play.outcome != PlayOutcome::Skipped {
+            session.push(play.track_id);
+        }
+    }
+    flush(&mut session);
+    counts
+}
+
+fn playlist_co_occurrence(app: &tauri::AppHandle, seed_ids: &HashSet<String>) -> HashMap<String, u32> {
+    let lists = match playlists::track_lists(app) {
+        Ok(lists) => lists,
+        Err(e) => {
+            log::warn!(target: "radio", "failed to load playlists: {}", e);
+            return HashMap::new();
+        }
+    };
+    let mut counts = HashMap::new();
+    for list in lists.into_iter().filter(|l| l.iter().any(|id| seed_ids.contains(id))) {
+        for id in list.into_iter().collect::<HashSet<_>>() {
+            *counts.entry(id).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+// Asks each Navidrome server that holds a seed track for similar and top songs of the seed artist.
+// Failures only cost the hints; the radio still works from the local index.
+async fn remote_hints(config: &AppConfig, seeds: &[Track]) -> RemoteHints {
+    let mut hints = RemoteHints::default();
+    let mut asked = HashSet::new();
+    for track in seeds {
+        let Some(Ok((server, song_id))) = navidrome_song(config, &track.canonical_id) else {
+            continue;
+        };
+        if !asked.insert(server.id.clone()) {
+            continue;
+        }
+        let prefix = format!("navidrome:{}:track:", server.id);
+        let (artist_id, artist) = match navidrome::get_song_artist(server, &song_id).await {
+            Ok(artist) => artist,
+            Err(e) => {
+                log::warn!(target: "radio", "{}: failed to look up seed song: {}", server.name, e);
+                continue;
+            }
+        };
+        if let Some(artist_id) = artist_id {
+            match navidrome::get_similar_songs(server, &artist_id, NAVIDROME_COUNT).await {
+                Ok(ids) => hints
+                    .similar
+                    .extend(ids.into_iter().map(|id| format!("{}{}", prefix, id))),
+                Err(e) => log::warn!(target: "radio", "{}: getSimilarSongs2 failed: {}", server.name, e),
+            }
+        }
+        let artist = artist.unwrap_or_else(|| track.artist.clone());
+        match navidrome::get_top_songs(server, &artist, NAVIDROME_COUNT).await {
+            Ok(ids) => hints.top.extend(ids.into_iter().map(|id| format!("{}{}", prefix, id))),
+            Err(e) => log::warn!(target: "radio", "{}: getTopSongs failed: {}", server.name, e),
+        }
+    }
+    hints
+}
+
+fn co_occurrence_score(count: Option<&u32>) -> f64 {
+    count.map_or(0.0, |c| f64::from(*c).min(MAX_CO_OCCURRENCE) / MAX_CO_OCCURRENCE)
+}
+
+fn score(
+    track: &Track,
+    profile: &Profile,
+    played_with: &HashMap<String, u32>,
+    listed_with: &HashMap<String, u32>,
+    hints: &RemoteHints,
+) -> f64 {
+    let mut score = 0.0;
+
+    let candidate_genres = genres(track);
+    let union = candidate_genres.union(&profile.genres).count();
+    if union > 0 {
+        let shared = candidate_genres.intersection(&profile.genres).count();
+        score += GENRE_WEIGHT * shared as f64 / union as f64;
+    }
+    if profile.artists.contains(&normalize(&track.artist)) || profile.artists.contains(&artist_key(track)) {
+        score += ARTIST_WEIGHT;
+    }
+    if track
+        .composer
+        .as_deref()
+        .is_some_and(|c| profile.composers.contains(&normalize(c)))
+    {
+        score += COMPOSER_WEIGHT;
+    }
+    if let (Some(seed_year), Some(year)) = (profile.year, track.year) {
+        score += YEAR_WEIGHT * (1.0 - (seed_year - f64::from(year)).abs() / YEAR_RANGE).max(0.0);
+    }
+    if let (Some(seed_bpm), Some(bpm)) = (profile.bpm, track.bpm) {
+        score += BPM_WEIGHT * (1.0 - bpm_distance(seed_bpm, f64::from(bpm)) / BPM_RANGE).max(0.0);
+    }
+    if track_key(track).is_some_and(|key| profile.keys.iter().any(|k| k.is_compatible(key))) {
+        score += KEY_WEIGHT;
+    }
+
+    score += HISTORY_WEIGHT * co_occurrence_score(played_with.get(&track.canonical_id));
+    score += PLAYLIST_WEIGHT * co_occurrence_score(listed_with.get(&track.canonical_id));
+    if hints.similar.contains(&track.canonical_id) {
+        score += SIMILAR_WEIGHT;
+    }
+    if hints.top.contains(&track.canonical_id) {
+        score += TOP_SONG_WEIGHT;
+    }
+
+    if let Some(rating) = track.rating {
+        score += RATING_WEIGHT * (f64::from(rating) - 3.0);
+    }
+    let plays = track.play_count + track.skip_count;
+    if plays > 0 {
+        score -= SKIP_WEIGHT * f64::from(track.skip_count) / f64::from(plays);
+    }
+    score.max(0.0)
+}
+
+// Weighted random picks from the best candidates, never the same artist twice in a row
+// unless nothing else is left.
+fn pick(mut candidates: Vec<(f64, Track)>, limit: usize) -> Vec<Track> {
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+    candidates.truncate((limit * 4).max(50));
+
+    let mut rng = Rng::new();
+    let mut picked: Vec<Track> = Vec::with_capacity(limit);
+    while picked.len() < limit && !candidates.is_empty() {
+        let last_artist = picked.last().map(artist_key);
+        let eligible: Vec<usize> = (0..candidates.len())
+            .filter(|i| last_artist.as_ref() != Some(&artist_key(&candidates[*i].1)))
+            .collect();
+        let eligible = if eligible.is_empty() {
+            (0..candidates.len()).collect()
+        } else {
+            eligible
+        };
+        let total: f64 = eligible.iter().map(|i| candidates[*i].0 + BASE_WEIGHT).sum();
+        let mut target = rng.unit() * total;
+        let mut chosen = eligible[eligible.len() - 1];
+        for i in eligible {
+            target -= candidates[i].0 + BASE_WEIGHT;
+            if target < 0.0 {
+                chosen = i;
+                break;
+            }
+        }
+        picked.push(candidates.swap_remove(chosen).1);
+    }
+    picked
+}
+
+// Builds the next stretch of an endless queue; callers pass what they have already played as
+// `exclude` and ask again when it runs out.
+#[tauri::command]
+pub async fn generate_radio(
+    app: tauri::AppHandle,
+    seed: RadioSeed,
+    limit: Option<usize>,
+    exclude: Option<Vec<String>>,
+) -> Result<Vec<Track>, CommandError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let tracks = index_snapshot().0;
+    let seeds = seed_tracks(&seed, &tracks);
+    if seeds.is_empty() {
+        return Err(CommandError::not_found("No tracks match the radio seed"));
+    }
+
+    let config = load_config(app.clone())?;
+    let hints = remote_hints(&config, &seeds).await;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let profile = profile(&seeds);
+        let played_with = history_co_occurrence(&profile.seed_ids);
+        let listed_with = playlist_co_occurrence(&app, &profile.seed_ids);
+        let exclude: HashSet<String> = exclude.unwrap_or_default().into_iter().collect();
+        let single_track = matches!(seed, RadioSeed::Track { .. });
+
+        let candidates: Vec<(f64, Track)> = tracks
+            .into_iter()
+            .filter(|t| t.available != Some(false) && !exclude.contains(&t.canonical_id))
+            .filter(|t| !(single_track && profile.seed_ids.contains(&t.canonical_id)))
+            .map(|t| (score(&t, &profile, &played_with, &listed_with, &hints), t))
+            .collect();
+        log::info!(target: "radio", "{} candidates for {:?}", candidates.len(), seed);
+        Ok(pick(candidates, limit))
+    })
+    .await?
+}
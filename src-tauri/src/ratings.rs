@@ -0,0 +1,290 @@
+use lofty::{ItemKey, ItemValue, Tag, TagExt, TagItem, TagType};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{LazyLock, Mutex};
+
+use super::{load_config, navidrome, path_policy, query, refresh_cached_track, Track};
+use crate::error::CommandError;
+use crate::store;
+use crate::tags::read_tagged_file;
+
+const RATINGS_FILE: &str = "ratings.json";
+const MAX_RATING: f32 = 5.0;
+const POPM_EMAIL: &str = "Windows Media Player 9 Series";
+// POPM bytes for 0.5 to 5 stars in half steps, as written by Windows Media Player and MusicBee.
+const POPM_STEPS: [u8; 10] = [13, 1, 54, 64, 118, 128, 186, 196, 242, 255];
+const FMPS_KEY: &str = "FMPS_RATING";
+const MP4_KEY: &str = "----:com.apple.iTunes:RATING";
+const RATING_KEY: &str = "RATING";
+// Unmapped keys; Vorbis RATING and MP4 `rate` are read through `ItemKey::Popularimeter` as text.
+const TEXT_KEYS: [&str; 3] = [FMPS_KEY, RATING_KEY, MP4_KEY];
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RatingUpdate {
+    track_id: String,
+    rating: Option<f32>,
+    written_to_file: bool,
+}
+
+#[derive(Default)]
+struct Ratings {
+    // A stored 0 records an explicitly cleared rating that should hide a stale tag value.
+    ratings: HashMap<String, f32>,
+    revision: u64,
+}
+
+static RATINGS: LazyLock<Mutex<Ratings>> = LazyLock::new(|| Mutex::new(Ratings::default()));
+
+fn round_half(stars: f32) -> f32 {
+    (stars.clamp(0.0, MAX_RATING) * 2.0).round() / 2.0
+}
+
+fn popm_stars(byte: u8) -> Option<f32> {
+    if byte == 0 {
+        return None;
+    }
+    let (index, _) = POPM_STEPS
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, step)| step.abs_diff(byte))?;
+    Some((index + 1) as f32 / 2.0)
+}
+
+fn popm_byte(stars: f32) -> u8 {
+    let index = ((stars * 2.0).round() as usize).clamp(1, POPM_STEPS.len()) - 1;
+    POPM_STEPS[index]
+}
+
+fn popm(tag: &Tag) -> Option<&[u8]> {
+    match tag.get(&ItemKey::Popularimeter).map(TagItem::value) {
+        Some(ItemValue::Binary(bytes)) => Some(bytes),
+        _ => None,
+    }
+}
+
+fn text_stars(key: &str, value: &str) -> Option<f32> {
+    let n: f32 = value.trim().parse().ok().filter(|n: &f32| n.is_finite())?;
+    let stars = if key.eq_ignore_ascii_case(FMPS_KEY) {
+        n * MAX_RATING
+    } else if n <= MAX_RATING {
+        n
+    } else {
+        n / 20.0
+    };
+    let stars = round_half(stars);
+    (stars > 0.0).then_some(stars)
+}
+
+fn text_item<'a>(tag: &'a Tag, name: &str) -> Option<&'a str> {
+    tag.items().find_map(|item| match item.key() {
+        ItemKey::Unknown(key) if key.eq_ignore_ascii_case(name) => item.value().text(),
+        _ => None,
+    })
+}
+
+fn remove_text(tag: &mut Tag, name: &str) {
+    tag.retain(|item| !matches!(item.key(), ItemKey::Unknown(key) if key.eq_ignore_ascii_case(name)));
+}
+
+// Unknown keys are rejected by `Tag::insert`, so they are inserted unchecked.
+fn set_text(tag: &mut Tag, name: &str, value: String) {
+    remove_text(tag, name);
+    tag.insert_unchecked(TagItem::new(ItemKey::Unknown(name.to_string()), ItemValue::Text(value)));
+}
+
+pub(crate) fn rating_from_tag(tag: &Tag) -> Option<f32> {
+    if let Some(bytes) = popm(tag) {
+        // POPM is email, NUL, rating byte, then an optional play counter.
+        let end = bytes.iter().position(|b| *b == 0)?;
+        return bytes.get(end + 1).copied().and_then(popm_stars);
+    }
+    TEXT_KEYS
+        .iter()
+        .find_map(|name| text_item(tag, name).and_then(|value| text_stars(name, value)))
+        .or_else(|| {
+            tag.get_string(&ItemKey::Popularimeter)
+                .and_then(|value| text_stars(RATING_KEY, value))
+        })
+}
+
+fn apply_rating(tag: &mut Tag, stars: Option<f32>) -> bool {
+    match (tag.tag_type(), stars) {
+        (TagType::Id3v2, None) => {
+            tag.remove_key(&ItemKey::Popularimeter);
+            true
+        }
+        (TagType::Id3v2, Some(stars)) => {
+            let previous = popm(tag).map(<[u8]>::to_vec).unwrap_or_default();
+            let bytes = match previous.iter().position(|b| *b == 0) {
+                Some(end) => {
+                    let mut bytes = previous[..=end].to_vec();
+                    bytes.push(popm_byte(stars));
+                    bytes.extend(previous.iter().skip(end + 2));
+                    bytes
+                }
+                None => [POPM_EMAIL.as_bytes(), &[0, popm_byte(stars)]].concat(),
+            };
+            tag.insert(TagItem::new(ItemKey::Popularimeter, ItemValue::Binary(bytes)))
+        }
+        (TagType::VorbisComments | TagType::Ape | TagType::Mp4Ilst, None) => {
+            for name in TEXT_KEYS {
+                remove_text(tag, name);
+            }
+            tag.remove_key(&ItemKey::Popularimeter);
+            true
+        }
+        (tag_type @ (TagType::VorbisComments | TagType::Ape), Some(stars)) => {
+            // Keep an existing RATING in step, on whichever scale it already uses.
+            let rating = tag
+                .get_string(&ItemKey::Popularimeter)
+                .or_else(|| text_item(tag, RATING_KEY))
+                .and_then(|value| value.trim().parse::<f32>().ok())
+                .map(|old| if old <= MAX_RATING { stars } else { stars * 20.0 });
+            if let Some(rating) = rating {
+                if tag_type == TagType::VorbisComments {
+                    tag.insert_text(ItemKey::Popularimeter, rating.to_string());
+                } else {
+                    set_text(tag, RATING_KEY, rating.to_string());
+                }
+            }
+            set_text(tag, FMPS_KEY, (stars / MAX_RATING).to_string());
+            true
+        }
+        (TagType::Mp4Ilst, Some(stars)) => {
+            let percent = ((stars * 20.0).round() as u32).to_string();
+            set_text(tag, MP4_KEY, percent.clone());
+            tag.insert_text(ItemKey::Popularimeter, percent)
+        }
+        _ => false,
+    }
+}
+
+fn write_file_rating(path: &Path, stars: Option<f32>) -> Result<bool, CommandError> {
+    let mut tagged_file = read_tagged_file(path)?;
+    let tag_type = tagged_file.primary_tag_type();
+    if tagged_file.primary_tag().is_none() {
+        tagged_file.insert_tag(Tag::new(tag_type));
+    }
+    let Some(tag) = tagged_file.primary_tag_mut() else {
+        return Ok(false);
+    };
+    if !apply_rating(tag, stars) {
+        return Ok(false);
+    }
+    tag.save_to_path(path)?;
+    Ok(true)
+}
+
+pub(crate) fn init(app: &tauri::AppHandle) {
+    let ratings: HashMap<String, f32> = match store::load(app, RATINGS_FILE) {
+        Ok(ratings) => ratings,
+        Err(e) => {
+            log::warn!(target: "ratings", "failed to load ratings: {}", e);
+            HashMap::new()
+        }
+    };
+    if let Ok(mut state) = RATINGS.lock() {
+        state.ratings = ratings;
+        state.revision += 1;
+    }
+}
+
+pub(crate) fn revision() -> u64 {
+    RATINGS.lock().map(|r| r.revision).unwrap_or(0)
+}
+
+pub(crate) fn merge_ratings(tracks: &mut [Track]) {
+    let Ok(state) = RATINGS.lock() else {
+        return;
+    };
+    for track in tracks {
+        if let Some(rating) = state.ratings.get(&track.canonical_id) {
+            track.rating = (*rating > 0.0).then_some(*rating);
+        }
+    }
+}
+
+fn remember(app: &tauri::AppHandle, track_id: &str, rating: Option<f32>) -> Result<(), CommandError> {
+    let mut state = RATINGS.lock().map_err(|_| "ratings lock poisoned".to_string())?;
+    state.ratings.insert(track_id.to_string(), rating.unwrap_or(0.0));
+    state.revision += 1;
+    store::save(app, RATINGS_FILE, &state.ratings)
+}
+
+pub(crate) fn relink_tracks(app: &tauri::AppHandle, moved: &HashMap<String, String>) -> Result<(), CommandError> {
+    let mut state = RATINGS.lock().map_err(|_| "ratings lock poisoned".to_string())?;
+    let mut changed = false;
+    for (from, to) in moved {
+        if let Some(rating) = state.ratings.remove(from) {
+            state.ratings.insert(to.clone(), rating);
+            changed = true;
+        }
+    }
+    if changed {
+        state.revision += 1;
+        store::save(app, RATINGS_FILE, &state.ratings)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_rating(
+    app: tauri::AppHandle,
+    track_id: String,
+    rating: Option<f32>,
+) -> Result<RatingUpdate, CommandError> {
+    let rating = match rating {
+        Some(r) if !r.is_finite() || !(0.0..=MAX_RATING).contains(&r) => {
+            return Err(CommandError::invalid_input("Rating must be between 0 and 5 stars"));
+        }
+        Some(r) => Some(round_half(r)).filter(|r| *r > 0.0),
+        None => None,
+    };
+
+    if let Some(rest) = track_id.strip_prefix("navidrome:") {
+        let (server_id, song_id) = rest
+            .split_once(":track:")
+            .ok_or_else(|| CommandError::invalid_input("Invalid Navidrome track id"))?;
+        let config = load_config(app)?;
+        let server = config
+            .navidrome_servers
+            .iter()
+            .find(|s| s.id == server_id)
+            .ok_or_else(|| CommandError::not_found("Navidrome server not found"))?;
+        // Subsonic only stores whole stars; 0 removes the rating.
+        let stars = rating.map_or(0, |r| r.round() as u8);
+        navidrome::set_rating(server, song_id, stars).await?;
+        return Ok(RatingUpdate {
+            track_id,
+            rating: (stars > 0).then_some(f32::from(stars)),
+            written_to_file: false,
+        });
+    }
+
+    tauri::async_runtime::spawn_blocking(move || -> Result<RatingUpdate, CommandError> {
+        let path = path_policy::resolve_track(&app, &track_id)?;
+        let key = query::local_tracks(&track_id)
+            .into_iter()
+            .next()
+            .map_or_else(|| track_id.clone(), |t| t.canonical_id);
+        let written_to_file = match write_file_rating(&path, rating) {
+            Ok(written) => written,
+            Err(e) => {
+                log::warn!(target: "ratings", "failed to write rating to {}: {}", path.display(), e);
+                false
+            }
+        };
+        if written_to_file {
+            refresh_cached_track(&path);
+        }
+        remember(&app, &key, rating)?;
+        Ok(RatingUpdate {
+            track_id,
+            rating,
+            written_to_file,
+        })
+    })
+    .await?
+}
@@ -0,0 +1,227 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{LazyLock, Mutex};
+use std::time::SystemTime;
+
+use super::{load_config, navidrome, query, to_unix_seconds, AppConfig, NavidromeServerConfig};
+use crate::error::CommandError;
+use crate::store;
+
+const RESUME_FILE: &str = "resume.json";
+const MAX_POSITIONS: usize = 1000;
+const MIN_POSITION: f64 = 10.0;
+// Positions this close to the end count as finished.
+const END_MARGIN: f64 = 30.0;
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BookProgress {
+    folder_id: String,
+    track_ids: Vec<String>,
+    duration: u64,
+    position: f64,
+    current_track_id: Option<String>,
+    current_position: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ResumePoint {
+    position: f64,
+    updated_at: u64,
+}
+
+static POSITIONS: LazyLock<Mutex<HashMap<String, ResumePoint>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+pub(crate) fn init(app: &tauri::AppHandle) {
+    let positions: HashMap<String, ResumePoint> = match store::load(app, RESUME_FILE) {
+        Ok(positions) => positions,
+        Err(e) => {
+            log::warn!(target: "resume", "failed to load resume positions: {}", e);
+            HashMap::new()
+        }
+    };
+    if let Ok(mut state) = POSITIONS.lock() {
+        *state = positions;
+    }
+}
+
+pub(crate) fn navidrome_song<'a>(
+    config: &'a AppConfig,
+    track_id: &str,
+) -> Option<Result<(&'a NavidromeServerConfig, String), CommandError>> {
+    let rest = track_id.strip_prefix("navidrome:")?;
+    let Some((server_id, song_id)) = rest.split_once(":track:") else {
+        return Some(Err(CommandError::invalid_input("Invalid Navidrome track id")));
+    };
+    Some(
+        config
+            .navidrome_servers
+            .iter()
+            .find(|s| s.id == server_id)
+            .map(|server| (server, song_id.to_string()))
+            .ok_or_else(|| CommandError::not_found("Navidrome server not found")),
+    )
+}
+
+fn in_audiobook_folder(config: &AppConfig, track_id: &str) -> bool {
+    let mut paths: Vec<String> = query::local_tracks(track_id).into_iter().map(|t| t.audio_url).collect();
+    if paths.is_empty() {
+        paths.push(track_id.to_string());
+    }
+    paths.iter().any(|path| {
+        config
+            .audiobook_folders
+            .iter()
+            .any(|folder| Path::new(path).starts_with(folder))
+    })
+}
+
+fn is_resumable(config: &AppConfig, track_id: &str, duration: u64) -> bool {
+    duration >= u64::from(config.resume_threshold_minutes) * 60 || in_audiobook_folder(config, track_id)
+}
+
+fn store_position(app: &tauri::AppHandle, track_id: &str, position: Option<f64>) -> Result<(), CommandError> {
+    let mut positions = POSITIONS.lock().map_err(|_| "resume lock poisoned".to_string())?;
+    match position {
+        Some(position) => {
+            positions.insert(
+                track_id.to_string(),
+                ResumePoint {
+                    position,
+                    updated_at: to_unix_seconds(SystemTime::now()),
+                },
+            );
+            if positions.len() > MAX_POSITIONS {
+                let mut oldest: Vec<(String, u64)> =
+                    positions.iter().map(|(id, p)| (id.clone(), p.updated_at)).collect();
+                oldest.sort_by_key(|(_, updated_at)| *updated_at);
+                for (id, _) in oldest.into_iter().take(positions.len() - MAX_POSITIONS) {
+                    positions.remove(&id);
+                }
+            }
+        }
+        None => {
+            if positions.remove(track_id).is_none() {
+                return Ok(());
+            }
+        }
+    }
+    store::save(app, RESUME_FILE, &*positions)
+}
+
+pub(crate) fn relink_tracks(app: &tauri::AppHandle, moved: &HashMap<String, String>) -> Result<(), CommandError> {
+    let mut positions = POSITIONS.lock().map_err(|_| "resume lock poisoned".to_string())?;
+    let mut changed = false;
+    for (from, to) in moved {
+        if let Some(point) = positions.remove(from) {
+            positions.insert(to.clone(), point);
+            changed = true;
+        }
+    }
+    if changed {
+        store::save(app, RESUME_FILE, &*positions)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_resume_position(app: tauri::AppHandle, track_id: String) -> Result<Option<f64>, CommandError> {
+    let config = load_config(app)?;
+    if let Some((server, song_id)) = navidrome_song(&config, &track_id).transpose()? {
+        return Ok(navidrome::get_bookmarks(server).await?.get(&song_id).copied());
+    }
+    let positions = POSITIONS.lock().map_err(|_| "resume lock poisoned".to_string())?;
+    Ok(positions.get(&track_id).map(|p| p.position))
+}
+
+#[tauri::command]
+pub async fn set_resume_position(
+    app: tauri::AppHandle,
+    track_id: String,
+    position: f64,
+    duration: u64,
+) -> Result<bool, CommandError> {
+    if track_id.trim().is_empty() || !position.is_finite() || position < 0.0 {
+        return Err(CommandError::invalid_input("Invalid resume position"));
+    }
+    let config = load_config(app.clone())?;
+    let song = navidrome_song(&config, &track_id).transpose()?;
+    if !is_resumable(&config, &track_id, duration) {
+        if song.is_none() {
+            store_position(&app, &track_id, None)?;
+        }
+        return Ok(false);
+    }
+    // Stopping within the first seconds (or before a restored seek lands) keeps the old position.
+    if position < MIN_POSITION {
+        return Ok(false);
+    }
+    let finished = duration > 0 && position >= duration as f64 - END_MARGIN;
+
+    match song {
+        Some((server, song_id)) if finished => navidrome::delete_bookmark(server, &song_id).await?,
+        Some((server, song_id)) => navidrome::create_bookmark(server, &song_id, position).await?,
+        None => store_position(&app, &track_id, (!finished).then_some(position))?,
+    }
+    Ok(!finished)
+}
+
+// Treats the files of one folder as a single book: the current file is the last one with a
+// saved position (or the one after the last played file), and everything before it counts as heard.
+#[tauri::command]
+pub async fn get_book(app: tauri::AppHandle, folder_id: String) -> Result<BookProgress, CommandError> {
+    let mut tracks: Vec<_> = query::index_snapshot()
+        .0
+        .into_iter()
+        .filter(|t| t.folder_id.as_deref() == Some(folder_id.as_str()))
+        .collect();
+    if tracks.is_empty() {
+        return Err(CommandError::not_found("No tracks in this folder"));
+    }
+    tracks.sort_by(|a, b| {
+        (a.disc_number, a.track_number, &a.canonical_id).cmp(&(b.disc_number, b.track_number, &b.canonical_id))
+    });
+
+    let config = load_config(app)?;
+    let positions: HashMap<String, f64> = match navidrome_song(&config, &tracks[0].canonical_id).transpose()? {
+        Some((server, _)) => {
+            let prefix = format!("navidrome:{}:track:", server.id);
+            navidrome::get_bookmarks(server)
+                .await?
+                .into_iter()
+                .map(|(song_id, position)| (format!("{}{}", prefix, song_id), position))
+                .collect()
+        }
+        None => {
+            let positions = POSITIONS.lock().map_err(|_| "resume lock poisoned".to_string())?;
+            positions.iter().map(|(id, p)| (id.clone(), p.position)).collect()
+        }
+    };
+
+    let current = tracks
+        .iter()
+        .rposition(|t| positions.contains_key(&t.canonical_id))
+        .or_else(|| {
+            let next = tracks.iter().rposition(|t| t.play_count > 0).map_or(0, |i| i + 1);
+            (next < tracks.len()).then_some(next)
+        });
+    let current_position = current
+        .and_then(|i| positions.get(&tracks[i].canonical_id))
+        .copied()
+        .unwrap_or(0.0);
+    let heard: u64 = tracks[..current.unwrap_or(tracks.len())]
+        .iter()
+        .map(|t| t.duration)
+        .sum();
+
+    Ok(BookProgress {
+        folder_id,
+        duration: tracks.iter().map(|t| t.duration).sum(),
+        position: heard as f64 + current_position,
+        current_track_id: current.map(|i| tracks[i].canonical_id.clone()),
+        current_position,
+        track_ids: tracks.into_iter().map(|t| t.canonical_id).collect(),
+    })
+}
@@ -0,0 +1,450 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::time::SystemTime;
+
+use super::{history, load_config, ratings, save_config, to_unix_seconds, MusicFolder, ScanResult, Track};
+use crate::analysis::{track_key, MusicalKey};
+use crate::error::CommandError;
+use crate::query::{index_snapshot, normalize};
+
+const ROOT_ID: &str = "smart";
+const MAX_LIMIT: usize = 5000;
+const DAY_SECONDS: u64 = 24 * 60 * 60;
+
+const ORDERS: [&str; 10] = [
+    "random", "title", "artist", "album", "year", "duration", "track", "rating", "bpm", "key",
+];
+
+fn default_match_all() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SmartRule {
+    field: String,
+    op: String,
+    #[serde(default)]
+    value: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SmartPlaylist {
+    id: String,
+    name: String,
+    #[serde(default = "default_match_all")]
+    match_all: bool,
+    #[serde(default)]
+    rules: Vec<SmartRule>,
+    #[serde(default)]
+    limit: Option<usize>,
+    #[serde(default)]
+    order: Option<String>,
+    #[serde(default)]
+    descending: bool,
+}
+
+#[derive(Clone, Copy)]
+enum TextOp {
+    Is,
+    IsNot,
+    Contains,
+    NotContains,
+    StartsWith,
+    EndsWith,
+}
+
+#[derive(Clone, Copy)]
+enum NumberOp {
+    Is,
+    IsNot,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    Between,
+}
+
+enum Matcher {
+    Text(fn(&Track) -> Option<&str>, TextOp, String),
+    Number(fn(&Track) -> Option<f64>, NumberOp, f64, f64),
+    Favorite(bool),
+    LastPlayed { within: bool, days: f64 },
+    Key { key: MusicalKey, op: KeyOp },
+}
+
+#[derive(Clone, Copy)]
+enum KeyOp {
+    Is,
+    IsNot,
+    CompatibleWith,
+}
+
+struct Context {
+    favorites: HashSet<String>,
+    now: u64,
+}
+
+impl Context {
+    fn new(favorites: &[String]) -> Self {
+        Context {
+            favorites: favorites.iter().cloned().collect(),
+            now: to_unix_seconds(SystemTime::now()),
+        }
+    }
+}
+
+fn text_getter(field: &str) -> Option<fn(&Track) -> Option<&str>> {
+    let getter: fn(&Track) -> Option<&str> = match field {
+        "title" => |t| Some(t.title.as_str()),
+        "artist" => |t| Some(t.artist.as_str()),
+        "album" => |t| Some(t.album.as_str()),
+        "albumArtist" => |t| t.album_artist.as_deref(),
+        "genre" => |t| t.genre.as_deref(),
+        "format" => |t| t.format.as_deref(),
+        "source" => |t| Some(t.source.as_str()),
+        _ => return None,
+    };
+    Some(getter)
+}
+
+fn number_getter(field: &str) -> Option<fn(&Track) -> Option<f64>> {
+    let getter: fn(&Track) -> Option<f64> = match field {
+        "year" => |t| t.year.map(f64::from),
+        "duration" => |t| Some(t.duration as f64),
+        "trackNumber" => |t| t.track_number.map(f64::from),
+        "discNumber" => |t| t.disc_number.map(f64::from),
+        "playCount" => |t| Some(f64::from(t.play_count)),
+        "skipCount" => |t| Some(f64::from(t.skip_count)),
+        "rating" => |t| Some(f64::from(t.rating.unwrap_or(0.0))),
+        "bpm" => |t| t.bpm.map(f64::from),
+        _ => return None,
+    };
+    Some(getter)
+}
+
+fn json_number(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+    .filter(|n: &f64| n.is_finite())
+}
+
+fn compile_rule(rule: &SmartRule) -> Result<Matcher, CommandError> {
+    let invalid = || CommandError::invalid_input(format!("Invalid rule: {} {} {}", rule.field, rule.op, rule.value));
+
+    if let Some(getter) = text_getter(&rule.field) {
+        let op = match rule.op.as_str() {
+            "is" => TextOp::Is,
+            "isNot" => TextOp::IsNot,
+            "contains" => TextOp::Contains,
+            "notContains" => TextOp::NotContains,
+            "startsWith" => TextOp::StartsWith,
+            "endsWith" => TextOp::EndsWith,
+            _ => return Err(invalid()),
+        };
+        let value = rule.value.as_str().ok_or_else(invalid)?;
+        return Ok(Matcher::Text(getter, op, normalize(value.trim())));
+    }
+
+    if let Some(getter) = number_getter(&rule.field) {
+        let op = match rule.op.as_str() {
+            "is" => NumberOp::Is,
+            "isNot" => NumberOp::IsNot,
+            "lt" => NumberOp::Lt,
+            "lte" => NumberOp::Lte,
+            "gt" => NumberOp::Gt,
+            "gte" => NumberOp::Gte,
+            "between" => NumberOp::Between,
+            _ => return Err(invalid()),
+        };
+        let (a, b) = match (op, &rule.value) {
+            (NumberOp::Between, serde_json::Value::Array(bounds)) if bounds.len() == 2 => (
+                json_number(&bounds[0]).ok_or_else(invalid)?,
+                json_number(&bounds[1]).ok_or_else(invalid)?,
+            ),
+            (NumberOp::Between, _) => return Err(invalid()),
+            (_, value) => {
+                let n = json_number(value).ok_or_else(invalid)?;
+                (n, n)
+            }
+        };
+        return Ok(Matcher::Number(getter, op, a.min(b), a.max(b)));
+    }
+
+    match (rule.field.as_str(), rule.op.as_str(), &rule.value) {
+        ("favorite", "is", serde_json::Value::Bool(value)) => Ok(Matcher::Favorite(*value)),
+        ("key", op, serde_json::Value::String(value)) => Ok(Matcher::Key {
+            key: MusicalKey::parse(value).ok_or_else(invalid)?,
+            op: match op {
+                "is" => KeyOp::Is,
+                "isNot" => KeyOp::IsNot,
+                "compatibleWith" => KeyOp::CompatibleWith,
+                _ => return Err(invalid()),
+            },
+        }),
+        ("lastPlayed", "inLast" | "notInLast", value) => Ok(Matcher::LastPlayed {
+            within: rule.op == "inLast",
+            days: json_number(value).filter(|d| *d >= 0.0).ok_or_else(invalid)?,
+        }),
+        _ => Err(invalid()),
+    }
+}
+
+fn text_matches(op: TextOp, text: &str, value: &str) -> bool {
+    match op {
+        TextOp::Is => text == value || text.split(['\0', ';']).any(|part| part.trim() == value),
+        TextOp::IsNot => !text_matches(TextOp::Is, text, value),
+        TextOp::Contains => text.contains(value),
+        TextOp::NotContains => !text.contains(value),
+        TextOp::StartsWith => text.starts_with(value),
+        TextOp::EndsWith => text.ends_with(value),
+    }
+}
+
+fn matches(matcher: &Matcher, track: &Track, context: &Context) -> bool {
+    match matcher {
+        Matcher::Text(getter, op, value) => {
+            let text = getter(track).map(normalize).unwrap_or_default();
+            text_matches(*op, &text, value)
+        }
+        Matcher::Number(getter, op, a, b) => match getter(track) {
+            Some(v) => match op {
+                NumberOp::Is => v == *a,
+                NumberOp::IsNot => v != *a,
+                NumberOp::Lt => v < *a,
+                NumberOp::Lte => v <= *a,
+                NumberOp::Gt => v > *a,
+                NumberOp::Gte => v >= *a,
+                NumberOp::Between => v >= *a && v <= *b,
+            },
+            None => matches!(op, NumberOp::IsNot),
+        },
+        Matcher::Favorite(value) => context.favorites.contains(&track.canonical_id) == *value,
+        Matcher::LastPlayed { within, days } => {
+            let cutoff = context.now.saturating_sub((days * DAY_SECONDS as f64) as u64);
+            track.last_played.is_some_and(|played| played >= cutoff) == *within
+        }
+        Matcher::Key { key, op } => {
+            let found = track_key(track);
+            match op {
+                KeyOp::Is => found == Some(*key),
+                KeyOp::IsNot => found != Some(*key),
+                KeyOp::CompatibleWith => found.is_some_and(|k| key.is_compatible(k)),
+            }
+        }
+    }
+}
+
+fn shuffle_key(seed: &str, track: &Track) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    seed.hash(&mut hasher);
+    track.canonical_id.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn compare(order: &str, a: &Track, b: &Track) -> Ordering {
+    let text = |x: &str, y: &str| normalize(x).cmp(&normalize(y));
+    match order {
+        "title" => text(&a.title, &b.title),
+        "artist" => text(&a.artist, &b.artist),
+        "album" => text(&a.album, &b.album),
+        "year" => a.year.cmp(&b.year),
+        "duration" => a.duration.cmp(&b.duration),
+        "track" => (a.disc_number, a.track_number).cmp(&(b.disc_number, b.track_number)),
+        "rating" => a.rating.unwrap_or(0.0).total_cmp(&b.rating.unwrap_or(0.0)),
+        "bpm" => a.bpm.unwrap_or(0.0).total_cmp(&b.bpm.unwrap_or(0.0)),
+        "key" => track_key(a)
+            .map(MusicalKey::order)
+            .cmp(&track_key(b).map(MusicalKey::order)),
+        _ => Ordering::Equal,
+    }
+}
+
+fn validate(playlist: &SmartPlaylist) -> Result<Vec<Matcher>, CommandError> {
+    if playlist.id.trim().is_empty() || playlist.id.contains(':') {
+        return Err(CommandError::invalid_input("Invalid smart playlist id"));
+    }
+    if playlist.name.trim().is_empty() {
+        return Err(CommandError::invalid_input("Smart playlist name cannot be empty"));
+    }
+    if let Some(order) = playlist.order.as_deref() {
+        if !ORDERS.contains(&order) {
+            return Err(CommandError::invalid_input(format!("Unknown order: {}", order)));
+        }
+    }
+    playlist.rules.iter().map(compile_rule).collect()
+}
+
+fn evaluate<'a>(
+    playlist: &SmartPlaylist,
+    matchers: &[Matcher],
+    tracks: &'a [Track],
+    context: &Context,
+    seed: &str,
+) -> Vec<&'a Track> {
+    let mut selected: Vec<&Track> = tracks
+        .iter()
+        .filter(|t| {
+            matchers.is_empty()
+                || if playlist.match_all {
+                    matchers.iter().all(|m| matches(m, t, context))
+                } else {
+                    matchers.iter().any(|m| matches(m, t, context))
+                }
+        })
+        .collect();
+
+    match playlist.order.as_deref() {
+        Some("random") => {
+            let seed = format!("{}|{}", seed, playlist.id);
+            selected.sort_by_cached_key(|t| shuffle_key(&seed, t));
+        }
+        Some(order) => {
+            selected.sort_by(|a, b| {
+                let ordering = compare(order, a, b);
+                if playlist.descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            });
+        }
+        None => {}
+    }
+    selected.truncate(playlist.limit.unwrap_or(MAX_LIMIT).min(MAX_LIMIT));
+    selected
+}
+
+fn build(playlists: &[SmartPlaylist], favorites: &[String]) -> ScanResult {
+    let (mut tracks, index_revision) = index_snapshot();
+    // Index entries carry stats from their last scan, so current plays and ratings are merged in.
+    history::merge_stats(&mut tracks);
+    ratings::merge_ratings(&mut tracks);
+    let context = Context::new(favorites);
+
+    let mut hasher = DefaultHasher::new();
+    index_revision.hash(&mut hasher);
+    history::revision().hash(&mut hasher);
+    ratings::revision().hash(&mut hasher);
+    favorites.hash(&mut hasher);
+    (context.now / DAY_SECONDS).hash(&mut hasher);
+    serde_json::to_string(playlists).unwrap_or_default().hash(&mut hasher);
+    let revision = format!("{:016x}", hasher.finish());
+
+    let mut result = ScanResult {
+        tracks: Vec::new(),
+        folders: Vec::new(),
+        revision,
+    };
+    if playlists.is_empty() {
+        return result;
+    }
+
+    let mut total = 0;
+    for playlist in playlists {
+        let matchers = match validate(playlist) {
+            Ok(matchers) => matchers,
+            Err(e) => {
+                log::warn!(target: "smart", "skipping smart playlist {}: {}", playlist.id, e);
+                continue;
+            }
+        };
+        let folder_id = format!("{}:{}", ROOT_ID, playlist.id);
+        let selected = evaluate(playlist, &matchers, &tracks, &context, &index_revision);
+        for track in &selected {
+            let mut copy = (*track).clone();
+            copy.id = format!("{}:track:{}", folder_id, track.canonical_id);
+            copy.folder_id = Some(folder_id.clone());
+            result.tracks.push(copy);
+        }
+        total += selected.len();
+        result.folders.push(MusicFolder {
+            id: folder_id.clone(),
+            parent_id: Some(ROOT_ID.to_string()),
+            name: playlist.name.clone(),
+            path: folder_id,
+            track_count: selected.len(),
+            source: "smart".to_string(),
+            track_ids: Vec::new(),
+        });
+    }
+
+    result.folders.insert(
+        0,
+        MusicFolder {
+            id: ROOT_ID.to_string(),
+            parent_id: None,
+            name: "Smart Playlists".to_string(),
+            path: ROOT_ID.to_string(),
+            track_count: total,
+            source: "smart".to_string(),
+            track_ids: Vec::new(),
+        },
+    );
+    result
+}
+
+#[tauri::command]
+pub async fn scan_smart_playlists(app: tauri::AppHandle) -> Result<ScanResult, CommandError> {
+    let config = load_config(app)?;
+    let result =
+        tauri::async_runtime::spawn_blocking(move || build(&config.smart_playlists, &config.favorites)).await?;
+    log::debug!(
+        target: "smart",
+        "evaluated {} smart playlist(s), {} tracks",
+        result.folders.len().saturating_sub(1),
+        result.tracks.len()
+    );
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn preview_smart_playlist(
+    app: tauri::AppHandle,
+    playlist: SmartPlaylist,
+) -> Result<Vec<Track>, CommandError> {
+    let config = load_config(app)?;
+    tauri::async_runtime::spawn_blocking(move || -> Result<Vec<Track>, CommandError> {
+        let matchers = validate(&playlist)?;
+        let (tracks, revision) = index_snapshot();
+        let context = Context::new(&config.favorites);
+        Ok(evaluate(&playlist, &matchers, &tracks, &context, &revision)
+            .into_iter()
+            .cloned()
+            .collect())
+    })
+    .await?
+}
+
+#[tauri::command]
+pub fn save_smart_playlist(app: tauri::AppHandle, playlist: SmartPlaylist) -> Result<Vec<SmartPlaylist>, CommandError> {
+    validate(&playlist)?;
+    let mut config = load_config(app.clone())?;
+    match config.smart_playlists.iter_mut().find(|p| p.id == playlist.id) {
+        Some(existing) => *existing = playlist,
+        None => config.smart_playlists.push(playlist),
+    }
+    let playlists = config.smart_playlists.clone();
+    save_config(app, config)?;
+    Ok(playlists)
+}
+
+#[tauri::command]
+pub fn delete_smart_playlist(app: tauri::AppHandle, id: String) -> Result<Vec<SmartPlaylist>, CommandError> {
+    let mut config = load_config(app.clone())?;
+    let before = config.smart_playlists.len();
+    config.smart_playlists.retain(|p| p.id != id);
+    if config.smart_playlists.len() == before {
+        return Err(CommandError::not_found(format!("Smart playlist not found: {}", id)));
+    }
+    let playlists = config.smart_playlists.clone();
+    save_config(app, config)?;
+    Ok(playlists)
+}
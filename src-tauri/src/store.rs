@@ -0,0 +1,122 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use super::to_unix_seconds;
+use crate::error::CommandError;
+
+fn store_path(app: &tauri::AppHandle, name: &str) -> Result<PathBuf, CommandError> {
+    use tauri::Manager;
+    Ok(app.path().resolve(name, tauri::path::BaseDirectory::AppData)?)
+}
+
+pub(crate) fn load<T: DeserializeOwned + Default>(app: &tauri::AppHandle, name: &str) -> Result<T, CommandError> {
+    let path = store_path(app, name)?;
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let json = std::fs::read_to_string(&path)?;
+    match serde_json::from_str(&json) {
+        Ok(value) => Ok(value),
+        Err(e) => {
+            // Moved aside so the next save cannot overwrite the only copy.
+            let mut corrupt = path.clone().into_os_string();
+            corrupt.push(format!(".corrupt-{}", to_unix_seconds(SystemTime::now())));
+            std::fs::rename(&path, &corrupt)?;
+            log::warn!(target: "store", "moved unreadable {} to {:?}: {}", path.display(), corrupt, e);
+            Err(CommandError::parse(format!("{} is unreadable and was moved aside", name)).with_details(e.to_string()))
+        }
+    }
+}
+
+pub(crate) fn save<T: Serialize>(app: &tauri::AppHandle, name: &str, value: &T) -> Result<(), CommandError> {
+    let path = store_path(app, name)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_string(value)?)?;
+    std::fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+pub(crate) fn load_lines<T: DeserializeOwned>(app: &tauri::AppHandle, name: &str) -> Result<Vec<T>, CommandError> {
+    let path = store_path(app, name)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let text = std::fs::read_to_string(&path)?;
+    let mut skipped = 0;
+    let values = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| {
+            let value = serde_json::from_str(line).ok();
+            skipped += usize::from(value.is_none());
+            value
+        })
+        .collect();
+    if skipped > 0 {
+        log::warn!(target: "store", "ignoring {} unreadable lines in {}", skipped, path.display());
+    }
+    Ok(values)
+}
+
+pub(crate) fn append_line<T: Serialize>(app: &tauri::AppHandle, name: &str, value: &T) -> Result<(), CommandError> {
+    use std::io::Write;
+    let path = store_path(app, name)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(file, "{}", serde_json::to_string(value)?)?;
+    Ok(())
+}
+
+pub(crate) fn save_lines<T: Serialize>(app: &tauri::AppHandle, name: &str, values: &[T]) -> Result<(), CommandError> {
+    let path = store_path(app, name)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut text = String::new();
+    for value in values {
+        text.push_str(&serde_json::to_string(value)?);
+        text.push('\n');
+    }
+    let tmp = path.with_extension("jsonl.tmp");
+    std::fs::write(&tmp, text)?;
+    std::fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+// Deletes the least recently written files in `dir` until at most `keep` remain.
+pub(crate) fn prune_dir(app: &tauri::AppHandle, dir: &str, keep: usize) -> Result<usize, CommandError> {
+    let Ok(entries) = std::fs::read_dir(store_path(app, dir)?) else {
+        return Ok(0);
+    };
+    let mut files: Vec<(SystemTime, PathBuf)> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let metadata = e.metadata().ok().filter(|m| m.is_file())?;
+            Some((metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH), e.path()))
+        })
+        .collect();
+    if files.len() <= keep {
+        return Ok(0);
+    }
+    files.sort();
+    let excess = files.len() - keep;
+    for (_, path) in &files[..excess] {
+        std::fs::remove_file(path)?;
+    }
+    Ok(excess)
+}
+
+pub(crate) fn remove(app: &tauri::AppHandle, name: &str) -> Result<(), CommandError> {
+    let path = store_path(app, name)?;
+    match std::fs::remove_file(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,404 @@
+use lofty::{Accessor, ItemKey, Picture, PictureType, Probe, Tag, TagExt, TaggedFile, TaggedFileExt};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::path::Path;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use super::{path_policy, refresh_cached_track, Track};
+use crate::error::CommandError;
+
+const MAX_EMBEDDED_COVER_BYTES: usize = 5 * 1024 * 1024;
+const COVER_DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(20);
+// Older edits can no longer be undone.
+const MAX_TAG_EDITS: usize = 20;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackTags {
+    pub(crate) path: String,
+    pub(crate) title: Option<String>,
+    pub(crate) artist: Option<String>,
+    pub(crate) album_artist: Option<String>,
+    pub(crate) album: Option<String>,
+    pub(crate) track_number: Option<u32>,
+    pub(crate) disc_number: Option<u32>,
+    pub(crate) year: Option<u32>,
+    pub(crate) genre: Option<String>,
+    pub(crate) comment: Option<String>,
+    pub(crate) has_cover: bool,
+}
+
+/// Absent fields are left untouched, `null` clears the field.
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TagPatch {
+    #[serde(default, deserialize_with = "present")]
+    title: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    artist: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    album_artist: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    album: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    track_number: Option<Option<u32>>,
+    #[serde(default, deserialize_with = "present")]
+    disc_number: Option<Option<u32>>,
+    #[serde(default, deserialize_with = "present")]
+    year: Option<Option<u32>>,
+    #[serde(default, deserialize_with = "present")]
+    genre: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    comment: Option<Option<String>>,
+    #[serde(default)]
+    cover: Option<CoverSource>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum CoverSource {
+    File { path: String },
+    Url { url: String },
+    Remove,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TagEditResult {
+    edit_id: String,
+    tracks: Vec<Track>,
+    failed: Vec<TagEditFailure>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TagEditFailure {
+    path: String,
+    error: CommandError,
+}
+
+struct TagSnapshot {
+    tags: TrackTags,
+    front_cover: Option<Picture>,
+}
+
+// Oldest edit first.
+static TAG_EDIT_HISTORY: LazyLock<Mutex<Vec<(String, Vec<TagSnapshot>)>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+pub(crate) fn read_tagged_file(path: &Path) -> Result<TaggedFile, CommandError> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| -> Result<TaggedFile, CommandError> {
+        Ok(Probe::open(path)?.read()?)
+    }))
+    .map_err(|_| CommandError::parse("panic while reading tags"))?
+}
+
+fn tags_from_tag(path: &Path, tag: Option<&Tag>) -> TrackTags {
+    let Some(tag) = tag else {
+        return TrackTags {
+            path: path.to_string_lossy().to_string(),
+            ..Default::default()
+        };
+    };
+    TrackTags {
+        path: path.to_string_lossy().to_string(),
+        title: tag.title().map(|s| s.to_string()),
+        artist: tag.artist().map(|s| s.to_string()),
+        album_artist: tag.get_string(&ItemKey::AlbumArtist).map(|s| s.to_string()),
+        album: tag.album().map(|s| s.to_string()),
+        track_number: tag.track(),
+        disc_number: tag.disk(),
+        year: tag.year(),
+        genre: tag.genre().map(|s| s.to_string()),
+        comment: tag.comment().map(|s| s.to_string()),
+        has_cover: !tag.pictures().is_empty(),
+    }
+}
+
+// Only the front cover is snapshotted, since that is the only picture an edit replaces.
+fn front_cover(tag: &Tag) -> Option<Picture> {
+    tag.pictures()
+        .iter()
+        .find(|p| p.pic_type() == PictureType::CoverFront)
+        .cloned()
+}
+
+pub(crate) fn read_track_tags(path: &Path) -> Result<TrackTags, CommandError> {
+    let tagged_file = read_tagged_file(path)?;
+    Ok(tags_from_tag(path, tagged_file.primary_tag()))
+}
+
+fn set_text(tag: &mut Tag, key: ItemKey, value: &Option<String>) {
+    match value.as_deref().map(str::trim) {
+        Some(v) if !v.is_empty() => {
+            tag.insert_text(key, v.to_string());
+        }
+        _ => {
+            tag.remove_key(&key);
+        }
+    }
+}
+
+fn apply_patch(tag: &mut Tag, patch: &TagPatch) {
+    if let Some(title) = &patch.title {
+        set_text(tag, ItemKey::TrackTitle, title);
+    }
+    if let Some(artist) = &patch.artist {
+        set_text(tag, ItemKey::TrackArtist, artist);
+    }
+    if let Some(album_artist) = &patch.album_artist {
+        set_text(tag, ItemKey::AlbumArtist, album_artist);
+    }
+    if let Some(album) = &patch.album {
+        set_text(tag, ItemKey::AlbumTitle, album);
+    }
+    if let Some(genre) = &patch.genre {
+        set_text(tag, ItemKey::Genre, genre);
+    }
+    if let Some(comment) = &patch.comment {
+        set_text(tag, ItemKey::Comment, comment);
+    }
+    if let Some(track) = patch.track_number {
+        match track {
+            Some(n) => tag.set_track(n),
+            None => tag.remove_track(),
+        }
+    }
+    if let Some(disc) = patch.disc_number {
+        match disc {
+            Some(n) => tag.set_disk(n),
+            None => tag.remove_disk(),
+        }
+    }
+    if let Some(year) = patch.year {
+        match year {
+            Some(n) => tag.set_year(n),
+            None => tag.remove_year(),
+        }
+    }
+}
+
+fn replace_front_cover(tag: &mut Tag, cover: Option<Picture>) {
+    tag.remove_picture_type(PictureType::CoverFront);
+    if let Some(mut picture) = cover {
+        picture.set_pic_type(PictureType::CoverFront);
+        tag.push_picture(picture);
+    }
+}
+
+fn primary_tag_for_write(tagged_file: &mut TaggedFile) -> Result<&mut Tag, CommandError> {
+    if tagged_file.primary_tag().is_none() {
+        let tag_type = tagged_file.primary_tag_type();
+        tagged_file.insert_tag(Tag::new(tag_type));
+    }
+    tagged_file
+        .primary_tag_mut()
+        .ok_or_else(|| CommandError::invalid_input("File does not support tags"))
+}
+
+fn write_one(path: &Path, patch: &TagPatch, cover: Option<&Option<Picture>>) -> Result<TagSnapshot, CommandError> {
+    let mut tagged_file = read_tagged_file(path)?;
+    let snapshot = TagSnapshot {
+        tags: tags_from_tag(path, tagged_file.primary_tag()),
+        front_cover: tagged_file.primary_tag().and_then(front_cover),
+    };
+
+    let tag = primary_tag_for_write(&mut tagged_file)?;
+    apply_patch(tag, patch);
+    if let Some(cover) = cover {
+        replace_front_cover(tag, cover.clone());
+    }
+    tag.save_to_path(path)?;
+    Ok(snapshot)
+}
+
+fn restore_snapshot(snapshot: &TagSnapshot) -> Result<(), CommandError> {
+    let path = Path::new(&snapshot.tags.path);
+    let previous = &snapshot.tags;
+    let patch = TagPatch {
+        title: Some(previous.title.clone()),
+        artist: Some(previous.artist.clone()),
+        album_artist: Some(previous.album_artist.clone()),
+        album: Some(previous.album.clone()),
+        track_number: Some(previous.track_number),
+        disc_number: Some(previous.disc_number),
+        year: Some(previous.year),
+        genre: Some(previous.genre.clone()),
+        comment: Some(previous.comment.clone()),
+        cover: None,
+    };
+    let mut tagged_file = read_tagged_file(path)?;
+    let tag = primary_tag_for_write(&mut tagged_file)?;
+    apply_patch(tag, &patch);
+    replace_front_cover(tag, snapshot.front_cover.clone());
+    Ok(tag.save_to_path(path)?)
+}
+
+fn picture_from_bytes(bytes: Vec<u8>) -> Result<Picture, CommandError> {
+    if bytes.len() > MAX_EMBEDDED_COVER_BYTES {
+        return Err(CommandError::invalid_input("Cover image is larger than 5 MiB"));
+    }
+    let mut reader = std::io::Cursor::new(bytes);
+    let mut picture = Picture::from_reader(&mut reader)?;
+    picture.set_pic_type(PictureType::CoverFront);
+    Ok(picture)
+}
+
+async fn load_cover(source: &CoverSource) -> Result<Option<Picture>, CommandError> {
+    match source {
+        CoverSource::Remove => Ok(None),
+        CoverSource::File { path } => {
+            if std::fs::metadata(path)?.len() > MAX_EMBEDDED_COVER_BYTES as u64 {
+                return Err(CommandError::invalid_input("Cover image is larger than 5 MiB"));
+            }
+            let bytes = std::fs::read(path)?;
+            picture_from_bytes(bytes).map(Some)
+        }
+        CoverSource::Url { url } => {
+            let client = reqwest::Client::builder().timeout(COVER_DOWNLOAD_TIMEOUT).build()?;
+            let mut response = client.get(url).send().await?;
+            if !response.status().is_success() {
+                return Err(CommandError::from_status(response.status()).with_details(url.clone()));
+            }
+            let mut bytes = Vec::new();
+            while let Some(chunk) = response.chunk().await? {
+                if bytes.len() + chunk.len() > MAX_EMBEDDED_COVER_BYTES {
+                    return Err(CommandError::invalid_input("Cover image is larger than 5 MiB"));
+                }
+                bytes.extend_from_slice(&chunk);
+            }
+            picture_from_bytes(bytes).map(Some)
+        }
+    }
+}
+
+fn remember_edit(history: &mut Vec<(String, Vec<TagSnapshot>)>, edit_id: String, snapshots: Vec<TagSnapshot>) {
+    history.push((edit_id, snapshots));
+    if history.len() > MAX_TAG_EDITS {
+        let excess = history.len() - MAX_TAG_EDITS;
+        history.drain(..excess);
+    }
+}
+
+fn write_tags_blocking(
+    app: tauri::AppHandle,
+    paths: Vec<String>,
+    patch: TagPatch,
+    cover: Option<Option<Picture>>,
+) -> Result<TagEditResult, CommandError> {
+    let mut snapshots = Vec::new();
+    let mut tracks = Vec::new();
+    let mut failed = Vec::new();
+
+    for path in paths {
+        let file_path = match path_policy::resolve_existing(&app, &path) {
+            Ok(p) => p,
+            Err(error) => {
+                failed.push(TagEditFailure { path, error });
+                continue;
+            }
+        };
+        match write_one(&file_path, &patch, cover.as_ref()) {
+            Ok(snapshot) => {
+                snapshots.push(snapshot);
+                if let Some(track) = refresh_cached_track(&file_path) {
+                    tracks.push(track);
+                }
+            }
+            Err(error) => failed.push(TagEditFailure { path, error }),
+        }
+    }
+
+    let edit_id = uuid::Uuid::new_v4().to_string();
+    if !snapshots.is_empty() {
+        let mut history = TAG_EDIT_HISTORY
+            .lock()
+            .map_err(|_| "tag history poisoned".to_string())?;
+        remember_edit(&mut history, edit_id.clone(), snapshots);
+    }
+
+    Ok(TagEditResult {
+        edit_id,
+        tracks,
+        failed,
+    })
+}
+
+#[tauri::command]
+pub async fn read_tags(paths: Vec<String>) -> Result<Vec<TrackTags>, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        paths
+            .iter()
+            .map(|p| read_track_tags(Path::new(p)))
+            .collect::<Result<Vec<_>, _>>()
+    })
+    .await?
+}
+
+#[tauri::command]
+pub async fn write_tags(
+    app: tauri::AppHandle,
+    paths: Vec<String>,
+    patch: TagPatch,
+) -> Result<TagEditResult, CommandError> {
+    let cover = match &patch.cover {
+        Some(source) => Some(load_cover(source).await?),
+        None => None,
+    };
+    tauri::async_runtime::spawn_blocking(move || write_tags_blocking(app, paths, patch, cover)).await?
+}
+
+// Files that fail to restore stay under the same edit id so the undo can be retried.
+#[tauri::command]
+pub async fn undo_tag_edit(edit_id: String) -> Result<TagEditResult, CommandError> {
+    let snapshots = {
+        let mut history = TAG_EDIT_HISTORY
+            .lock()
+            .map_err(|_| "tag history poisoned".to_string())?;
+        let index = history
+            .iter()
+            .position(|(id, _)| *id == edit_id)
+            .ok_or_else(|| CommandError::not_found("Tag edit not found"))?;
+        history.remove(index).1
+    };
+    tauri::async_runtime::spawn_blocking(move || -> Result<TagEditResult, CommandError> {
+        let mut tracks = Vec::new();
+        let mut failed = Vec::new();
+        let mut remaining = Vec::new();
+        for snapshot in snapshots {
+            match restore_snapshot(&snapshot) {
+                Ok(()) => {
+                    if let Some(track) = refresh_cached_track(Path::new(&snapshot.tags.path)) {
+                        tracks.push(track);
+                    }
+                }
+                Err(error) => {
+                    failed.push(TagEditFailure {
+                        path: snapshot.tags.path.clone(),
+                        error,
+                    });
+                    remaining.push(snapshot);
+                }
+            }
+        }
+        if !remaining.is_empty() {
+            let mut history = TAG_EDIT_HISTORY
+                .lock()
+                .map_err(|_| "tag history poisoned".to_string())?;
+            remember_edit(&mut history, edit_id.clone(), remaining);
+        }
+        Ok(TagEditResult {
+            edit_id,
+            tracks,
+            failed,
+        })
+    })
+    .await?
+}
@@ -0,0 +1,339 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::sync::{LazyLock, Mutex};
+use std::time::SystemTime;
+use tauri::Emitter;
+
+use super::{load_config, save_config, to_unix_seconds, Track};
+use crate::error::CommandError;
+use crate::query::{index_snapshot, normalize};
+
+const TICK: std::time::Duration = std::time::Duration::from_secs(1);
+const DEFAULT_FADE_SECONDS: u32 = 30;
+const MAX_SLEEP_MINUTES: u32 = 24 * 60;
+// Alarms missed by more than this (e.g. while the machine was asleep) are skipped.
+const ALARM_GRACE_MINUTES: i64 = 5;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SleepMode {
+    Minutes,
+    EndOfTrack,
+    EndOfAlbum,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SleepTimer {
+    mode: SleepMode,
+    fade_seconds: u32,
+    ends_at: Option<u64>,
+    #[serde(skip)]
+    fading: bool,
+    // The last reported track and whether the timer stops after it.
+    #[serde(skip)]
+    last_track: Option<(String, bool)>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "kebab-case")]
+enum SleepAction {
+    Fade,
+    Stop,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SleepTimerEvent {
+    action: SleepAction,
+    fade_seconds: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Alarm {
+    #[serde(default)]
+    id: String,
+    #[serde(default = "default_alarm_enabled")]
+    enabled: bool,
+    // Local time as "HH:MM".
+    time: String,
+    // 0 = Monday … 6 = Sunday; an alarm without days fires once and then disables itself.
+    #[serde(default)]
+    days: Vec<u8>,
+    playlist_id: String,
+    #[serde(default = "default_alarm_volume")]
+    volume: f32,
+    #[serde(default = "default_alarm_ramp")]
+    ramp_seconds: u32,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct AlarmEvent {
+    alarm_id: String,
+    playlist_id: String,
+    volume: f32,
+    ramp_seconds: u32,
+}
+
+fn default_alarm_enabled() -> bool {
+    true
+}
+
+fn default_alarm_volume() -> f32 {
+    0.6
+}
+
+fn default_alarm_ramp() -> u32 {
+    60
+}
+
+static SLEEP_TIMER: LazyLock<Mutex<Option<SleepTimer>>> = LazyLock::new(|| Mutex::new(None));
+
+fn now() -> u64 {
+    to_unix_seconds(SystemTime::now())
+}
+
+fn emit_sleep(app: &tauri::AppHandle, action: SleepAction, fade_seconds: u32) {
+    log::info!(target: "timers", "sleep timer: {:?}", action);
+    let _ = app.emit("sleep-timer", SleepTimerEvent { action, fade_seconds });
+}
+
+fn tick_sleep_timer(app: &tauri::AppHandle) {
+    let Ok(mut timer) = SLEEP_TIMER.lock() else {
+        return;
+    };
+    let Some(state) = timer.as_mut() else {
+        return;
+    };
+    let Some(ends_at) = state.ends_at else {
+        return;
+    };
+    let now = now();
+    if now >= ends_at {
+        *timer = None;
+        emit_sleep(app, SleepAction::Stop, 0);
+    } else if !state.fading && now + u64::from(state.fade_seconds) >= ends_at {
+        state.fading = true;
+        emit_sleep(app, SleepAction::Fade, (ends_at - now) as u32);
+    }
+}
+
+fn album_key(track: &Track) -> String {
+    let artist = track.album_artist.as_deref().unwrap_or(&track.artist);
+    format!("{}|{}", normalize(artist), normalize(&track.album))
+}
+
+fn is_album_end(track_id: &str) -> bool {
+    let tracks = index_snapshot().0;
+    let Some(track) = tracks.iter().find(|t| t.canonical_id == track_id) else {
+        return true;
+    };
+    let key = album_key(track);
+    let order = |t: &Track| (t.disc_number.unwrap_or(1), t.track_number.unwrap_or(0));
+    !tracks
+        .iter()
+        .any(|t| t.canonical_id != track.canonical_id && album_key(t) == key && order(t) > order(track))
+}
+
+// Called for every recorded play; completed plays end the "end of track/album" timers.
+pub(crate) fn track_finished(app: &tauri::AppHandle, track_id: &str, completed: bool) {
+    let Ok(mut timer) = SLEEP_TIMER.lock() else {
+        return;
+    };
+    let done = match timer.as_ref().map(|t| t.mode) {
+        Some(SleepMode::EndOfTrack) => completed,
+        Some(SleepMode::EndOfAlbum) => completed && is_album_end(track_id),
+        _ => false,
+    };
+    if done {
+        *timer = None;
+        emit_sleep(app, SleepAction::Stop, 0);
+    }
+}
+
+fn parse_time(time: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(time.trim(), "%H:%M").ok()
+}
+
+// The most recent scheduled time of an alarm in (since, until], if any.
+fn due_at(alarm: &Alarm, since: DateTime<Local>, until: DateTime<Local>) -> Option<DateTime<Local>> {
+    let time = parse_time(&alarm.time)?;
+    [until.date_naive(), until.date_naive() - Duration::days(1)]
+        .into_iter()
+        .filter(|date| alarm.days.is_empty() || alarm.days.contains(&(date.weekday().num_days_from_monday() as u8)))
+        .filter_map(|date| Local.from_local_datetime(&date.and_time(time)).earliest())
+        .find(|at| *at > since && *at <= until)
+}
+
+fn check_alarms(app: &tauri::AppHandle, since: DateTime<Local>, until: DateTime<Local>) -> Result<(), CommandError> {
+    let mut config = load_config(app.clone())?;
+    let mut changed = false;
+    for alarm in config.alarms.iter_mut().filter(|a| a.enabled) {
+        let Some(at) = due_at(alarm, since, until) else {
+            continue;
+        };
+        if until - at > Duration::minutes(ALARM_GRACE_MINUTES) {
+            log::info!(target: "timers", "skipping alarm {} missed at {}", alarm.id, at);
+            continue;
+        }
+        log::info!(target: "timers", "alarm {} firing", alarm.id);
+        let _ = app.emit(
+            "alarm",
+            AlarmEvent {
+                alarm_id: alarm.id.clone(),
+                playlist_id: alarm.playlist_id.clone(),
+                volume: alarm.volume,
+                ramp_seconds: alarm.ramp_seconds,
+            },
+        );
+        if alarm.days.is_empty() {
+            alarm.enabled = false;
+            changed = true;
+        }
+    }
+    if changed {
+        save_config(app.clone(), config)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn start(app: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        let mut last_check = Local::now();
+        loop {
+            std::thread::sleep(TICK);
+            tick_sleep_timer(&app);
+
+            // Alarms have minute precision, so the config is only read once a minute.
+            let now = Local::now();
+            if now.timestamp() / 60 == last_check.timestamp() / 60 {
+                continue;
+            }
+            if let Err(e) = check_alarms(&app, last_check, now) {
+                log::warn!(target: "timers", "failed to check alarms: {}", e);
+            }
+            last_check = now;
+        }
+    });
+}
+
+#[tauri::command]
+pub fn start_sleep_timer(
+    mode: SleepMode,
+    minutes: Option<u32>,
+    fade_seconds: Option<u32>,
+) -> Result<SleepTimer, CommandError> {
+    let ends_at = match (mode, minutes) {
+        (SleepMode::Minutes, Some(minutes)) if (1..=MAX_SLEEP_MINUTES).contains(&minutes) => {
+            Some(now() + u64::from(minutes) * 60)
+        }
+        (SleepMode::Minutes, _) => {
+            return Err(CommandError::invalid_input("Sleep timer must be 1 to 1440 minutes"));
+        }
+        _ => None,
+    };
+    let timer = SleepTimer {
+        mode,
+        fade_seconds: fade_seconds.unwrap_or(DEFAULT_FADE_SECONDS),
+        ends_at,
+        fading: false,
+        last_track: None,
+    };
+    let mut state = SLEEP_TIMER
+        .lock()
+        .map_err(|_| "sleep timer lock poisoned".to_string())?;
+    *state = Some(timer.clone());
+    Ok(timer)
+}
+
+// The player reports the time left in the playing track (None while paused), so "end of
+// track/album" timers get an end time once the final track plays and fade out like the others.
+#[tauri::command]
+pub fn sleep_timer_progress(track_id: String, remaining: Option<f64>) -> Result<Option<SleepTimer>, CommandError> {
+    let mut timer = SLEEP_TIMER
+        .lock()
+        .map_err(|_| "sleep timer lock poisoned".to_string())?;
+    let Some(state) = timer.as_mut().filter(|t| t.mode != SleepMode::Minutes) else {
+        return Ok(timer.clone());
+    };
+    let is_last = match &state.last_track {
+        Some((id, is_last)) if *id == track_id => *is_last,
+        _ => {
+            let is_last = state.mode == SleepMode::EndOfTrack || is_album_end(&track_id);
+            state.last_track = Some((track_id, is_last));
+            is_last
+        }
+    };
+    // One second of slack so the stop never lands before the track has actually ended.
+    state.ends_at = remaining
+        .filter(|r| is_last && r.is_finite() && *r >= 0.0)
+        .map(|r| now() + r.ceil() as u64 + 1);
+    if state.ends_at.is_none() {
+        state.fading = false;
+    }
+    Ok(timer.clone())
+}
+
+#[tauri::command]
+pub fn cancel_sleep_timer() -> Result<(), CommandError> {
+    let mut state = SLEEP_TIMER
+        .lock()
+        .map_err(|_| "sleep timer lock poisoned".to_string())?;
+    *state = None;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_sleep_timer() -> Result<Option<SleepTimer>, CommandError> {
+    Ok(SLEEP_TIMER
+        .lock()
+        .map_err(|_| "sleep timer lock poisoned".to_string())?
+        .clone())
+}
+
+#[tauri::command]
+pub fn get_alarms(app: tauri::AppHandle) -> Result<Vec<Alarm>, CommandError> {
+    Ok(load_config(app)?.alarms)
+}
+
+#[tauri::command]
+pub fn save_alarm(app: tauri::AppHandle, mut alarm: Alarm) -> Result<Alarm, CommandError> {
+    if parse_time(&alarm.time).is_none() {
+        return Err(CommandError::invalid_input("Alarm time must be HH:MM"));
+    }
+    if alarm.days.iter().any(|d| *d > 6) {
+        return Err(CommandError::invalid_input(
+            "Alarm days must be 0 (Monday) to 6 (Sunday)",
+        ));
+    }
+    if alarm.playlist_id.trim().is_empty() {
+        return Err(CommandError::invalid_input("Alarm needs a playlist"));
+    }
+    alarm.volume = alarm.volume.clamp(0.0, 1.0);
+    alarm.days.sort_unstable();
+    alarm.days.dedup();
+
+    let mut config = load_config(app.clone())?;
+    if alarm.id.is_empty() {
+        alarm.id = uuid::Uuid::new_v4().to_string();
+    }
+    match config.alarms.iter_mut().find(|a| a.id == alarm.id) {
+        Some(existing) => *existing = alarm.clone(),
+        None => config.alarms.push(alarm.clone()),
+    }
+    save_config(app, config)?;
+    Ok(alarm)
+}
+
+#[tauri::command]
+pub fn delete_alarm(app: tauri::AppHandle, id: String) -> Result<(), CommandError> {
+    let mut config = load_config(app.clone())?;
+    let before = config.alarms.len();
+    config.alarms.retain(|a| a.id != id);
+    if config.alarms.len() == before {
+        return Err(CommandError::not_found("Alarm not found"));
+    }
+    save_config(app, config)
+}
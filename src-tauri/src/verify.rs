@@ -0,0 +1,318 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use symphonia::core::errors::Error as DecodeError;
+use tauri::Emitter;
+
+use super::{audio_files, get_file_stamp};
+use crate::decode;
+use crate::error::CommandError;
+use crate::store;
+
+const VERIFY_CACHE_FILE: &str = "verify-cache.json";
+const MAX_ISSUES_PER_FILE: usize = 20;
+
+static VERIFY_RUNNING: AtomicBool = AtomicBool::new(false);
+static VERIFY_CANCELLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum VerifyIssueKind {
+    OpenFailed,
+    // Older caches reported CRC failures separately.
+    #[serde(alias = "crc-mismatch")]
+    DecodeError,
+    SkippedFrames,
+    Truncated,
+    Md5Mismatch,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyIssue {
+    kind: VerifyIssueKind,
+    at_seconds: Option<f64>,
+    message: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FileVerification {
+    path: String,
+    md5_checked: bool,
+    issues: Vec<VerifyIssue>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct CachedVerification {
+    modified: u64,
+    size: u64,
+    result: FileVerification,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct VerifyProgress {
+    done: usize,
+    total: usize,
+    current: String,
+    failed: usize,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyReport {
+    root: String,
+    total: usize,
+    checked: usize,
+    cached: usize,
+    cancelled: bool,
+    failed: Vec<FileVerification>,
+}
+
+fn verify_file(path: &Path) -> FileVerification {
+    let mut issues = Vec::new();
+    let mut md5_checked = false;
+
+    match decode::open(path, true) {
+        Err(e) => issues.push(VerifyIssue {
+            kind: VerifyIssueKind::OpenFailed,
+            at_seconds: None,
+            message: e.to_string(),
+        }),
+        Ok(mut session) => {
+            let mut next_ts: Option<u64> = None;
+            // The end-of-stream and MD5 checks are meaningless once decoding stopped early.
+            let mut stopped_early = false;
+            loop {
+                if issues.len() >= MAX_ISSUES_PER_FILE {
+                    stopped_early = true;
+                    break;
+                }
+                let packet = match session.format.next_packet() {
+                    Ok(packet) => packet,
+                    Err(DecodeError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                    Err(DecodeError::ResetRequired) => {
+                        session.decoder.reset();
+                        continue;
+                    }
+                    Err(e) => {
+                        issues.push(VerifyIssue {
+                            kind: VerifyIssueKind::DecodeError,
+                            at_seconds: next_ts.map(|ts| session.seconds(ts)),
+                            message: e.to_string(),
+                        });
+                        stopped_early = true;
+                        break;
+                    }
+                };
+                if packet.track_id() != session.track_id {
+                    continue;
+                }
+
+                if let Some(expected) = next_ts {
+                    if packet.ts() > expected {
+                        issues.push(VerifyIssue {
+                            kind: VerifyIssueKind::SkippedFrames,
+                            at_seconds: Some(session.seconds(expected)),
+                            message: format!("{} frames could not be read", packet.ts() - expected),
+                        });
+                    }
+                }
+                next_ts = Some(packet.ts() + packet.dur());
+
+                let decoded = session.decoder.decode(&packet).map(|_| ());
+                match decoded {
+                    Ok(()) => {}
+                    Err(DecodeError::DecodeError(message)) => issues.push(VerifyIssue {
+                        kind: VerifyIssueKind::DecodeError,
+                        at_seconds: Some(session.seconds(packet.ts())),
+                        message: message.to_string(),
+                    }),
+                    Err(DecodeError::IoError(e)) => issues.push(VerifyIssue {
+                        kind: VerifyIssueKind::Truncated,
+                        at_seconds: Some(session.seconds(packet.ts())),
+                        message: format!("Frame ends early: {}", e),
+                    }),
+                    Err(e) => {
+                        issues.push(VerifyIssue {
+                            kind: VerifyIssueKind::DecodeError,
+                            at_seconds: Some(session.seconds(packet.ts())),
+                            message: e.to_string(),
+                        });
+                        stopped_early = true;
+                        break;
+                    }
+                }
+            }
+
+            if let (false, Some(total), Some(end)) = (stopped_early, session.n_frames, next_ts) {
+                let tolerance = session.sample_rate.map(|r| u64::from(r) / 2).unwrap_or(4096);
+                if end + tolerance < total {
+                    issues.push(VerifyIssue {
+                        kind: VerifyIssueKind::Truncated,
+                        at_seconds: Some(session.seconds(end)),
+                        message: format!(
+                            "Stream ends at {:.1}s of {:.1}s",
+                            session.seconds(end),
+                            session.seconds(total)
+                        ),
+                    });
+                }
+            }
+
+            if let (false, Some(ok)) = (stopped_early, session.decoder.finalize().verify_ok) {
+                md5_checked = true;
+                if !ok {
+                    issues.push(VerifyIssue {
+                        kind: VerifyIssueKind::Md5Mismatch,
+                        at_seconds: None,
+                        message: "Decoded audio does not match the STREAMINFO MD5".to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    FileVerification {
+        path: path.to_string_lossy().to_string(),
+        md5_checked,
+        issues,
+    }
+}
+
+fn verify_blocking(app: &tauri::AppHandle, path: &str, force: bool) -> Result<VerifyReport, CommandError> {
+    let root = std::fs::canonicalize(path)?;
+    let root_str = root.to_string_lossy().to_string();
+    let files = audio_files(&root);
+    let total = files.len();
+    let mut cache: HashMap<String, CachedVerification> = match store::load(app, VERIFY_CACHE_FILE) {
+        Ok(cache) => cache,
+        Err(e) => {
+            log::warn!(target: "verify", "failed to load verify cache: {}", e);
+            HashMap::new()
+        }
+    };
+
+    log::info!(target: "verify", "verifying {} files under {}", total, root.display());
+
+    let mut seen = HashSet::new();
+    let mut failed = Vec::new();
+    let mut checked = 0;
+    let mut cached = 0;
+    let mut cancelled = false;
+
+    for (idx, file) in files.iter().enumerate() {
+        if VERIFY_CANCELLED.load(Ordering::SeqCst) {
+            cancelled = true;
+            break;
+        }
+        let path_str = file.to_string_lossy().to_string();
+        let _ = app.emit(
+            "verify-progress",
+            VerifyProgress {
+                done: idx,
+                total,
+                current: path_str.clone(),
+                failed: failed.len(),
+            },
+        );
+
+        let Some((modified, size)) = get_file_stamp(file) else {
+            continue;
+        };
+        seen.insert(path_str.clone());
+
+        let reusable = cache
+            .get(&path_str)
+            .filter(|c| !force && c.modified == modified && c.size == size)
+            .map(|c| c.result.clone());
+        let result = match reusable {
+            Some(result) => {
+                cached += 1;
+                result
+            }
+            None => {
+                checked += 1;
+                let result = verify_file(file);
+                if !result.issues.is_empty() {
+                    log::warn!(
+                        target: "verify",
+                        "{}: {} issue(s), first: {}",
+                        path_str,
+                        result.issues.len(),
+                        result.issues[0].message
+                    );
+                }
+                cache.insert(
+                    path_str.clone(),
+                    CachedVerification {
+                        modified,
+                        size,
+                        result: result.clone(),
+                    },
+                );
+                result
+            }
+        };
+        if !result.issues.is_empty() {
+            failed.push(result);
+        }
+    }
+
+    if !cancelled {
+        cache.retain(|p, _| !Path::new(p).starts_with(&root) || seen.contains(p));
+    }
+    store::save(app, VERIFY_CACHE_FILE, &cache)?;
+
+    let _ = app.emit(
+        "verify-progress",
+        VerifyProgress {
+            done: checked + cached,
+            total,
+            current: String::new(),
+            failed: failed.len(),
+        },
+    );
+    log::info!(
+        target: "verify",
+        "verification of {} {}: {} checked, {} cached, {} with issues",
+        root.display(),
+        if cancelled { "cancelled" } else { "finished" },
+        checked,
+        cached,
+        failed.len()
+    );
+
+    Ok(VerifyReport {
+        root: root_str,
+        total,
+        checked,
+        cached,
+        cancelled,
+        failed,
+    })
+}
+
+#[tauri::command]
+pub async fn verify_library(app: tauri::AppHandle, path: String, force: Option<bool>) -> Result<VerifyReport, CommandError> {
+    if VERIFY_RUNNING.swap(true, Ordering::SeqCst) {
+        return Err(CommandError::invalid_input("A verification job is already running"));
+    }
+    VERIFY_CANCELLED.store(false, Ordering::SeqCst);
+    let result =
+        tauri::async_runtime::spawn_blocking(move || verify_blocking(&app, &path, force.unwrap_or(false))).await;
+    VERIFY_RUNNING.store(false, Ordering::SeqCst);
+    result?
+}
+
+#[tauri::command]
+pub fn cancel_verify() -> bool {
+    let running = VERIFY_RUNNING.load(Ordering::SeqCst);
+    if running {
+        VERIFY_CANCELLED.store(true, Ordering::SeqCst);
+    }
+    running
+}
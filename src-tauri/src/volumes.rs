@@ -0,0 +1,350 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+use super::{load_config, lock_config, query, write_config, ScanResult};
+use crate::error::CommandError;
+use crate::store;
+
+#[cfg(target_os = "linux")]
+const NETWORK_FILESYSTEMS: [&str; 6] = ["cifs", "smb3", "smbfs", "nfs", "nfs4", "fuse.sshfs"];
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderVolume {
+    path: String,
+    volume_id: Option<String>,
+    mount_point: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderStatus {
+    path: String,
+    available: bool,
+    volume_id: Option<String>,
+    mount_point: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct VolumeSnapshot {
+    result: ScanResult,
+    // The first `indexed` tracks are the real files; the rest are virtual and playlist copies.
+    indexed: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Mount {
+    mount_point: PathBuf,
+    volume_id: String,
+}
+
+static SAVED_REVISIONS: LazyLock<Mutex<HashMap<String, String>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+static OFFLINE_FOLDERS: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+static OFFLINE_RESULTS: LazyLock<Mutex<HashMap<String, ScanResult>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+static MOVED_FOLDERS: LazyLock<Mutex<HashMap<String, String>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+#[cfg(target_os = "linux")]
+fn unescape_mount(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let octal = bytes
+            .get(i + 1..i + 4)
+            .filter(|d| bytes[i] == b'\\' && d.iter().all(|b| (b'0'..=b'7').contains(b)));
+        match octal {
+            Some(d) => {
+                out.push((d[0] - b'0') * 64 + (d[1] - b'0') * 8 + (d[2] - b'0'));
+                i += 4;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(target_os = "linux")]
+fn device_uuids() -> HashMap<PathBuf, String> {
+    let Ok(entries) = std::fs::read_dir("/dev/disk/by-uuid") else {
+        return HashMap::new();
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let device = std::fs::canonicalize(e.path()).ok()?;
+            Some((device, e.file_name().to_string_lossy().to_string()))
+        })
+        .collect()
+}
+
+// mountinfo lines look like:
+// 36 35 98:0 / /media/usb rw,noatime shared:1 - ext4 /dev/sdb1 rw
+#[cfg(target_os = "linux")]
+fn parse_mountinfo_line(line: &str, uuids: &HashMap<PathBuf, String>) -> Option<Mount> {
+    let (mount, filesystem) = line.split_once(" - ")?;
+    let mount_point = PathBuf::from(unescape_mount(mount.split(' ').nth(4)?));
+    let mut filesystem = filesystem.split(' ');
+    let fstype = filesystem.next()?;
+    let source = unescape_mount(filesystem.next()?);
+
+    let device = std::fs::canonicalize(&source).unwrap_or_else(|_| PathBuf::from(&source));
+    let volume_id = if let Some(uuid) = uuids.get(&device) {
+        format!("uuid:{}", uuid)
+    } else if NETWORK_FILESYSTEMS.contains(&fstype) {
+        format!("net:{}", source)
+    } else {
+        format!("dev:{}", source)
+    };
+    Some(Mount { mount_point, volume_id })
+}
+
+#[cfg(target_os = "linux")]
+const MOUNTINFO: &str = "/proc/self/mountinfo";
+
+#[cfg(target_os = "linux")]
+fn read_mounts() -> Vec<Mount> {
+    let Ok(text) = std::fs::read_to_string(MOUNTINFO) else {
+        return Vec::new();
+    };
+    let uuids = device_uuids();
+    text.lines()
+        .filter_map(|line| parse_mountinfo_line(line, &uuids))
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_mounts() -> Vec<Mount> {
+    Vec::new()
+}
+
+fn locate<'a>(path: &Path, mounts: &'a [Mount]) -> Option<&'a Mount> {
+    mounts
+        .iter()
+        .filter(|m| path.starts_with(&m.mount_point))
+        .max_by_key(|m| m.mount_point.components().count())
+}
+
+fn snapshot_name(root_key: &str) -> String {
+    let digest = Sha256::digest(root_key.as_bytes());
+    format!("volumes/{}.json", URL_SAFE_NO_PAD.encode(&digest[..12]))
+}
+
+pub(crate) fn forget_root(app: &tauri::AppHandle, root_key: &str) {
+    query::remove_from_index(root_key);
+    if let Ok(mut offline) = OFFLINE_RESULTS.lock() {
+        offline.remove(root_key);
+    }
+    if let Ok(mut saved) = SAVED_REVISIONS.lock() {
+        saved.remove(root_key);
+    }
+    if let Err(e) = store::remove(app, &snapshot_name(root_key)) {
+        log::warn!(target: "volumes", "failed to remove snapshot for {}: {}", root_key, e);
+    }
+}
+
+// Rewrites music folders that reconcile followed to a new mount point, so a config
+// loaded before the move does not bring the old path back when it is saved.
+pub(crate) fn follow_moves(folders: &mut [String]) {
+    let Ok(moved) = MOVED_FOLDERS.lock() else {
+        return;
+    };
+    for folder in folders.iter_mut() {
+        if let Some(path) = moved.get(folder.as_str()) {
+            *folder = path.clone();
+        }
+    }
+}
+
+pub(crate) fn reconcile(app: &tauri::AppHandle) -> Result<Vec<FolderStatus>, CommandError> {
+    let _guard = lock_config();
+    let mut config = load_config(app.clone())?;
+    let mounts = read_mounts();
+    let mut changed = false;
+    let mut offline = HashSet::new();
+
+    for folder in config.music_folders.iter_mut() {
+        let index = match config.folder_volumes.iter().position(|v| v.path == *folder) {
+            Some(index) => index,
+            None => {
+                config.folder_volumes.push(FolderVolume {
+                    path: folder.clone(),
+                    ..Default::default()
+                });
+                config.folder_volumes.len() - 1
+            }
+        };
+        let record = &mut config.folder_volumes[index];
+
+        let path = Path::new(folder.as_str());
+        let mount = locate(path, &mounts);
+        // An empty directory where a known UUID or network volume used to be is just the
+        // leftover mount point of an unplugged drive.
+        let stable = record.volume_id.as_deref().is_some_and(|id| !id.starts_with("dev:"));
+        let unmounted = stable
+            && mount.map(|m| &m.volume_id) != record.volume_id.as_ref()
+            && std::fs::read_dir(path).is_ok_and(|mut entries| entries.next().is_none());
+        if path.exists() && !unmounted {
+            let current = FolderVolume {
+                path: folder.clone(),
+                volume_id: mount.map(|m| m.volume_id.clone()),
+                mount_point: mount.map(|m| m.mount_point.to_string_lossy().to_string()),
+            };
+            changed |= *record != current;
+            *record = current;
+            continue;
+        }
+        offline.insert(folder.to_lowercase());
+
+        // The folder is offline; look for its volume mounted somewhere else. Device names
+        // are not stable across plug-ins, so only UUID and network volumes can be followed.
+        let (Some(volume_id), Some(old_mount)) = (&record.volume_id, &record.mount_point) else {
+            continue;
+        };
+        if volume_id.starts_with("dev:") {
+            continue;
+        }
+        let Some(mount) = mounts.iter().find(|m| &m.volume_id == volume_id) else {
+            continue;
+        };
+        let Ok(relative) = Path::new(folder.as_str()).strip_prefix(old_mount) else {
+            continue;
+        };
+        let Ok(moved) = std::fs::canonicalize(mount.mount_point.join(relative)) else {
+            continue;
+        };
+        let moved = moved.to_string_lossy().to_string();
+        log::info!(target: "volumes", "{} is now mounted at {}", folder, moved);
+        forget_root(app, &folder.to_lowercase());
+        *record = FolderVolume {
+            path: moved.clone(),
+            volume_id: Some(mount.volume_id.clone()),
+            mount_point: Some(mount.mount_point.to_string_lossy().to_string()),
+        };
+        offline.remove(&folder.to_lowercase());
+        if let Ok(mut moves) = MOVED_FOLDERS.lock() {
+            for path in moves.values_mut().filter(|path| **path == *folder) {
+                *path = moved.clone();
+            }
+            moves.insert(folder.clone(), moved.clone());
+        }
+        *folder = moved;
+        changed = true;
+    }
+
+    let folders = config.music_folders.clone();
+    let before = config.folder_volumes.len();
+    config.folder_volumes.retain(|v| folders.contains(&v.path));
+    changed |= config.folder_volumes.len() != before;
+
+    let statuses = config
+        .folder_volumes
+        .iter()
+        .map(|v| FolderStatus {
+            path: v.path.clone(),
+            available: !offline.contains(&v.path.to_lowercase()),
+            volume_id: v.volume_id.clone(),
+            mount_point: v.mount_point.clone(),
+        })
+        .collect();
+    if let Ok(mut folders) = OFFLINE_FOLDERS.lock() {
+        *folders = offline;
+    }
+    if changed {
+        write_config(app, &config)?;
+    }
+    Ok(statuses)
+}
+
+pub(crate) fn is_offline(root_key: &str) -> bool {
+    OFFLINE_FOLDERS.lock().is_ok_and(|folders| folders.contains(root_key))
+}
+
+pub(crate) fn save_snapshot(app: &tauri::AppHandle, root_key: &str, result: &ScanResult, indexed: usize) {
+    if let Ok(mut offline) = OFFLINE_RESULTS.lock() {
+        offline.remove(root_key);
+    }
+    let Ok(mut saved) = SAVED_REVISIONS.lock() else {
+        return;
+    };
+    if saved.get(root_key) == Some(&result.revision) {
+        return;
+    }
+    let snapshot = VolumeSnapshot {
+        result: result.clone(),
+        indexed,
+    };
+    match store::save(app, &snapshot_name(root_key), &snapshot) {
+        Ok(()) => {
+            saved.insert(root_key.to_string(), result.revision.clone());
+        }
+        Err(e) => log::warn!(target: "volumes", "failed to save snapshot for {}: {}", root_key, e),
+    }
+}
+
+pub(crate) fn offline_result(app: &tauri::AppHandle, root_key: &str) -> Result<ScanResult, CommandError> {
+    let mut offline = OFFLINE_RESULTS
+        .lock()
+        .map_err(|_| "offline cache poisoned".to_string())?;
+    if let Some(result) = offline.get(root_key) {
+        return Ok(result.clone());
+    }
+
+    let snapshot: VolumeSnapshot = store::load(app, &snapshot_name(root_key))?;
+    let mut result = snapshot.result;
+    for track in &mut result.tracks {
+        track.available = Some(false);
+    }
+    result.revision = format!("offline:{}", result.revision);
+    let indexed = snapshot.indexed.min(result.tracks.len());
+    query::update_index(root_key, &result.revision, &result.tracks[..indexed]);
+    log::info!(target: "volumes", "{} is offline, keeping {} tracks", root_key, indexed);
+    offline.insert(root_key.to_string(), result.clone());
+    Ok(result)
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn watch(app: tauri::AppHandle) {
+    use tauri::Emitter;
+    const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+    std::thread::spawn(move || {
+        let mut last: Option<String> = None;
+        loop {
+            let current = std::fs::read_to_string(MOUNTINFO).unwrap_or_default();
+            if last.as_ref() != Some(&current) {
+                if last.is_some() {
+                    log::debug!(target: "volumes", "mount table changed");
+                }
+                last = Some(current);
+                match reconcile(&app) {
+                    Ok(statuses) => {
+                        let _ = app.emit("volumes-changed", statuses);
+                    }
+                    Err(e) => log::warn!(target: "volumes", "failed to reconcile music folders: {}", e),
+                }
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn watch(app: tauri::AppHandle) {
+    if let Err(e) = reconcile(&app) {
+        log::warn!(target: "volumes", "failed to reconcile music folders: {}", e);
+    }
+}
+
+#[tauri::command]
+pub fn music_folder_status(app: tauri::AppHandle) -> Result<Vec<FolderStatus>, CommandError> {
+    reconcile(&app)
+}
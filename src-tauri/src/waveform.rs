@@ -0,0 +1,225 @@
+use serde::{Deserialize, Serialize};
+use std::sync::{LazyLock, Mutex};
+
+use super::{get_file_stamp, load_config, navidrome, path_policy};
+use crate::decode::{self, DecodeSession};
+use crate::error::CommandError;
+use crate::resume::navidrome_song;
+use crate::store;
+
+const WAVEFORM_DIR: &str = "waveforms";
+// Bump when the peak computation changes so old cache files are rebuilt.
+const WAVEFORM_VERSION: u32 = 1;
+// Peaks are first collected per 10 ms window and then reduced to each stored level.
+const WINDOWS_PER_SECOND: u32 = 100;
+const LEVELS: [usize; 3] = [256, 1024, 4096];
+const MAX_STREAM_BYTES: u64 = 256 * 1024 * 1024;
+const MEMORY_CACHE_SIZE: usize = 16;
+// Each cached waveform is about 40 KiB on disk.
+const DISK_CACHE_SIZE: usize = 1000;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct PeakLevel {
+    // Quantized to -127..=127.
+    min: Vec<i8>,
+    max: Vec<i8>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct CachedWaveform {
+    version: u32,
+    // File stamp of local tracks; zero for Navidrome tracks, which are keyed by id alone.
+    modified: u64,
+    size: u64,
+    duration: f64,
+    levels: Vec<PeakLevel>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Waveform {
+    track_id: String,
+    duration: f64,
+    min: Vec<f32>,
+    max: Vec<f32>,
+}
+
+// Most recently used last.
+static RECENT: LazyLock<Mutex<Vec<(String, CachedWaveform)>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+fn cache_name(track_id: &str) -> String {
+    format!("{}/{:x}.json", WAVEFORM_DIR, md5::compute(track_id))
+}
+
+fn remember(track_id: &str, waveform: &CachedWaveform) {
+    if let Ok(mut recent) = RECENT.lock() {
+        recent.retain(|(id, _)| id != track_id);
+        recent.push((track_id.to_string(), waveform.clone()));
+        if recent.len() > MEMORY_CACHE_SIZE {
+            recent.remove(0);
+        }
+    }
+}
+
+fn load_cached(app: &tauri::AppHandle, track_id: &str, stamp: (u64, u64)) -> Option<CachedWaveform> {
+    let valid = |w: &CachedWaveform| w.version == WAVEFORM_VERSION && (w.modified, w.size) == stamp;
+    if let Some((_, waveform)) = RECENT.lock().ok()?.iter().find(|(id, w)| id == track_id && valid(w)) {
+        return Some(waveform.clone());
+    }
+    let waveform: CachedWaveform = match store::load(app, &cache_name(track_id)) {
+        Ok(waveform) => waveform,
+        Err(e) => {
+            log::warn!(target: "waveform", "failed to read cached waveform for {}: {}", track_id, e);
+            return None;
+        }
+    };
+    valid(&waveform).then(|| {
+        remember(track_id, &waveform);
+        waveform
+    })
+}
+
+// Min/max of each 10 ms window of the mono signal, plus the duration in seconds.
+fn window_peaks(session: &mut DecodeSession) -> Result<(Vec<(f32, f32)>, f64), CommandError> {
+    let mut windows = Vec::new();
+    let mut current = (0.0f32, 0.0f32);
+    let (mut count, mut frames, mut rate) = (0u32, 0u64, 0u32);
+    decode::for_each_mono(session, |sample, sample_rate| {
+        rate = sample_rate;
+        current = (current.0.min(sample), current.1.max(sample));
+        count += 1;
+        frames += 1;
+        if count >= (sample_rate / WINDOWS_PER_SECOND).max(1) {
+            windows.push(current);
+            current = (0.0, 0.0);
+            count = 0;
+        }
+        true
+    })?;
+    if count > 0 {
+        windows.push(current);
+    }
+    let duration = if rate > 0 { frames as f64 / f64::from(rate) } else { 0.0 };
+    Ok((windows, duration))
+}
+
+fn reduce(peaks: &[(f32, f32)], buckets: usize) -> Vec<(f32, f32)> {
+    if peaks.is_empty() {
+        return vec![(0.0, 0.0); buckets];
+    }
+    (0..buckets)
+        .map(|i| {
+            let start = i * peaks.len() / buckets;
+            let end = ((i + 1) * peaks.len() / buckets).clamp(start + 1, peaks.len());
+            peaks[start..end]
+                .iter()
+                .fold((0.0f32, 0.0f32), |(lo, hi), (min, max)| (lo.min(*min), hi.max(*max)))
+        })
+        .collect()
+}
+
+fn quantize(value: f32) -> i8 {
+    (value.clamp(-1.0, 1.0) * 127.0).round() as i8
+}
+
+fn build(
+    mut session: DecodeSession,
+    app: &tauri::AppHandle,
+    track_id: &str,
+    stamp: (u64, u64),
+) -> Result<CachedWaveform, CommandError> {
+    let (windows, duration) = window_peaks(&mut session)?;
+    let levels = LEVELS
+        .iter()
+        .map(|buckets| {
+            let (min, max) = reduce(&windows, *buckets)
+                .into_iter()
+                .map(|(min, max)| (quantize(min), quantize(max)))
+                .unzip();
+            PeakLevel { min, max }
+        })
+        .collect();
+    let waveform = CachedWaveform {
+        version: WAVEFORM_VERSION,
+        modified: stamp.0,
+        size: stamp.1,
+        duration,
+        levels,
+    };
+    log::debug!(target: "waveform", "computed waveform for {} ({:.1}s)", track_id, duration);
+    if let Err(e) = store::save(app, &cache_name(track_id), &waveform) {
+        log::warn!(target: "waveform", "failed to cache waveform for {}: {}", track_id, e);
+    }
+    match store::prune_dir(app, WAVEFORM_DIR, DISK_CACHE_SIZE) {
+        Ok(0) => {}
+        Ok(removed) => log::debug!(target: "waveform", "pruned {} cached waveforms", removed),
+        Err(e) => log::warn!(target: "waveform", "failed to prune waveform cache: {}", e),
+    }
+    remember(track_id, &waveform);
+    Ok(waveform)
+}
+
+// Returns `buckets` min/max pairs in -1..1 covering the whole track. Peaks are computed once per
+// file (or Navidrome song) and served from the finest cached level that has enough detail.
+#[tauri::command]
+pub async fn get_waveform(app: tauri::AppHandle, track_id: String, buckets: usize) -> Result<Waveform, CommandError> {
+    let max_buckets = LEVELS[LEVELS.len() - 1];
+    if buckets == 0 || buckets > max_buckets {
+        return Err(CommandError::invalid_input(format!(
+            "Waveform needs 1 to {} buckets",
+            max_buckets
+        )));
+    }
+
+    let config = load_config(app.clone())?;
+    let waveform = match navidrome_song(&config, &track_id).transpose()? {
+        Some((server, song_id)) => match load_cached(&app, &track_id, (0, 0)) {
+            Some(waveform) => waveform,
+            None => {
+                // The stream is untranscoded, so the song's suffix tells the decoder its container.
+                let suffix = navidrome::get_song_suffix(server, &song_id).await.unwrap_or_else(|e| {
+                    log::debug!(target: "waveform", "no suffix for {}: {}", track_id, e);
+                    None
+                });
+                let bytes = navidrome::fetch_stream(server, &song_id, MAX_STREAM_BYTES).await?;
+                let (app, track_id) = (app.clone(), track_id.clone());
+                tauri::async_runtime::spawn_blocking(move || {
+                    build(decode::open_bytes(bytes, suffix.as_deref())?, &app, &track_id, (0, 0))
+                })
+                .await??
+            }
+        },
+        None => {
+            let path = path_policy::resolve_track(&app, &track_id)?;
+            let (app, track_id) = (app.clone(), track_id.clone());
+            tauri::async_runtime::spawn_blocking(move || {
+                let stamp = get_file_stamp(&path).ok_or_else(|| CommandError::not_found("Track file not found"))?;
+                match load_cached(&app, &track_id, stamp) {
+                    Some(waveform) => Ok(waveform),
+                    None => build(decode::open(&path, false)?, &app, &track_id, stamp),
+                }
+            })
+            .await??
+        }
+    };
+
+    let level = LEVELS.iter().position(|l| *l >= buckets).unwrap_or(LEVELS.len() - 1);
+    let Some(peaks) = waveform.levels.get(level) else {
+        return Err(CommandError::internal("Cached waveform is incomplete"));
+    };
+    let peaks: Vec<(f32, f32)> = peaks
+        .min
+        .iter()
+        .zip(&peaks.max)
+        .map(|(min, max)| (f32::from(*min) / 127.0, f32::from(*max) / 127.0))
+        .collect();
+    let (min, max) = reduce(&peaks, buckets).into_iter().unzip();
+    Ok(Waveform {
+        track_id,
+        duration: waveform.duration,
+        min,
+        max,
+    })
+}
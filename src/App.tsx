@@ -3,15 +3,16 @@ import { Sidebar } from "@/components/sidebar";
 import { Header } from "@/components/header";
 import { TrackList } from "@/components/track-list";
 import { NowPlaying } from "@/components/now-playing";
-import { QueueView } from "@/components/queue-view";
+import { QueueView, type QueueItem } from "@/components/queue-view";
 import { ArtistView } from "@/components/artist-view";
 import { PlayerControls } from "@/components/player-controls";
 import { SettingsPanel } from "@/components/settings-panel";
-import type { Track, PlayerState, MusicFolder, AppConfig } from "@/lib/types";
+import type { Track, PlayerState, MusicFolder, AppConfig, Chapter, RadioSeed, SleepMode, SleepTimer, SleepTimerEvent, AlarmEvent, PlayQueue } from "@/lib/types";
 import { applyThemeMap } from "@/lib/theme"
-import { getDisplayTitle } from "@/lib/utils"
+import { errorMessage, getDisplayTitle } from "@/lib/utils"
 import { open } from "@tauri-apps/plugin-dialog";
 import { convertFileSrc, invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 
 interface ScanResult {
   tracks: Track[];
@@ -19,11 +20,27 @@ interface ScanResult {
   revision: string;
 }
 
+// Queue entries hold canonical ids; several tracks (virtual and playlist copies) can share one.
+function canonicalTrackMap(tracks: Track[]) {
+  const byCanonicalId = new Map<string, Track>()
+  tracks.forEach((t) => {
+    const existing = byCanonicalId.get(t.canonicalId)
+    if (!existing || (existing.available === false && t.available !== false)) byCanonicalId.set(t.canonicalId, t)
+  })
+  return byCanonicalId
+}
+
 function App() {
   const [view, setView] = useState<"library" | "nowplaying" | "queue" | "artist" | "favorites" | "recent">("library");
   const [sidebarCollapsed, setSidebarCollapsed] = useState(true);
   const [settingsOpen, setSettingsOpen] = useState(false);
   const [currentTrack, setCurrentTrack] = useState<Track | null>(null);
+  const [chapters, setChapters] = useState<Chapter[]>([]);
+  const [sleepTimer, setSleepTimer] = useState<SleepTimer | null>(null);
+  const sleepTimerRef = useRef<SleepTimer | null>(null)
+  const volumeRampRef = useRef<number | null>(null)
+  const sleepVolumeRef = useRef<number | null>(null)
+  const handleAlarmRef = useRef<(alarm: AlarmEvent) => void>(() => {})
   const [selectedFolder, setSelectedFolder] = useState<MusicFolder | null>(null);
   const [selectedArtist, setSelectedArtist] = useState<string | null>(null)
   const lastNonArtistViewRef = useRef<"library" | "nowplaying" | "queue" | "favorites" | "recent">("library")
@@ -73,16 +90,17 @@ function App() {
   const tracksRef = useRef<Track[]>([])
   const currentTrackRef = useRef<Track | null>(null)
   const playbackHistoryRef = useRef<string[]>([])
-  const playTrackRef = useRef<((track: Track, useCrossfade: boolean) => void) | null>(null)
+  const playStartRef = useRef<{ trackId: string; startedAt: number; duration: number } | null>(null)
+  const queueEndedRef = useRef<((current: Track) => void) | null>(null)
+  const radioRequestRef = useRef(0)
 
   const trackByIdRef = useRef<Map<string, Track>>(new Map())
-  const playbackListIdsRef = useRef<string[]>([])
-  const shuffleOrderIdsRef = useRef<string[] | null>(null)
-  const shuffleNonceRef = useRef(0)
-  const [manualQueueIds, setManualQueueIds] = useState<string[]>([])
-  const manualQueueIdsRef = useRef<string[]>([])
-  const [queueRemovedIds, setQueueRemovedIds] = useState<string[]>([])
-  const queueRemovedSetRef = useRef<Set<string>>(new Set())
+  const trackByCanonicalIdRef = useRef<Map<string, Track>>(new Map())
+  const [queue, setQueue] = useState<PlayQueue | null>(null)
+  const queueRef = useRef<PlayQueue | null>(null)
+  // Entries added with "Add to queue"; they are listed apart from the playing context.
+  const manualEntryIdsRef = useRef<Set<number>>(new Set())
+  const restoredPositionRef = useRef<{ audioUrl: string; position: number } | null>(null)
   const [contextLabel, setContextLabel] = useState("Library")
 
   useEffect(() => {
@@ -92,16 +110,9 @@ function App() {
   useEffect(() => {
     tracksRef.current = tracks
     trackByIdRef.current = new Map(tracks.map((t) => [t.id, t]))
+    trackByCanonicalIdRef.current = canonicalTrackMap(tracks)
   }, [tracks])
 
-  useEffect(() => {
-    queueRemovedSetRef.current = new Set(queueRemovedIds)
-  }, [queueRemovedIds])
-
-  useEffect(() => {
-    manualQueueIdsRef.current = manualQueueIds
-  }, [manualQueueIds])
-
   useEffect(() => {
     currentTrackRef.current = currentTrack
   }, [currentTrack])
@@ -141,7 +152,7 @@ function App() {
         coverUrl,
       })
         .then(() => setDiscordRpcError(null))
-        .catch((e) => setDiscordRpcError(typeof e === "string" ? e : "Discord Rich Presence failed"))
+        .catch((e) => setDiscordRpcError(errorMessage(e, "Discord Rich Presence failed")))
     }
 
     send()
@@ -160,6 +171,7 @@ function App() {
   const coverCacheRef = useRef<Map<string, string>>(new Map())
   const coverInFlightRef = useRef<Set<string>>(new Set())
   const refreshInFlightRef = useRef(false)
+  const refreshPendingRef = useRef(false)
   const libraryRevisionRef = useRef<string | null>(null)
   const navidromeCacheRef = useRef<{ tracks: Track[]; folders: MusicFolder[]; revisions: string[]; serverKey: string } | null>(null)
   const navidromeLastRefreshRef = useRef(0)
@@ -297,7 +309,7 @@ function App() {
         const config = await invoke<AppConfig>("load_config");
         
         setFavorites(new Set(config.favorites || []));
-        setRecentTracks(config.recentTracks || []);
+        setRecentTracks(await invoke<string[]>("get_recent_tracks", { limit: 50 }));
         
         if (config.eqEnabled !== undefined) setEqEnabled(config.eqEnabled);
         if (config.eqPreset) setEqPreset(config.eqPreset);
@@ -331,6 +343,12 @@ function App() {
         const localResults = prunedPaths.length > 0
           ? await Promise.all(prunedPaths.map((path) => invoke<ScanResult>("scan_music_library", { path })))
           : []
+        if (localResults.length > 0) {
+          // Scanning relinks moved files, which can rewrite favorites and recents.
+          const scannedConfig = await invoke<AppConfig>("load_config")
+          setFavorites(new Set(scannedConfig.favorites || []))
+          setRecentTracks(await invoke<string[]>("get_recent_tracks", { limit: 50 }))
+        }
 
         const enabledServers = (config.navidromeServers || []).filter((s) => s.enabled)
         const serverKey = enabledServers.map((s) => s.id).sort().join("|")
@@ -346,7 +364,9 @@ function App() {
         }
         navidromeLastRefreshRef.current = Date.now()
 
-        const results = [...localResults, ...navidromeResults]
+        const playlistResult = await invoke<ScanResult>("scan_playlists")
+        const smartResult = await invoke<ScanResult>("scan_smart_playlists")
+        const results = [...localResults, ...navidromeResults, playlistResult, smartResult]
         if (results.length > 0) {
           const tracksById = new Map<string, Track>();
           const foldersByPath = new Map<string, MusicFolder>();
@@ -365,6 +385,18 @@ function App() {
             setTracks(nextTracks)
             setFolders(nextFolders)
           })
+
+          // Pick up where the last session stopped, paused at the saved position.
+          const savedQueue = await invoke<PlayQueue>("get_queue")
+          applyQueue(savedQueue)
+          const entry = savedQueue.current === null ? undefined : savedQueue.entries[savedQueue.current]
+          const restored = entry ? canonicalTrackMap(nextTracks).get(entry.trackId) : undefined
+          if (restored && !currentTrackRef.current) {
+            restoredPositionRef.current = { audioUrl: restored.audioUrl, position: savedQueue.position }
+            currentTrackRef.current = restored
+            setCurrentTrack(restored)
+            setPlayerState((prev) => ({ ...prev, currentTime: savedQueue.position, duration: restored.duration }))
+          }
         }
       } catch (error) {
         console.error("Failed to load config:", error);
@@ -377,8 +409,12 @@ function App() {
   useEffect(() => {
     const refreshLibrary = async () => {
       if (document.hidden) return
-      if (refreshInFlightRef.current) return
+      if (refreshInFlightRef.current) {
+        refreshPendingRef.current = true
+        return
+      }
       refreshInFlightRef.current = true
+      refreshPendingRef.current = false
       try {
         try {
           const themeName = selectedThemeRef.current || "default"
@@ -431,10 +467,23 @@ function App() {
         const cachedNav = navidromeCacheRef.current
         const navidromeResultsForRevision = cachedNav ? cachedNav.revisions : []
 
-        const combinedRevision = [...localResults.map((r) => r.revision), ...navidromeResultsForRevision].join("|")
+        const playlistResult = await invoke<ScanResult>("scan_playlists")
+        const smartResult = await invoke<ScanResult>("scan_smart_playlists")
+
+        const combinedRevision = [
+          ...localResults.map((r) => r.revision),
+          ...navidromeResultsForRevision,
+          playlistResult.revision,
+          smartResult.revision,
+        ].join("|")
         if (libraryRevisionRef.current === combinedRevision) return
         libraryRevisionRef.current = combinedRevision
 
+        // Rescans relink moved files, which can rewrite favorites and recents.
+        const latestConfig = await invoke<AppConfig>("load_config")
+        setFavorites(new Set(latestConfig.favorites || []))
+        setRecentTracks(await invoke<string[]>("get_recent_tracks", { limit: 50 }))
+
         const tracksById = new Map<string, Track>();
         const foldersByPath = new Map<string, MusicFolder>();
 
@@ -446,6 +495,10 @@ function App() {
           cachedNav.tracks.forEach((t) => tracksById.set(t.id, t))
           cachedNav.folders.forEach((f) => foldersByPath.set(f.path, f))
         }
+        playlistResult.tracks.forEach((t) => tracksById.set(t.id, t))
+        playlistResult.folders.forEach((f) => foldersByPath.set(f.path, f))
+        smartResult.tracks.forEach((t) => tracksById.set(t.id, t))
+        smartResult.folders.forEach((f) => foldersByPath.set(f.path, f))
 
         const nextTracks = applyCachedCovers(Array.from(tracksById.values()))
         const nextFolders = Array.from(foldersByPath.values())
@@ -457,11 +510,17 @@ function App() {
         console.error("Failed to refresh library:", error);
       } finally {
         refreshInFlightRef.current = false
+        if (refreshPendingRef.current) void refreshLibrary()
       }
     };
 
     const interval = setInterval(refreshLibrary, 5000);
-    return () => clearInterval(interval);
+    // A drive coming back or moving to a new mount point changes which folders can be scanned.
+    const unlistenVolumes = listen("volumes-changed", () => void refreshLibrary())
+    return () => {
+      clearInterval(interval)
+      void unlistenVolumes.then((fn) => fn())
+    };
   }, []);
 
   const handleSaveEqSettings = useCallback((enabled: boolean, preset: string, values: number[], cf: number, norm: boolean) => {
@@ -522,7 +581,7 @@ function App() {
     } else {
       invoke("discord_rpc_connect")
         .then(() => setDiscordRpcError(null))
-        .catch((e) => setDiscordRpcError(typeof e === "string" ? e : "Failed to connect to Discord"))
+        .catch((e) => setDiscordRpcError(errorMessage(e, "Failed to connect to Discord")))
     }
     invoke("load_config").then((c: any) => {
       invoke("save_config", { config: { ...c, discordRichPresence: enabled } })
@@ -552,7 +611,7 @@ function App() {
         setDiscordRpcError(null)
         setDiscordRpcTestSuccessAt(Date.now())
       })
-      .catch((e) => setDiscordRpcError(typeof e === "string" ? e : "Discord Rich Presence test failed"))
+      .catch((e) => setDiscordRpcError(errorMessage(e, "Discord Rich Presence test failed")))
   }, [])
 
 
@@ -596,6 +655,14 @@ function App() {
 
         audioPathRef.current = track.audioUrl
         audio.load()
+
+        const resumeAt = await invoke<number | null>("get_resume_position", { trackId: track.canonicalId }).catch(() => null)
+        const restored = restoredPositionRef.current
+        restoredPositionRef.current = null
+        const startAt = resumeAt || (restored?.audioUrl === track.audioUrl ? restored.position : null)
+        if (startAt && audioPathRef.current === track.audioUrl) {
+          audio.currentTime = startAt
+        }
       }
 
       audio.muted = false
@@ -651,25 +718,47 @@ function App() {
     if (playerState.isPlaying) {
       audio.pause();
       setPlayerState((prev) => ({ ...prev, isPlaying: false }));
+      saveResumePosition(currentTrack.canonicalId, audio.currentTime, currentTrack.duration);
+      invoke("queue_set_position", { seconds: audio.currentTime }).catch(() => {})
       return;
     }
 
+    if (!playStartRef.current) beginPlay(currentTrack)
     await startPlayback(currentTrack);
     setPlayerState((prev) => ({ ...prev, isPlaying: !audio.paused }));
   };
 
   const addToRecent = useCallback((trackId: string) => {
-    setRecentTracks(prev => {
-        const next = [trackId, ...prev.filter(id => id !== trackId)].slice(0, 50);
-        
-        invoke("load_config").then((c: any) => {
-            invoke("save_config", { config: { ...c, recentTracks: next } });
-        });
-        
-        return next;
-    });
+    setRecentTracks(prev => [trackId, ...prev.filter(id => id !== trackId)].slice(0, 50));
+  }, []);
+
+  const beginPlay = useCallback((track: Track) => {
+    playStartRef.current = { trackId: track.canonicalId, startedAt: Math.floor(Date.now() / 1000), duration: track.duration }
+  }, []);
+
+  const saveResumePosition = useCallback((trackId: string, position: number, duration: number) => {
+    invoke("set_resume_position", { trackId, position, duration: Math.round(duration) }).catch(() => {})
   }, []);
 
+  const finishPlay = useCallback((position: number) => {
+    const play = playStartRef.current
+    if (!play) return
+    playStartRef.current = null
+    const fraction = play.duration > 0 ? Math.min(1, position / play.duration) : 0
+    invoke("record_play", { trackId: play.trackId, startedAt: play.startedAt, fraction }).catch(() => {})
+    saveResumePosition(play.trackId, Math.min(position, play.duration), play.duration)
+  }, [saveResumePosition]);
+
+  useEffect(() => {
+    if (!playerState.isPlaying) return
+    const timer = window.setInterval(() => {
+      const play = playStartRef.current
+      if (play) saveResumePosition(play.trackId, audioRef.current.currentTime, play.duration)
+      invoke("queue_set_position", { seconds: audioRef.current.currentTime }).catch(() => {})
+    }, 30000)
+    return () => clearInterval(timer)
+  }, [playerState.isPlaying, saveResumePosition]);
+
   const playTrack = useCallback((track: Track, useCrossfade: boolean) => {
     if (crossfadeTimerRef.current !== null) {
       clearInterval(crossfadeTimerRef.current)
@@ -677,6 +766,7 @@ function App() {
     }
 
     const playNext = () => {
+        finishPlay(audioRef.current ? audioRef.current.currentTime : 0)
         const prev = currentTrackRef.current
         if (prev && prev.id !== track.id) {
           playbackHistoryRef.current = [...playbackHistoryRef.current, prev.id].slice(-100)
@@ -691,6 +781,7 @@ function App() {
           duration: track.duration,
         }));
         addToRecent(track.canonicalId);
+        beginPlay(track);
         void startPlayback(track);
     };
 
@@ -726,11 +817,7 @@ function App() {
         else if (audioRef.current) audioRef.current.volume = playerState.volume;
         playNext();
     }
-  }, [addToRecent, crossfade, playerState.isPlaying, playerState.volume, playerState.isMuted, ensureCoverForAudioUrl]);
-
-  useEffect(() => {
-    playTrackRef.current = playTrack
-  }, [playTrack])
+  }, [addToRecent, beginPlay, finishPlay, crossfade, playerState.isPlaying, playerState.volume, playerState.isMuted, ensureCoverForAudioUrl]);
 
   useEffect(() => {
     if (!currentTrack) return
@@ -740,219 +827,129 @@ function App() {
     if (match && match !== currentTrack) setCurrentTrack(match)
   }, [tracks, currentTrack])
 
-  const hashString = (input: string) => {
-    let h = 2166136261
-    for (let i = 0; i < input.length; i++) {
-      h ^= input.charCodeAt(i)
-      h = Math.imul(h, 16777619)
-    }
-    return h >>> 0
-  }
-
-  const mulberry32 = (seed: number) => {
-    let a = seed >>> 0
-    return () => {
-      a = (a + 0x6d2b79f5) >>> 0
-      let t = Math.imul(a ^ (a >>> 15), 1 | a)
-      t ^= t + Math.imul(t ^ (t >>> 7), 61 | t)
-      return ((t ^ (t >>> 14)) >>> 0) / 4294967296
-    }
-  }
-
-  const arraysEqual = (a: string[], b: string[]) => {
-    if (a === b) return true
-    if (a.length !== b.length) return false
-    for (let i = 0; i < a.length; i++) {
-      if (a[i] !== b[i]) return false
-    }
-    return true
-  }
-
-  const setPlaybackContext = useCallback((contextTracks: Track[], nextLabel: string) => {
-    const nextIds = contextTracks.map((t) => t.id)
-    const changed = !arraysEqual(nextIds, playbackListIdsRef.current)
-    playbackListIdsRef.current = nextIds
-    if (changed) {
-      setQueueRemovedIds([])
-      setContextLabel(nextLabel)
-    }
-  }, [])
-
-  const handleTrackSelect = useCallback((track: Track, contextTracks: Track[], nextLabel: string) => {
-    setPlaybackContext(contextTracks, nextLabel)
-    if (playerStateRef.current.isShuffled) {
-      shuffleNonceRef.current += 1
-      shuffleOrderIdsRef.current = null
-    }
-    playTrack(track, false);
-  }, [playTrack, setPlaybackContext]);
-
-  const ensureShuffleOrder = useCallback(() => {
-    if (!playerStateRef.current.isShuffled) {
-      shuffleOrderIdsRef.current = null
-      return
-    }
-    if (shuffleOrderIdsRef.current && shuffleOrderIdsRef.current.length > 0) return
-
-    const listIds = playbackListIdsRef.current.length > 0
-      ? playbackListIdsRef.current
-      : tracksRef.current.map((t) => t.id)
-    const current = currentTrackRef.current
-    const currentId = current?.id
-    const currentIndex = currentId ? listIds.indexOf(currentId) : -1
-    const headId = currentIndex >= 0 ? listIds[currentIndex] : listIds[0]
-
-    const remaining = listIds.filter((id) => id !== headId)
-    const seed = hashString(`${shuffleNonceRef.current}|${headId}|${listIds.length}`)
-    const rnd = mulberry32(seed)
-    for (let i = remaining.length - 1; i > 0; i--) {
-      const j = Math.floor(rnd() * (i + 1))
-      const tmp = remaining[i]
-      remaining[i] = remaining[j]
-      remaining[j] = tmp
-    }
-    shuffleOrderIdsRef.current = [headId, ...remaining]
-  }, [])
-
-  const getSequenceIds = useCallback(() => {
-    if (playerStateRef.current.isShuffled) {
-      ensureShuffleOrder()
-      return shuffleOrderIdsRef.current || []
-    }
-    return playbackListIdsRef.current.length > 0 ? playbackListIdsRef.current : tracksRef.current.map((t) => t.id)
-  }, [ensureShuffleOrder])
-
   const findTrackById = useCallback((id: string) => {
-    return trackByIdRef.current.get(id) || tracksRef.current.find((t) => t.id === id) || null
+    return (
+      trackByCanonicalIdRef.current.get(id) ||
+      trackByIdRef.current.get(id) ||
+      tracksRef.current.find((t) => t.id === id) ||
+      null
+    )
   }, [])
 
-  const getQueueIds = useCallback(() => {
-    const current = currentTrackRef.current
-    if (!current) return []
-
-    const sequence = getSequenceIds()
-    if (sequence.length === 0) return []
-
-    const removed = queueRemovedSetRef.current
-    const idx = sequence.indexOf(current.id)
-    const start = idx >= 0 ? idx + 1 : 1
-
-    const ids: string[] = []
-
-    if (playerStateRef.current.repeatMode === "all") {
-      for (let offset = 0; offset < sequence.length - 1; offset++) {
-        const i = (start + offset) % sequence.length
-        const id = sequence[i]
-        if (id && !removed.has(id)) ids.push(id)
-      }
-      return ids
-    }
-
-    for (let i = start; i < sequence.length; i++) {
-      const id = sequence[i]
-      if (id && !removed.has(id)) ids.push(id)
-    }
-    return ids
-  }, [getSequenceIds])
-
-  const getQueueIdsDisplay = useCallback(() => {
-    const current = currentTrackRef.current
-    if (!current) return []
-
-    const sequence = getSequenceIds()
-    if (sequence.length === 0) return []
-
-    const removed = queueRemovedSetRef.current
-    const idx = sequence.indexOf(current.id)
-    const start = idx >= 0 ? idx + 1 : 1
-
-    const ids: string[] = []
-    for (let i = start; i < sequence.length; i++) {
-      const id = sequence[i]
-      if (id && !removed.has(id)) ids.push(id)
-    }
-    return ids
-  }, [getSequenceIds])
-
-  const enqueueTrackId = useCallback((trackId: string) => {
-    setManualQueueIds((prev) => [...prev, trackId])
+  const queueTrackAt = useCallback((q: PlayQueue, index: number | null) => {
+    const entry = index === null ? undefined : q.entries[index]
+    return entry ? findTrackById(entry.trackId) : null
+  }, [findTrackById])
+
+  const applyQueue = useCallback((next: PlayQueue) => {
+    queueRef.current = next
+    setQueue(next)
+    const manual = manualEntryIdsRef.current
+    const upcoming = new Set(next.entries.slice((next.current ?? -1) + 1).map((e) => e.id))
+    manual.forEach((id) => {
+      if (!upcoming.has(id)) manual.delete(id)
+    })
+    setPlayerState((prev) => {
+      const isShuffled = next.shuffle !== "off"
+      if (prev.isShuffled === isShuffled && prev.repeatMode === next.repeat) return prev
+      const state = { ...prev, isShuffled, repeatMode: next.repeat }
+      playerStateRef.current = state
+      return state
+    })
   }, [])
 
-  const removeManualQueueIndex = useCallback((index: number) => {
-    setManualQueueIds((prev) => prev.filter((_, i) => i !== index))
-  }, [])
+  const setPlaybackContext = useCallback((contextTracks: Track[], nextLabel: string, start: Track) => {
+    setContextLabel(nextLabel)
+    manualEntryIdsRef.current.clear()
+    const index = contextTracks.findIndex((t) => t.id === start.id)
+    const trackIds = (index >= 0 ? contextTracks : [start, ...contextTracks]).map((t) => t.canonicalId)
+    invoke<PlayQueue>("queue_set", { trackIds, start: Math.max(0, index) })
+      .then(applyQueue)
+      .catch((e) => console.error("Failed to set queue:", e))
+  }, [applyQueue])
 
-  const consumeManualQueueThroughId = useCallback((trackId: string) => {
-    setManualQueueIds((prev) => {
-      const idx = prev.indexOf(trackId)
-      if (idx === -1) return prev
-      return prev.slice(idx + 1)
-    })
-  }, [])
+  const handleTrackSelect = useCallback((track: Track, contextTracks: Track[], nextLabel: string) => {
+    setPlaybackContext(contextTracks, nextLabel, track)
+    playTrack(track, false);
+  }, [playTrack, setPlaybackContext]);
 
-  const getNextSpotifyQueueItem = useCallback((): { id: string; source: "manual" | "context" } | null => {
-    const manual = manualQueueIdsRef.current
-    if (manual.length > 0) return { id: manual[0], source: "manual" }
-    const ctx = getQueueIds()[0]
-    if (!ctx) return null
-    return { id: ctx, source: "context" }
-  }, [getQueueIds])
+  // When the queue runs out, keep going with tracks similar to the one that just ended.
+  const continueWithRadio = useCallback((seed: Track) => {
+    const request = ++radioRequestRef.current
+    const exclude = [...playbackHistoryRef.current, seed.id]
+      .map((id) => findTrackById(id)?.canonicalId ?? id)
+    const radioSeed: RadioSeed = { type: "track", id: seed.canonicalId }
+    invoke<Track[]>("generate_radio", { seed: radioSeed, exclude })
+      .then((radio) => {
+        if (request !== radioRequestRef.current || currentTrackRef.current !== seed || radio.length === 0) return
+        setPlaybackContext(radio, "Radio", radio[0])
+        playTrack(radio[0], false)
+      })
+      .catch(() => {})
+  }, [findTrackById, playTrack, setPlaybackContext])
 
   const handleNext = useCallback((autoOrEvent: boolean | unknown = false) => {
-    const current = currentTrackRef.current
-    if (!current) return
+    if (!currentTrackRef.current) return
     const auto = typeof autoOrEvent === "boolean" ? autoOrEvent : false
 
-    const next = getNextSpotifyQueueItem()
-    if (!next) {
-      audioRef.current.pause()
-      setPlayerState((prev) => ({ ...prev, isPlaying: false, currentTime: 0 }))
-      return
-    }
-
-    if (next.source === "manual") {
-      removeManualQueueIndex(0)
-    }
-
-    const nextTrack = findTrackById(next.id)
-    if (!nextTrack) return
-    playTrack(nextTrack, auto)
-  }, [findTrackById, getNextSpotifyQueueItem, playTrack, removeManualQueueIndex])
-
-  const handlePrevious = useCallback(() => {
-    const current = currentTrackRef.current
-    const state = playerStateRef.current
-    if (!current) return
-
-    if (state.isShuffled && playbackHistoryRef.current.length > 0) {
-      const prevId = playbackHistoryRef.current.pop()
-      if (prevId) {
-        const match = findTrackById(prevId)
-        if (match) {
-          playTrack(match, false)
+    invoke<PlayQueue>("queue_next", { ended: false })
+      .then((q) => {
+        applyQueue(q)
+        const nextTrack = queueTrackAt(q, q.current)
+        if (!nextTrack) {
+          audioRef.current.pause()
+          setPlayerState((prev) => ({ ...prev, isPlaying: false, currentTime: 0 }))
           return
         }
-      }
-    }
-
-    const sequence = getSequenceIds()
-    if (sequence.length === 0) return
+        playTrack(nextTrack, auto)
+      })
+      .catch((e) => console.error("Failed to advance queue:", e))
+  }, [applyQueue, playTrack, queueTrackAt])
 
-    const currentIndex = sequence.indexOf(current.id)
-    if (currentIndex === -1) return
+  const handlePrevious = useCallback(() => {
+    if (!currentTrackRef.current) return
 
-    let prevIndex = currentIndex - 1
-    if (prevIndex < 0) {
-      if (state.repeatMode !== "all") return
-      prevIndex = sequence.length - 1
-    }
+    invoke<PlayQueue>("queue_previous")
+      .then((q) => {
+        applyQueue(q)
+        const prevTrack = queueTrackAt(q, q.current)
+        if (prevTrack) playTrack(prevTrack, false)
+      })
+      .catch((e) => console.error("Failed to go back in queue:", e))
+  }, [applyQueue, playTrack, queueTrackAt])
+
+  const handleQueueEnded = useCallback((current: Track) => {
+    const previous = queueRef.current
+    const previousEntry = previous && previous.current !== null ? previous.entries[previous.current] : undefined
+    invoke<PlayQueue>("queue_next", { ended: true })
+      .then((q) => {
+        applyQueue(q)
+        const entry = q.current === null ? undefined : q.entries[q.current]
+        if (entry && previousEntry && entry.id === previousEntry.id) {
+          const audio = audioRef.current
+          beginPlay(current)
+          audio.currentTime = 0
+          void audio.play().catch(() => {})
+          setPlayerState((prev) => ({ ...prev, isPlaying: !audio.paused, currentTime: 0 }))
+          return
+        }
+        const nextTrack = queueTrackAt(q, q.current)
+        if (!nextTrack) {
+          setPlayerState((prev) => ({ ...prev, isPlaying: false, currentTime: 0 }))
+          if (!entry) continueWithRadio(current)
+          return
+        }
+        playTrack(nextTrack, false)
+      })
+      .catch((e) => {
+        console.error("Failed to advance queue:", e)
+        setPlayerState((prev) => ({ ...prev, isPlaying: false, currentTime: 0 }))
+      })
+  }, [applyQueue, beginPlay, continueWithRadio, playTrack, queueTrackAt])
 
-    const prevId = sequence[prevIndex]
-    if (!prevId) return
-    const prevTrack = findTrackById(prevId)
-    if (!prevTrack) return
-    playTrack(prevTrack, false)
-  }, [findTrackById, getSequenceIds, playTrack])
+  useEffect(() => {
+    queueEndedRef.current = handleQueueEnded
+  }, [handleQueueEnded])
 
   useEffect(() => {
     const audio = audioRef.current
@@ -967,42 +964,16 @@ function App() {
 
     const handleEnded = () => {
       const current = currentTrackRef.current
-      const state = playerStateRef.current
-      const play = playTrackRef.current
+      finishPlay(Infinity)
 
-      if (!current) {
+      // An "end of track/album" timer that is due stops here; its stop event follows the recorded play.
+      const timer = sleepTimerRef.current
+      const sleepDue = timer !== null && timer.mode !== "minutes" && timer.endsAt !== null
+      if (!current || !queueEndedRef.current || sleepDue) {
         setPlayerState((prev) => ({ ...prev, isPlaying: false, currentTime: 0 }))
         return
       }
-
-      if (state.repeatMode === "one") {
-        audio.currentTime = 0
-        void audio.play().catch(() => {})
-        setPlayerState((prev) => ({ ...prev, isPlaying: !audio.paused, currentTime: 0 }))
-        return
-      }
-
-      const next = getNextSpotifyQueueItem()
-      if (!next) {
-        setPlayerState((prev) => ({ ...prev, isPlaying: false, currentTime: 0 }))
-        return
-      }
-
-      if (!play) {
-        setPlayerState((prev) => ({ ...prev, isPlaying: false, currentTime: 0 }))
-        return
-      }
-
-      if (next.source === "manual") {
-        removeManualQueueIndex(0)
-      }
-
-      const nextTrack = findTrackById(next.id)
-      if (!nextTrack) {
-        setPlayerState((prev) => ({ ...prev, isPlaying: false, currentTime: 0 }))
-        return
-      }
-      play(nextTrack, false)
+      queueEndedRef.current(current)
     }
 
     const handleError = () => {
@@ -1028,6 +999,20 @@ function App() {
     }
   }, [])
 
+  useEffect(() => {
+    setChapters([])
+    if (!currentTrack || !currentTrack.chapterCount) return
+    let cancelled = false
+    invoke<Chapter[]>("get_chapters", { trackId: currentTrack.canonicalId })
+      .then((result) => {
+        if (!cancelled) setChapters(result)
+      })
+      .catch(() => {})
+    return () => {
+      cancelled = true
+    }
+  }, [currentTrack]);
+
   const handleSeek = (time: number) => {
     setPlayerState((prev) => ({ ...prev, currentTime: time }));
     audioRef.current.currentTime = time;
@@ -1037,33 +1022,105 @@ function App() {
     setPlayerState((prev) => ({ ...prev, volume }));
   };
 
+  const rampVolume = useCallback((from: number, to: number, seconds: number) => {
+    if (volumeRampRef.current !== null) clearInterval(volumeRampRef.current)
+    const steps = Math.max(1, Math.round(seconds * 4))
+    let step = 0
+    setPlayerState((prev) => ({ ...prev, volume: from }))
+    volumeRampRef.current = window.setInterval(() => {
+      step += 1
+      const volume = from + (to - from) * Math.min(1, step / steps)
+      setPlayerState((prev) => ({ ...prev, volume }))
+      if (step >= steps && volumeRampRef.current !== null) {
+        clearInterval(volumeRampRef.current)
+        volumeRampRef.current = null
+      }
+    }, 250)
+  }, []);
+
+  useEffect(() => {
+    sleepTimerRef.current = sleepTimer
+  }, [sleepTimer])
+
+  // Track-based timers learn from the time left in the playing track when to fade and stop.
+  const sleepTrackMode = sleepTimer !== null && sleepTimer.mode !== "minutes"
+  useEffect(() => {
+    if (!sleepTrackMode || !currentTrack) return
+    const report = () => {
+      const audio = audioRef.current
+      const duration = Number.isFinite(audio.duration) ? audio.duration : currentTrack.duration
+      const remaining = playerStateRef.current.isPlaying ? Math.max(0, duration - audio.currentTime) : null
+      invoke<SleepTimer | null>("sleep_timer_progress", { trackId: currentTrack.canonicalId, remaining })
+        .then((timer) => {
+          sleepTimerRef.current = timer
+          setSleepTimer(timer)
+        })
+        .catch(() => {})
+    }
+    report()
+    const interval = window.setInterval(report, 1000)
+    return () => clearInterval(interval)
+  }, [sleepTrackMode, currentTrack, playerState.isPlaying])
+
+  const handleSetSleepTimer = useCallback((mode: SleepMode | null, minutes?: number) => {
+    const request = mode
+      ? invoke<SleepTimer>("start_sleep_timer", { mode, minutes })
+      : invoke("cancel_sleep_timer").then(() => null)
+    request.then(setSleepTimer).catch((e) => console.error("Failed to set sleep timer:", e))
+  }, []);
+
+  handleAlarmRef.current = (alarm) => {
+    const context = tracksRef.current.filter((t) => t.folderId === alarm.playlistId)
+    if (context.length === 0) return
+    const label = folders.find((f) => f.id === alarm.playlistId)?.name || "Alarm"
+    setPlayerState((prev) => ({ ...prev, isMuted: false }))
+    handleTrackSelect(context[0], context, label)
+    rampVolume(0, alarm.volume, alarm.rampSeconds)
+  }
+
+  useEffect(() => {
+    const unlisteners = [
+      listen<SleepTimerEvent>("sleep-timer", ({ payload }) => {
+        if (payload.action === "fade") {
+          if (sleepVolumeRef.current === null) sleepVolumeRef.current = playerStateRef.current.volume
+          rampVolume(playerStateRef.current.volume, 0, payload.fadeSeconds)
+          return
+        }
+        if (volumeRampRef.current !== null) {
+          clearInterval(volumeRampRef.current)
+          volumeRampRef.current = null
+        }
+        radioRequestRef.current += 1
+        audioRef.current.pause()
+        const volume = sleepVolumeRef.current
+        sleepVolumeRef.current = null
+        setSleepTimer(null)
+        setPlayerState((prev) => ({ ...prev, isPlaying: false, volume: volume ?? prev.volume }))
+      }),
+      listen<AlarmEvent>("alarm", ({ payload }) => handleAlarmRef.current(payload)),
+    ]
+    return () => {
+      unlisteners.forEach((unlisten) => void unlisten.then((fn) => fn()))
+    }
+  }, [rampVolume]);
+
   const handleToggleMute = () => {
     setPlayerState((prev) => ({ ...prev, isMuted: !prev.isMuted }));
   };
 
   const handleToggleShuffle = () => {
-    setPlayerState((prev) => {
-      const nextIsShuffled = !prev.isShuffled
-      playerStateRef.current = { ...prev, isShuffled: nextIsShuffled }
-      if (nextIsShuffled) {
-        shuffleNonceRef.current += 1
-        shuffleOrderIdsRef.current = null
-        ensureShuffleOrder()
-      } else {
-        shuffleOrderIdsRef.current = null
-      }
-      return { ...prev, isShuffled: nextIsShuffled }
-    });
+    const mode = playerStateRef.current.isShuffled ? "off" : "random"
+    invoke<PlayQueue>("queue_set_shuffle", { mode })
+      .then(applyQueue)
+      .catch((e) => console.error("Failed to set shuffle:", e))
   };
 
   const handleToggleRepeat = () => {
-    setPlayerState((prev) => {
-      const modes: PlayerState["repeatMode"][] = ["off", "all", "one"];
-      const nextIndex = (modes.indexOf(prev.repeatMode) + 1) % modes.length;
-      const next = { ...prev, repeatMode: modes[nextIndex] };
-      playerStateRef.current = next
-      return next;
-    });
+    const modes: PlayerState["repeatMode"][] = ["off", "all", "one"];
+    const nextIndex = (modes.indexOf(playerStateRef.current.repeatMode) + 1) % modes.length;
+    invoke<PlayQueue>("queue_set_repeat", { mode: modes[nextIndex] })
+      .then(applyQueue)
+      .catch((e) => console.error("Failed to set repeat:", e))
   };
 
   const handleOpenSettings = useCallback(() => setSettingsOpen(true), []);
@@ -1085,7 +1142,7 @@ function App() {
         await invoke("create_folder", { name, parentPath: selectedFolder.path });
     } catch (e) {
         console.error("Failed to create folder:", e);
-        alert("Failed to create folder: " + e);
+        alert("Failed to create folder: " + errorMessage(e));
     }
   }, [selectedFolder]);
 
@@ -1120,44 +1177,60 @@ function App() {
 
   const handlePlayFromSearch = useCallback((track: Track) => {
     const context = tracksRef.current.length > 0 ? tracksRef.current : tracks
-    setPlaybackContext(context, "Library")
-    if (playerStateRef.current.isShuffled) {
-      shuffleNonceRef.current += 1
-      shuffleOrderIdsRef.current = null
-    }
+    setPlaybackContext(context, "Library", track)
     playTrack(track, false)
   }, [playTrack, setPlaybackContext, tracks])
 
-  const handleAddToQueue = useCallback((track: Track) => {
-    enqueueTrackId(track.id)
-  }, [enqueueTrackId])
-
-  const handleRemoveFromContextQueue = useCallback((trackId: string) => {
-    setQueueRemovedIds((prev) => (prev.includes(trackId) ? prev : [...prev, trackId]))
-  }, [])
-
-  const manualQueueTracks = useMemo(() => {
-    return manualQueueIds
-      .map((id) => findTrackById(id))
-      .filter((t): t is Track => !!t)
-  }, [manualQueueIds, findTrackById, tracks])
-
-  const contextQueueTracks = useMemo(() => {
-    const manualSet = new Set(manualQueueIds)
-    return getQueueIdsDisplay().filter((id) => !manualSet.has(id))
-      .map((id) => findTrackById(id))
-      .filter((t): t is Track => !!t)
-  }, [tracks, currentTrack, playerState.isShuffled, playerState.repeatMode, queueRemovedIds, manualQueueIds, getQueueIdsDisplay, findTrackById])
-
-  const handlePlayFromQueueScreen = useCallback((track: Track) => {
-    if (track.id === currentTrackRef.current?.id) return
-    consumeManualQueueThroughId(track.id)
-    if (playerStateRef.current.isShuffled) {
-      shuffleNonceRef.current += 1
-      shuffleOrderIdsRef.current = null
+  // "Add to queue" lines tracks up after the ones added before them, ahead of the rest of the context.
+  const handleAddToQueue = useCallback(async (track: Track) => {
+    try {
+      let q = await invoke<PlayQueue>("queue_enqueue", { trackIds: [track.canonicalId] })
+      const last = q.entries.length - 1
+      const manual = manualEntryIdsRef.current
+      manual.add(q.entries[last].id)
+      let target = (q.current ?? -1) + 1
+      while (target < last && manual.has(q.entries[target].id)) target++
+      if (target < last) q = await invoke<PlayQueue>("queue_move", { from: last, to: target })
+      applyQueue(q)
+    } catch (e) {
+      console.error("Failed to add to queue:", e)
     }
-    playTrack(track, false)
-  }, [consumeManualQueueThroughId, playTrack])
+  }, [applyQueue])
+
+  const handleRemoveFromQueue = useCallback((index: number) => {
+    invoke<PlayQueue>("queue_remove", { indices: [index] })
+      .then(applyQueue)
+      .catch((e) => console.error("Failed to remove from queue:", e))
+  }, [applyQueue])
+
+  const { manualQueueItems, contextQueueItems } = useMemo(() => {
+    const manualQueueItems: QueueItem[] = []
+    const contextQueueItems: QueueItem[] = []
+    if (!queue || queue.current === null) return { manualQueueItems, contextQueueItems }
+    let leading = true
+    for (let index = queue.current + 1; index < queue.entries.length; index++) {
+      const entry = queue.entries[index]
+      const track = findTrackById(entry.trackId)
+      if (!track) continue
+      if (leading && manualEntryIdsRef.current.has(entry.id)) {
+        manualQueueItems.push({ index, track })
+      } else {
+        leading = false
+        contextQueueItems.push({ index, track })
+      }
+    }
+    return { manualQueueItems, contextQueueItems }
+  }, [queue, tracks, findTrackById])
+
+  const handlePlayFromQueueScreen = useCallback((index: number) => {
+    invoke<PlayQueue>("queue_jump", { index })
+      .then((q) => {
+        applyQueue(q)
+        const track = queueTrackAt(q, q.current)
+        if (track) playTrack(track, false)
+      })
+      .catch((e) => console.error("Failed to jump in queue:", e))
+  }, [applyQueue, playTrack, queueTrackAt])
 
   const handleSelectArtist = useCallback((artist: string) => {
     if (!artist.trim()) return
@@ -1172,7 +1245,13 @@ function App() {
 
   const filteredTracks = useMemo(() => {
     const q = searchQuery ? searchQuery.toLowerCase() : ""
+    const dedupe = !!q || view === "favorites" || view === "recent"
+    const seen = new Set<string>()
     return tracks.filter((t) => {
+      if (dedupe) {
+        if (seen.has(t.canonicalId)) return false
+        seen.add(t.canonicalId)
+      }
       if (q) {
         const matches =
           t.title.toLowerCase().includes(q) ||
@@ -1258,12 +1337,11 @@ function App() {
           ) : view === "queue" ? (
             <QueueView
               currentTrack={currentTrack}
-              manualQueue={manualQueueTracks}
-              contextQueue={contextQueueTracks}
+              manualQueue={manualQueueItems}
+              contextQueue={contextQueueItems}
               contextLabel={contextLabel}
-              onPlayTrack={handlePlayFromQueueScreen}
-              onRemoveManualQueueIndex={removeManualQueueIndex}
-              onRemoveFromContextQueue={handleRemoveFromContextQueue}
+              onPlayIndex={handlePlayFromQueueScreen}
+              onRemoveIndex={handleRemoveFromQueue}
               onSelectArtist={handleSelectArtist}
               onNeedCovers={ensureCoversForAudioUrls}
             />
@@ -1294,6 +1372,9 @@ function App() {
               onToggleShuffle={handleToggleShuffle}
               onToggleRepeat={handleToggleRepeat}
               onSelectArtist={handleSelectArtist}
+              chapters={chapters}
+              sleepTimer={sleepTimer}
+              onSetSleepTimer={handleSetSleepTimer}
             />
           )}
         </main>
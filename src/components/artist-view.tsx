@@ -32,7 +32,14 @@ export const ArtistView = memo(function ArtistView({
   onToggleFavorite,
   onNeedCovers,
 }: ArtistViewProps) {
-  const artistTracks = useMemo(() => tracks.filter((t) => matchArtist(t.artist, artist)), [tracks, artist])
+  const artistTracks = useMemo(() => {
+    const seen = new Set<string>()
+    return tracks.filter((t) => {
+      if (!matchArtist(t.artist, artist) || seen.has(t.canonicalId)) return false
+      seen.add(t.canonicalId)
+      return true
+    })
+  }, [tracks, artist])
 
   const dummyFolder: MusicFolder = useMemo(
     () => ({
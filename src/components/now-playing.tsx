@@ -1,8 +1,9 @@
-import type { Track, PlayerState } from "@/lib/types"
+import type { Track, PlayerState, Chapter, SleepMode, SleepTimer } from "@/lib/types"
 import { formatTime, cn, getDisplayTitle } from "@/lib/utils"
 import { Slider } from "@/components/ui/slider"
 import { Button } from "@/components/ui/button"
 import { ArtistLinks } from "@/components/artist-links"
+import { DropdownMenu, DropdownMenuContent, DropdownMenuItem, DropdownMenuTrigger } from "@/components/ui/dropdown-menu"
 import {
   Music2,
   Play,
@@ -15,8 +16,11 @@ import {
   Volume2,
   VolumeX,
   Volume1,
+  Moon,
 } from "lucide-react"
 
+const SLEEP_MINUTES = [15, 30, 45, 60, 90]
+
 interface NowPlayingProps {
   track: Track | null
   playerState: PlayerState
@@ -29,6 +33,9 @@ interface NowPlayingProps {
   onToggleShuffle: () => void
   onToggleRepeat: () => void
   onSelectArtist: (artist: string) => void
+  chapters?: Chapter[]
+  sleepTimer?: SleepTimer | null
+  onSetSleepTimer?: (mode: SleepMode | null, minutes?: number) => void
 }
 
 export function NowPlaying({
@@ -43,11 +50,16 @@ export function NowPlaying({
   onToggleShuffle,
   onToggleRepeat,
   onSelectArtist,
+  chapters = [],
+  sleepTimer = null,
+  onSetSleepTimer,
 }: NowPlayingProps) {
   const VolumeIcon =
     playerState.isMuted || playerState.volume === 0 ? VolumeX : playerState.volume < 0.5 ? Volume1 : Volume2
   const RepeatIcon = playerState.repeatMode === "one" ? Repeat1 : Repeat
   const displayTitle = track ? getDisplayTitle(track.title, track.artist) : ""
+  const chapterIndex = chapters.reduce((found, c, i) => (c.start <= playerState.currentTime ? i : found), -1)
+  const currentChapter = chapterIndex >= 0 ? chapters[chapterIndex] : null
 
   if (!track) {
     return (
@@ -75,7 +87,7 @@ export function NowPlaying({
           />
           <div className="relative w-full h-full rounded-3xl overflow-hidden shadow-2xl bg-secondary">
             <img
-              src={track.coverUrl || "/icon.png"}
+              src={currentChapter?.image || track.coverUrl || "/icon.png"}
               alt={displayTitle || "Unknown Track"}
               className={cn(
                 "absolute inset-0 w-full h-full object-cover transition-transform duration-700",
@@ -87,7 +99,12 @@ export function NowPlaying({
 
         <h1 className="text-2xl font-semibold tracking-tight mb-1">{displayTitle}</h1>
         <ArtistLinks artist={track.artist} onSelectArtist={onSelectArtist} className="text-muted-foreground mb-1 text-sm" />
-        <p className="text-sm text-muted-foreground/70 mb-8">{track.album}</p>
+        <p className={cn("text-sm text-muted-foreground/70", currentChapter ? "mb-1" : "mb-8")}>{track.album}</p>
+        {currentChapter && (
+          <p className="text-xs text-muted-foreground mb-8">
+            Chapter {chapterIndex + 1} of {chapters.length}: {currentChapter.title}
+          </p>
+        )}
 
         <div className="space-y-2 mb-6">
           <Slider
@@ -150,7 +167,49 @@ export function NowPlaying({
             onValueChange={([value]) => onVolumeChange(value / 100)}
             className="w-32"
           />
+          {onSetSleepTimer && (
+            <DropdownMenu>
+              <DropdownMenuTrigger asChild>
+                <Button
+                  variant="ghost"
+                  size="icon"
+                  className={cn("h-9 w-9 rounded-full", sleepTimer && "text-foreground bg-secondary")}
+                  title={sleepTimer ? "Sleep timer on" : "Sleep timer"}
+                >
+                  <Moon className="w-4 h-4" />
+                </Button>
+              </DropdownMenuTrigger>
+              <DropdownMenuContent align="end">
+                {SLEEP_MINUTES.map((minutes) => (
+                  <DropdownMenuItem key={minutes} onClick={() => onSetSleepTimer("minutes", minutes)}>
+                    {minutes} minutes
+                  </DropdownMenuItem>
+                ))}
+                <DropdownMenuItem onClick={() => onSetSleepTimer("end-of-track")}>End of track</DropdownMenuItem>
+                <DropdownMenuItem onClick={() => onSetSleepTimer("end-of-album")}>End of album</DropdownMenuItem>
+                {sleepTimer && <DropdownMenuItem onClick={() => onSetSleepTimer(null)}>Turn off</DropdownMenuItem>}
+              </DropdownMenuContent>
+            </DropdownMenu>
+          )}
         </div>
+
+        {chapters.length > 0 && (
+          <div className="mt-6 max-h-48 overflow-y-auto text-left rounded-lg bg-secondary/40 p-1">
+            {chapters.map((chapter, index) => (
+              <button
+                key={`${chapter.start}-${index}`}
+                className={cn(
+                  "w-full flex items-center justify-between gap-3 px-3 py-1.5 rounded-md text-sm hover:bg-secondary",
+                  index === chapterIndex && "bg-secondary font-medium",
+                )}
+                onClick={() => onSeek(chapter.start)}
+              >
+                <span className="truncate">{chapter.title}</span>
+                <span className="text-xs text-muted-foreground tabular-nums">{formatTime(chapter.start)}</span>
+              </button>
+            ))}
+          </div>
+        )}
       </div>
     </div>
   )
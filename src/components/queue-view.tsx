@@ -8,14 +8,19 @@ import { ScrollArea } from "@/components/ui/scroll-area"
 import { ListMusic, X } from "lucide-react"
 import { ArtistLinks } from "@/components/artist-links"
 
+// `index` is the entry's position in the whole play queue.
+export interface QueueItem {
+  index: number
+  track: Track
+}
+
 interface QueueViewProps {
   currentTrack: Track | null
-  manualQueue: Track[]
-  contextQueue: Track[]
+  manualQueue: QueueItem[]
+  contextQueue: QueueItem[]
   contextLabel: string
-  onPlayTrack: (track: Track) => void
-  onRemoveManualQueueIndex: (index: number) => void
-  onRemoveFromContextQueue: (trackId: string) => void
+  onPlayIndex: (index: number) => void
+  onRemoveIndex: (index: number) => void
   onSelectArtist: (artist: string) => void
   onNeedCovers?: (audioUrls: string[]) => void
 }
@@ -25,9 +30,8 @@ export const QueueView = memo(function QueueView({
   manualQueue,
   contextQueue,
   contextLabel,
-  onPlayTrack,
-  onRemoveManualQueueIndex,
-  onRemoveFromContextQueue,
+  onPlayIndex,
+  onRemoveIndex,
   onSelectArtist,
   onNeedCovers,
 }: QueueViewProps) {
@@ -47,7 +51,7 @@ export const QueueView = memo(function QueueView({
 
   useEffect(() => {
     if (!onNeedCovers) return
-    const audioUrls = [currentTrack, ...manualQueue, ...contextQueue]
+    const audioUrls = [currentTrack, ...manualQueue.map((item) => item.track), ...contextQueue.map((item) => item.track)]
       .filter((t) => !t.coverUrl)
       .map((t) => t.audioUrl)
       .filter(Boolean)
@@ -66,9 +70,8 @@ export const QueueView = memo(function QueueView({
           <div>
             <h2 className="text-sm font-medium text-muted-foreground uppercase tracking-wider mb-3">Now Playing</h2>
             <div
-              onClick={() => onPlayTrack(currentTrack)}
               className={cn(
-                "grid grid-cols-[2rem_minmax(0,1fr)_3rem_2.5rem] md:grid-cols-[2rem_minmax(0,1fr)_12rem_3rem_2.5rem] items-center gap-4 p-3 rounded-xl cursor-pointer transition-colors hover:bg-secondary/40",
+                "grid grid-cols-[2rem_minmax(0,1fr)_3rem_2.5rem] md:grid-cols-[2rem_minmax(0,1fr)_12rem_3rem_2.5rem] items-center gap-4 p-3 rounded-xl transition-colors hover:bg-secondary/40",
               )}
             >
               <div className="w-8 flex items-center justify-center text-sm text-muted-foreground tabular-nums">
@@ -112,10 +115,10 @@ export const QueueView = memo(function QueueView({
 
           <div>
             <h2 className="text-sm font-medium text-muted-foreground uppercase tracking-wider mb-3">Next in Queue</h2>
-            {manualQueue.map((track, index) => (
+            {manualQueue.map(({ index: queueIndex, track }, index) => (
               <div
-                key={`${track.id}:${index}`}
-                onClick={() => onPlayTrack(track)}
+                key={queueIndex}
+                onClick={() => onPlayIndex(queueIndex)}
                 className={cn(
                   "grid grid-cols-[2rem_minmax(0,1fr)_3rem_2.5rem] md:grid-cols-[2rem_minmax(0,1fr)_12rem_3rem_2.5rem] items-center gap-4 p-3 rounded-xl cursor-pointer transition-colors group hover:bg-secondary/40",
                 )}
@@ -160,7 +163,7 @@ export const QueueView = memo(function QueueView({
                     onClick={(e) => {
                       e.preventDefault()
                       e.stopPropagation()
-                      onRemoveManualQueueIndex(index)
+                      onRemoveIndex(queueIndex)
                     }}
                   >
                     <X className="w-4 h-4" />
@@ -178,10 +181,10 @@ export const QueueView = memo(function QueueView({
 
           <div>
             <h2 className="text-sm font-medium text-muted-foreground uppercase tracking-wider mb-3">Next From {contextLabel}</h2>
-            {contextQueue.map((track, index) => (
+            {contextQueue.map(({ index: queueIndex, track }, index) => (
               <div
-                key={track.id}
-                onClick={() => onPlayTrack(track)}
+                key={queueIndex}
+                onClick={() => onPlayIndex(queueIndex)}
                 className={cn(
                   "grid grid-cols-[2rem_minmax(0,1fr)_3rem_2.5rem] md:grid-cols-[2rem_minmax(0,1fr)_12rem_3rem_2.5rem] items-center gap-4 p-3 rounded-xl cursor-pointer transition-colors group hover:bg-secondary/40",
                 )}
@@ -226,7 +229,7 @@ export const QueueView = memo(function QueueView({
                     onClick={(e) => {
                       e.preventDefault()
                       e.stopPropagation()
-                      onRemoveFromContextQueue(track.id)
+                      onRemoveIndex(queueIndex)
                     }}
                   >
                     <X className="w-4 h-4" />
@@ -9,6 +9,7 @@ import { Separator } from "@/components/ui/separator"
 import { Input } from "@/components/ui/input"
 import { DropdownMenu, DropdownMenuContent, DropdownMenuItem, DropdownMenuTrigger } from "@/components/ui/dropdown-menu"
 import type { NavidromeServerConfig } from "@/lib/types"
+import { errorMessage } from "@/lib/utils"
 
 interface SettingsPanelProps {
   onClose: () => void
@@ -173,7 +174,7 @@ export function SettingsPanel({
       setNavidromePassword("")
       setNavidromeApiKey("")
     } catch (e) {
-      alert("Failed to add Navidrome server: " + e)
+      alert("Failed to add Navidrome server: " + errorMessage(e))
     } finally {
       setNavidromeBusy(false)
     }
@@ -185,7 +186,7 @@ export function SettingsPanel({
     try {
       await persistNavidromeServers(next)
     } catch (e) {
-      alert("Failed to save Navidrome servers: " + e)
+      alert("Failed to save Navidrome servers: " + errorMessage(e))
     }
   }
 
@@ -195,7 +196,7 @@ export function SettingsPanel({
     try {
       await persistNavidromeServers(next)
     } catch (e) {
-      alert("Failed to save Navidrome servers: " + e)
+      alert("Failed to save Navidrome servers: " + errorMessage(e))
     }
   }
 
@@ -206,7 +207,7 @@ export function SettingsPanel({
       await invoke("navidrome_test_connection", { serverId: id })
       alert("Navidrome connection OK")
     } catch (e) {
-      alert("Navidrome connection failed: " + e)
+      alert("Navidrome connection failed: " + errorMessage(e))
     } finally {
       setNavidromeBusy(false)
     }
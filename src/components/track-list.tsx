@@ -1,6 +1,6 @@
 import { memo, useEffect, useMemo, useRef, useState } from "react"
 import type { Track, MusicFolder } from "@/lib/types"
-import { formatTime, cn, getDisplayTitle } from "@/lib/utils"
+import { formatTime, cn, errorMessage, getDisplayTitle } from "@/lib/utils"
 import { Play, MoreHorizontal, Heart, Folder, ChevronLeft, Trash2, ExternalLink, ListPlus, Library } from "lucide-react"
 import { Button } from "@/components/ui/button"
 import { ScrollArea } from "@/components/ui/scroll-area"
@@ -49,8 +49,13 @@ export const TrackList = memo(function TrackList({
   ignoreFolderFilter = false,
   onNeedCovers,
 }: TrackListProps) {
+  const trackById = useMemo(() => new Map(tracks.map((track) => [track.id, track])), [tracks])
+
   const displayedTracks = useMemo(() => {
     if (ignoreFolderFilter) return tracks
+    if (selectedFolder?.trackIds) {
+      return selectedFolder.trackIds.map((id) => trackById.get(id)).filter((track): track is Track => !!track)
+    }
     if (selectedFolder) {
       if (selectedFolder.source === "navidrome") {
         const parts = selectedFolder.id.split(":")
@@ -64,7 +69,7 @@ export const TrackList = memo(function TrackList({
       return tracks.filter((track) => track.folderId === selectedFolder.id)
     }
     return tracks.filter((track) => !track.folderId)
-  }, [ignoreFolderFilter, tracks, selectedFolder])
+  }, [ignoreFolderFilter, tracks, trackById, selectedFolder])
 
   const subFolders = useMemo(() => {
     if (ignoreFolderFilter) return []
@@ -111,17 +116,27 @@ export const TrackList = memo(function TrackList({
       if (!coverUrl) missing.add(audioUrl)
     }
 
-    for (const track of tracks) {
-      if (!track.folderId) continue
-      let current: string | null = track.folderId
+    const pushCovers = (folderId: string, track: Track) => {
+      let current: string | null = folderId
       while (current) {
         if (collageFolderIds.has(current)) pushCover(current, track.coverUrl ?? null, track.audioUrl)
         current = parentById.get(current) ?? null
       }
     }
 
+    for (const track of tracks) {
+      if (track.folderId) pushCovers(track.folderId, track)
+    }
+    for (const folder of folders) {
+      if (!folder.trackIds) continue
+      for (const id of folder.trackIds) {
+        const track = trackById.get(id)
+        if (track) pushCovers(folder.id, track)
+      }
+    }
+
     return { collageCoversByFolderId, collageMissingAudioUrls: Array.from(missing) }
-  }, [playlistCollageCovers, collageFolderIds, folders, tracks])
+  }, [playlistCollageCovers, collageFolderIds, folders, tracks, trackById])
 
   const scrollAreaRef = useRef<any>(null)
   const viewportRef = useRef<HTMLElement | null>(null)
@@ -199,6 +214,7 @@ export const TrackList = memo(function TrackList({
         className={cn(
           "flex items-center gap-4 p-3 rounded-xl cursor-pointer transition-colors group",
           isCurrentTrack ? "bg-secondary" : "hover:bg-secondary/50",
+          track.available === false && "opacity-50",
         )}
       >
         <div className="w-8 flex items-center justify-center">
@@ -299,7 +315,7 @@ export const TrackList = memo(function TrackList({
                         try {
                           await invoke("delete_track", { path: track.audioUrl })
                         } catch (err) {
-                          alert("Failed to delete file: " + err)
+                          alert("Failed to delete file: " + errorMessage(err))
                         }
                       }
                     }}
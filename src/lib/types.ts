@@ -9,6 +9,75 @@ export interface Track {
   audioUrl: string
   folderId: string | null
   source: "local" | "navidrome"
+  albumArtist?: string | null
+  trackNumber?: number | null
+  discNumber?: number | null
+  year?: number | null
+  genre?: string | null
+  composer?: string | null
+  inferredFields?: string[]
+  format?: string | null
+  playCount?: number
+  skipCount?: number
+  lastPlayed?: number | null
+  rating?: number | null
+  identity?: string | null
+  available?: boolean | null
+  chapterCount?: number
+  bpm?: number | null
+  key?: string | null
+  camelot?: string | null
+}
+
+export type RadioSeed =
+  | { type: "track"; id: string }
+  | { type: "artist"; name: string }
+  | { type: "album"; album: string; artist?: string | null }
+
+export type SleepMode = "minutes" | "end-of-track" | "end-of-album"
+
+export interface SleepTimer {
+  mode: SleepMode
+  fadeSeconds: number
+  endsAt: number | null
+}
+
+export interface SleepTimerEvent {
+  action: "fade" | "stop"
+  fadeSeconds: number
+}
+
+export interface Alarm {
+  id: string
+  enabled: boolean
+  time: string
+  days: number[]
+  playlistId: string
+  volume: number
+  rampSeconds: number
+}
+
+export interface AlarmEvent {
+  alarmId: string
+  playlistId: string
+  volume: number
+  rampSeconds: number
+}
+
+export interface Chapter {
+  title: string
+  start: number
+  end: number | null
+  image: string | null
+}
+
+export interface BookProgress {
+  folderId: string
+  trackIds: string[]
+  duration: number
+  position: number
+  currentTrackId: string | null
+  currentPosition: number
 }
 
 export interface PlayerState {
@@ -27,7 +96,8 @@ export interface MusicFolder {
   name: string
   path: string
   trackCount: number
-  source: "local" | "navidrome"
+  source: "local" | "navidrome" | "smart"
+  trackIds?: string[]
 }
 
 export interface NavidromeServerConfig {
@@ -56,4 +126,127 @@ export interface AppConfig {
   discordRichPresence?: boolean
   navidromeServers?: NavidromeServerConfig[]
   playlistCollageCovers?: boolean
+  inferFromPaths?: boolean
+  pathPatterns?: string[] | null
+  logLevel?: string | null
+  smartPlaylists?: SmartPlaylist[]
+  folderVolumes?: FolderVolume[]
+  resumeThresholdMinutes?: number
+  audiobookFolders?: string[]
+  alarms?: Alarm[]
+}
+
+export interface FolderVolume {
+  path: string
+  volumeId: string | null
+  mountPoint: string | null
+}
+
+export interface FolderStatus extends FolderVolume {
+  available: boolean
+}
+
+export interface PlayRecord {
+  trackId: string
+  startedAt: number
+  fraction: number
+  outcome: "completed" | "partial" | "skipped"
+}
+
+export interface RatingUpdate {
+  trackId: string
+  rating: number | null
+  writtenToFile: boolean
+}
+
+export interface PlayStats {
+  playCount: number
+  skipCount: number
+  lastPlayed: number | null
+}
+
+export interface LocalPlaylist {
+  id: string
+  name: string
+  createdAt: number
+  updatedAt: number
+  trackIds: string[]
+}
+
+export type RepeatMode = "off" | "one" | "all"
+
+export type ShuffleMode = "off" | "random" | "smart" | "album"
+
+export interface QueueEntry {
+  id: number
+  trackId: string
+}
+
+export interface PlayQueue {
+  entries: QueueEntry[]
+  current: number | null
+  position: number
+  repeat: RepeatMode
+  shuffle: ShuffleMode
+  unshuffled: number[]
+  nextId: number
+}
+
+export interface SmartRule {
+  field: string
+  op: string
+  value: string | number | boolean | [number, number]
+}
+
+export interface SmartPlaylist {
+  id: string
+  name: string
+  matchAll?: boolean
+  rules: SmartRule[]
+  limit?: number | null
+  order?: "random" | "title" | "artist" | "album" | "year" | "duration" | "track" | "rating" | "bpm" | "key" | null
+  descending?: boolean
+}
+
+export type CommandErrorKind =
+  | "not-found"
+  | "permission"
+  | "network"
+  | "auth"
+  | "server-error"
+  | "parse"
+  | "cancelled"
+  | "invalid-input"
+  | "path-not-allowed"
+  | "internal"
+
+export interface CommandError {
+  kind: CommandErrorKind
+  message: string
+  details: string | null
+}
+
+export interface AnalysisProgress {
+  done: number
+  total: number
+  current: string
+  analyzed: number
+}
+
+export interface AnalysisReport {
+  root: string
+  total: number
+  tagged: number
+  analyzed: number
+  cached: number
+  written: number
+  cancelled: boolean
+  failed: { path: string; error: CommandError }[]
+}
+
+export interface Waveform {
+  trackId: string
+  duration: number
+  min: number[]
+  max: number[]
 }
@@ -1,10 +1,22 @@
 import { clsx, type ClassValue } from "clsx"
 import { twMerge } from "tailwind-merge"
+import type { CommandError } from "./types"
 
 export function cn(...inputs: ClassValue[]) {
   return twMerge(clsx(inputs))
 }
 
+export function isCommandError(error: unknown): error is CommandError {
+  return typeof error === "object" && error !== null && "kind" in error && "message" in error
+}
+
+export function errorMessage(error: unknown, fallback = "Something went wrong"): string {
+  if (typeof error === "string") return error
+  if (isCommandError(error)) return error.details ? `${error.message} (${error.details})` : error.message
+  if (error instanceof Error) return error.message
+  return fallback
+}
+
 export function formatTime(seconds: number): string {
   const mins = Math.floor(seconds / 60)
   const secs = Math.floor(seconds % 60)